@@ -0,0 +1,370 @@
+use super::errors::AppError;
+use crate::core::engine::GtpEngine;
+use crate::core::entities::{Coords, StoneColor};
+use crate::core::goban::Goban;
+use gtp::{Command, Response};
+use std::collections::VecDeque;
+use std::time::Duration;
+
+pub const BUILTIN_ENGINE_NAME: &str = "gogame-term built-in (very weak)";
+
+/// Commands answered by [`BuiltinEngine`], as listed by `list_commands`
+const COMMANDS: [&str; 15] = [
+    "protocol_version",
+    "name",
+    "version",
+    "known_command",
+    "list_commands",
+    "boardsize",
+    "query_boardsize",
+    "clear_board",
+    "komi",
+    "get_komi",
+    "play",
+    "genmove",
+    "undo",
+    "list_stones",
+    "final_score",
+];
+
+/// In-process opponent for when no engine is installed. It plays random legal moves, preferring
+/// captures and saving its own groups from atari, and never fills its own eyes, so it passes
+/// once the board is settled. Scoring counts every stone as alive.
+pub struct BuiltinEngine {
+    goban: Goban,
+    komi: f32,
+    /// Position before each move, for `undo`
+    previous: Vec<Goban>,
+    responses: VecDeque<Response>,
+    rng: fastrand::Rng,
+}
+
+impl BuiltinEngine {
+    pub fn new() -> Self {
+        Self::with_rng(fastrand::Rng::new())
+    }
+
+    fn with_rng(rng: fastrand::Rng) -> Self {
+        Self {
+            goban: Goban::new(19),
+            komi: 7.5,
+            previous: vec![],
+            responses: VecDeque::new(),
+            rng,
+        }
+    }
+
+    fn execute(&mut self, line: &str) -> Result<String, String> {
+        let mut tokens = line.split_whitespace();
+        let name = tokens.next().unwrap_or("");
+        let args: Vec<&str> = tokens.collect();
+        let arg = |index: usize| args.get(index).copied().ok_or("syntax error");
+
+        match name {
+            "protocol_version" => Ok("2".to_string()),
+            "name" => Ok(BUILTIN_ENGINE_NAME.to_string()),
+            "version" => Ok(env!("CARGO_PKG_VERSION").to_string()),
+            "known_command" => Ok(COMMANDS.contains(&arg(0)?).to_string()),
+            "list_commands" => Ok(COMMANDS.join("\n")),
+            "boardsize" => {
+                let size: u8 = arg(0)?.parse().map_err(|_| "syntax error")?;
+
+                if !(2..=25).contains(&size) {
+                    return Err("unacceptable size".to_string());
+                }
+                self.goban = Goban::new(size);
+                self.previous.clear();
+                Ok(String::new())
+            }
+            "query_boardsize" => Ok(self.goban.size().to_string()),
+            "clear_board" => {
+                self.goban = Goban::new(self.goban.size());
+                self.previous.clear();
+                Ok(String::new())
+            }
+            "komi" => {
+                self.komi = arg(0)?.parse().map_err(|_| "syntax error")?;
+                Ok(String::new())
+            }
+            "get_komi" => Ok(self.komi.to_string()),
+            "play" => {
+                let color = parse_color(arg(0)?)?;
+                let vertex = arg(1)?;
+
+                if vertex.eq_ignore_ascii_case("pass") {
+                    self.pass();
+                    return Ok(String::new());
+                }
+
+                let coords = Coords::from_gtp(vertex, self.goban.size()).ok_or("illegal move")?;
+                self.play(color, coords).map_err(|_| "illegal move")?;
+                Ok(String::new())
+            }
+            "genmove" => {
+                let color = parse_color(arg(0)?)?;
+
+                match self.choose_move(color) {
+                    Some(coords) => {
+                        self.play(color, coords).map_err(|e| e.message)?;
+                        Ok(coords.to_gtp(self.goban.size()))
+                    }
+                    None => {
+                        self.pass();
+                        Ok("pass".to_string())
+                    }
+                }
+            }
+            "undo" => {
+                self.goban = self.previous.pop().ok_or("cannot undo")?;
+                Ok(String::new())
+            }
+            "list_stones" => {
+                let color = parse_color(arg(0)?)?;
+
+                Ok(self
+                    .points()
+                    .filter(|coords| self.goban.get(coords) == Some(color))
+                    .map(|coords| coords.to_gtp(self.goban.size()))
+                    .collect::<Vec<String>>()
+                    .join(" "))
+            }
+            "final_score" => Ok(format_score(area_score(&self.goban) - self.komi)),
+            "quit" => Ok(String::new()),
+            _ => Err("unknown command".to_string()),
+        }
+    }
+
+    fn play(&mut self, color: StoneColor, coords: Coords) -> Result<(), AppError> {
+        let before = self.goban.clone();
+
+        self.goban.play(color, coords)?;
+        self.previous.push(before);
+        Ok(())
+    }
+
+    fn pass(&mut self) {
+        self.previous.push(self.goban.clone());
+        self.goban.pass();
+    }
+
+    /// Random move among the best kind available: captures first, then moves that take one of
+    /// its groups out of atari, then any move that doesn't fill one of its own eyes
+    fn choose_move(&mut self, color: StoneColor) -> Option<Coords> {
+        let mut best: Vec<Coords> = vec![];
+        let mut best_priority = 0;
+
+        for coords in self.goban.legal_moves(color) {
+            let priority = self.move_priority(color, coords);
+
+            if priority > best_priority {
+                best_priority = priority;
+                best.clear();
+            }
+            if priority == best_priority && priority > 0 {
+                best.push(coords);
+            }
+        }
+
+        if best.is_empty() {
+            None
+        } else {
+            Some(best[self.rng.usize(..best.len())])
+        }
+    }
+
+    /// 0 for moves it won't play
+    fn move_priority(&self, color: StoneColor, coords: Coords) -> u8 {
+        let mut after = self.goban.clone();
+        let captures = match after.play(color, coords) {
+            Ok(captured) => !captured.is_empty(),
+            Err(_) => return 0,
+        };
+        let neighbors = self.goban.neighbors(&coords);
+
+        if captures {
+            return 3;
+        }
+
+        let saves_a_group = neighbors.iter().any(|neighbor| {
+            self.goban.get(neighbor) == Some(color) && self.goban.liberties(neighbor) == 1
+        }) && after.liberties(&coords) > 1;
+
+        if saves_a_group {
+            return 2;
+        }
+
+        let own_eye = neighbors
+            .iter()
+            .all(|neighbor| self.goban.get(neighbor) == Some(color));
+
+        if own_eye {
+            0
+        } else {
+            1
+        }
+    }
+
+    fn points(&self) -> impl Iterator<Item = Coords> {
+        let size = self.goban.size();
+
+        (1..=size).flat_map(move |row| (1..=size).map(move |col| Coords::from(row, col)))
+    }
+}
+
+impl Default for BuiltinEngine {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl GtpEngine for BuiltinEngine {
+    fn send(&mut self, cmd: Command) {
+        let response = match self.execute(cmd.to_string().trim()) {
+            Ok(text) => Response::Result((None, text)),
+            Err(text) => Response::Error((None, text)),
+        };
+
+        self.responses.push_back(response);
+    }
+
+    fn wait_response(&mut self, timeout: Duration) -> Result<Response, AppError> {
+        self.responses.pop_front().ok_or_else(|| AppError {
+            message: format!("No response from engine after {}ms", timeout.as_millis()),
+        })
+    }
+}
+
+fn parse_color(text: &str) -> Result<StoneColor, String> {
+    match text.to_lowercase().as_str() {
+        "b" | "black" => Ok(StoneColor::Black),
+        "w" | "white" => Ok(StoneColor::White),
+        _ => Err("syntax error".to_string()),
+    }
+}
+
+/// Black's area minus white's: stones plus the empty regions bordered by a single color
+fn area_score(goban: &Goban) -> f32 {
+    let size = goban.size();
+    let mut seen: Vec<Coords> = vec![];
+    let mut score: i32 = 0;
+
+    for row in 1..=size {
+        for col in 1..=size {
+            let coords = Coords::from(row, col);
+
+            match goban.get(&coords) {
+                Some(StoneColor::Black) => score += 1,
+                Some(StoneColor::White) => score -= 1,
+                None if seen.contains(&coords) => {}
+                None => {
+                    let mut region = vec![coords];
+                    let mut borders: Vec<StoneColor> = vec![];
+                    let mut pending = vec![coords];
+
+                    while let Some(current) = pending.pop() {
+                        for neighbor in goban.neighbors(&current) {
+                            match goban.get(&neighbor) {
+                                Some(color) if !borders.contains(&color) => borders.push(color),
+                                None if !region.contains(&neighbor) => {
+                                    region.push(neighbor);
+                                    pending.push(neighbor);
+                                }
+                                _ => {}
+                            }
+                        }
+                    }
+
+                    match borders.as_slice() {
+                        [StoneColor::Black] => score += region.len() as i32,
+                        [StoneColor::White] => score -= region.len() as i32,
+                        _ => {}
+                    }
+                    seen.extend(region);
+                }
+            }
+        }
+    }
+
+    score as f32
+}
+
+/// GTP score such as `B+3.5`, `0` for a draw
+fn format_score(score: f32) -> String {
+    if score > 0.0 {
+        format!("B+{}", score)
+    } else if score < 0.0 {
+        format!("W+{}", -score)
+    } else {
+        "0".to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn answer(engine: &mut BuiltinEngine, line: &str) -> Response {
+        engine.send(Command::new(line));
+        engine.wait_response(Duration::from_millis(1)).unwrap()
+    }
+
+    #[test]
+    fn genmove_plays_legal_moves_until_it_has_to_pass() {
+        let mut engine = BuiltinEngine::with_rng(fastrand::Rng::with_seed(7));
+        answer(&mut engine, "boardsize 5");
+        let mut color = StoneColor::Black;
+        let mut passed = false;
+
+        for _ in 0..200 {
+            let before = engine.goban.clone();
+            let text = match answer(&mut engine, &format!("genmove {}", color.letter())) {
+                Response::Result((_, text)) => text,
+                Response::Error((_, text)) => panic!("genmove failed: {}", text),
+            };
+
+            if text == "pass" {
+                assert!(before
+                    .legal_moves(color)
+                    .iter()
+                    .all(|coords| engine.move_priority(color, *coords) == 0));
+                passed = true;
+                break;
+            }
+            let coords = Coords::from_gtp(&text, 5).unwrap();
+            assert!(before.is_legal(color, &coords), "{} is illegal", text);
+
+            color = color.inverse();
+        }
+
+        // the board filled up instead of the game running out of moves
+        assert!(passed);
+        match answer(&mut engine, "final_score") {
+            Response::Result((_, score)) => {
+                assert!(score.starts_with('B') || score.starts_with('W'))
+            }
+            Response::Error((_, text)) => panic!("final_score failed: {}", text),
+        }
+    }
+
+    #[test]
+    fn genmove_passes_when_no_move_is_left() {
+        let mut engine = BuiltinEngine::with_rng(fastrand::Rng::with_seed(1));
+        answer(&mut engine, "boardsize 2");
+        answer(&mut engine, "play b A1");
+        answer(&mut engine, "play b B2");
+
+        // A2 and B1 are black's own eyes, and white can't play in either
+        assert_eq!(
+            Response::Result((None, "pass".to_string())),
+            answer(&mut engine, "genmove w")
+        );
+        assert_eq!(
+            Response::Result((None, "pass".to_string())),
+            answer(&mut engine, "genmove b")
+        );
+        assert_eq!(
+            Response::Error((None, "illegal move".to_string())),
+            answer(&mut engine, "play w A2")
+        );
+    }
+}
@@ -0,0 +1,163 @@
+use super::errors::AppError;
+use clap::{App, Arg, ArgGroup, ArgMatches};
+use std::io::IsTerminal;
+
+pub fn get_matches() -> ArgMatches<'static> {
+    app().get_matches()
+}
+
+pub fn app() -> App<'static, 'static> {
+    let app = App::new(env!("CARGO_PKG_NAME"))
+        .version(env!("CARGO_PKG_VERSION"))
+        .author(env!("CARGO_PKG_AUTHORS"))
+        .about(env!("CARGO_PKG_DESCRIPTION"))
+        .arg(
+            Arg::with_name("config")
+                .short("c")
+                .long("config")
+                .value_name("FILE")
+                .help("Sets a custom config file")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("debug-file")
+                .short("d")
+                .long("debug-file")
+                .value_name("Debug file")
+                .help("Output debug to a file")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("v")
+                .short("v")
+                .multiple(true)
+                .help("Sets the level of verbosity"),
+        )
+        .arg(
+            Arg::with_name("load")
+                .short("l")
+                .long("load")
+                .value_name("FILE")
+                .help("Loads an SGF game record and continues from its last position")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("sgf")
+                .long("sgf")
+                .value_name("FILE")
+                .help("Like --load, but the engine reads the file with loadsgf when it can")
+                .takes_value(true),
+        )
+        .group(ArgGroup::with_name("record").args(&["load", "sgf"]))
+        .arg(
+            Arg::with_name("boardsize")
+                .short("b")
+                .long("boardsize")
+                .value_name("SIZE")
+                .help("Board size of new games (2 to 25), overrides the config")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("komi")
+                .long("komi")
+                .value_name("POINTS")
+                .help("Komi of new games, overrides the config")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("handicap")
+                .long("handicap")
+                .value_name("STONES")
+                .help("Handicap stones of new games (2 to 9), overrides the config")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("color")
+                .long("color")
+                .value_name("COLOR")
+                .help("Color played in new games, overrides the config")
+                .possible_values(&["black", "white"])
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("two-player")
+                .long("two-player")
+                .help("Two players take turns on this terminal, the engine doesn't play"),
+        )
+        .arg(
+            Arg::with_name("continue-as")
+                .long("continue-as")
+                .value_name("COLOR")
+                .help("Color taken when continuing the loaded game, the engine plays the other")
+                .possible_values(&["black", "white", "to-move"])
+                .requires("record")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("bench-render")
+                .long("bench-render")
+                .value_name("FRAMES")
+                .help("Times FRAMES board rebuilds and prints the stats")
+                .hidden(true)
+                .takes_value(true),
+        );
+
+    #[cfg(feature = "serve")]
+    let app = app.arg(
+        Arg::with_name("serve")
+            .long("serve")
+            .value_name("ADDR")
+            .help("Serves the game status as JSON over TCP (e.g. 127.0.0.1:7878)")
+            .takes_value(true),
+    );
+
+    app
+}
+
+/// The board runs in raw mode, which needs a terminal on both ends. When piped or redirected the
+/// renderer would only fail with a generic error, so this explains what's missing instead.
+pub fn check_interactive_terminal(
+    stdin: &impl IsTerminal,
+    stdout: &impl IsTerminal,
+) -> Result<(), AppError> {
+    let not_terminals: Vec<&str> = [
+        ("stdin", stdin.is_terminal()),
+        ("stdout", stdout.is_terminal()),
+    ]
+    .iter()
+    .filter(|(_, is_terminal)| !is_terminal)
+    .map(|(name, _)| *name)
+    .collect();
+
+    if not_terminals.is_empty() {
+        Ok(())
+    } else {
+        Err(AppError {
+            message: format!(
+                "{} requires an interactive terminal, but {} {} redirected",
+                env!("CARGO_PKG_NAME"),
+                not_terminals.join(" and "),
+                if not_terminals.len() > 1 { "are" } else { "is" }
+            ),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs::File;
+
+    #[test]
+    fn redirected_stdin_is_not_interactive() {
+        let path = std::env::temp_dir().join(format!("gogame-redirect-{}", std::process::id()));
+        let stdin = File::create(&path).unwrap();
+        let stdout = File::open(&path).unwrap();
+
+        let error = check_interactive_terminal(&stdin, &stdout).unwrap_err();
+        std::fs::remove_file(&path).unwrap();
+
+        assert!(error.message.contains("requires an interactive terminal"));
+        assert!(error.message.contains("stdin and stdout"));
+    }
+}
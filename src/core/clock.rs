@@ -0,0 +1,104 @@
+use crate::core::entities::StoneColor;
+use std::time::{Duration, Instant};
+
+/// Main time left for each color, only the clock of the color to move runs. Times are given
+/// as arguments so the clock can be driven by ticks and checked in tests.
+#[derive(Debug, Clone)]
+pub struct GameClock {
+    black: Duration,
+    white: Duration,
+    /// Color whose time is running, and since when
+    running: Option<(StoneColor, Instant)>,
+}
+
+impl GameClock {
+    pub fn new(main_time: Duration) -> Self {
+        Self {
+            black: main_time,
+            white: main_time,
+            running: None,
+        }
+    }
+
+    pub fn remaining(&self, color: StoneColor, now: Instant) -> Duration {
+        let left = match color {
+            StoneColor::Black => self.black,
+            StoneColor::White => self.white,
+        };
+
+        match self.running {
+            Some((running, since)) if running == color => {
+                left.saturating_sub(now.saturating_duration_since(since))
+            }
+            _ => left,
+        }
+    }
+
+    /// Stops the running clock, charging its color for the time used, and starts `color`'s
+    pub fn start(&mut self, color: StoneColor, now: Instant) {
+        self.stop(now);
+        self.running = Some((color, now));
+    }
+
+    pub fn stop(&mut self, now: Instant) {
+        if let Some((color, _)) = self.running {
+            let left = self.remaining(color, now);
+
+            match color {
+                StoneColor::Black => self.black = left,
+                StoneColor::White => self.white = left,
+            }
+            self.running = None;
+        }
+    }
+
+    /// Color that ran out of time
+    pub fn flagged(&self, now: Instant) -> Option<StoneColor> {
+        self.running
+            .map(|(color, _)| color)
+            .filter(|color| self.remaining(*color, now) == Duration::from_secs(0))
+    }
+}
+
+/// `m:ss`, rounded up so the clock shows `0:00` only once time is over
+pub fn format_clock(remaining: Duration) -> String {
+    let secs = (remaining.as_millis() as u64).div_ceil(1000);
+
+    format!("{}:{:02}", secs / 60, secs % 60)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_the_color_to_move_runs_down() {
+        let start = Instant::now();
+        let mut clock = GameClock::new(Duration::from_secs(60));
+
+        clock.start(StoneColor::Black, start);
+        clock.start(StoneColor::White, start + Duration::from_secs(10));
+
+        let now = start + Duration::from_secs(15);
+        assert_eq!(
+            Duration::from_secs(50),
+            clock.remaining(StoneColor::Black, now)
+        );
+        assert_eq!(
+            Duration::from_secs(55),
+            clock.remaining(StoneColor::White, now)
+        );
+        assert_eq!(None, clock.flagged(now));
+        assert_eq!(
+            Some(StoneColor::White),
+            clock.flagged(start + Duration::from_secs(70))
+        );
+    }
+
+    #[test]
+    fn clocks_round_up_to_the_second() {
+        assert_eq!("1:05", format_clock(Duration::from_millis(64_200)));
+        assert_eq!("0:01", format_clock(Duration::from_millis(1)));
+        assert_eq!("0:00", format_clock(Duration::from_secs(0)));
+    }
+}
@@ -1,10 +1,11 @@
+use super::entities::StoneColor;
 use super::errors::AppError;
 use directories::ProjectDirs;
-use log::info;
+use log::{info, warn};
 use serde::Deserialize;
 use std::fs::File;
 use std::io::prelude::*;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
 #[derive(Debug, PartialEq, Deserialize)]
 pub struct AppConfig {
@@ -13,10 +14,188 @@ pub struct AppConfig {
 
     #[serde(default = "get_default_engine_config")]
     pub engine: EngineConfig,
+
+    #[serde(default = "get_default_theme_config")]
+    pub theme: ThemeConfig,
+}
+
+/// Overrides applied on top of the theme preset, anything not set is left as the preset has it
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(default)]
+pub struct ThemeConfig {
+    /// Colors, as `#rrggbb`, `#rgb`, `rgb(r, g, b)` or a basic CSS name
+    pub board_bg_color: Option<String>,
+    pub board_bg_hl_color: Option<String>,
+    pub text_fg_color: Option<String>,
+    pub intersection_star_color: Option<String>,
+    pub intersection_color: Option<String>,
+    pub white_stone_color: Option<String>,
+    pub black_stone_color: Option<String>,
+    pub error_message_fg: Option<String>,
+    pub error_message_bg: Option<String>,
+    pub loading_label_fg: Option<String>,
+    pub loading_label_bg: Option<String>,
+    pub black_territory_color: Option<String>,
+    pub white_territory_color: Option<String>,
+    pub dame_color: Option<String>,
+    pub status_idle_color: Option<String>,
+    pub status_busy_color: Option<String>,
+    pub status_error_color: Option<String>,
+    pub legal_highlight_color: Option<String>,
+    pub illegal_highlight_color: Option<String>,
+    pub disabled_fg_color: Option<String>,
+    pub hint_marker_color: Option<String>,
+    pub atari_marker_color: Option<String>,
+    pub markup_color: Option<String>,
+    pub last_move_color: Option<String>,
+    pub previous_move_color: Option<String>,
+    /// Glyphs, each drawn in a single terminal column
+    pub intersection_char: Option<String>,
+    pub intersection_star_char: Option<String>,
+    pub intersection_horiz_char: Option<String>,
+    pub white_stone_char: Option<String>,
+    pub black_stone_char: Option<String>,
+    pub dead_stone_char: Option<String>,
 }
 
 #[derive(Debug, PartialEq, Deserialize)]
-pub struct GeneralConfig {}
+pub struct GeneralConfig {
+    #[serde(default = "get_default_panel_position")]
+    pub panel_position: PanelPosition,
+    /// Draws the board without connectors between intersections, for very small terminals
+    #[serde(default = "get_default_compact_board")]
+    pub compact_board: bool,
+    /// Blank lines above the board, reduced when the terminal is too short for it
+    #[serde(default = "get_default_board_margin")]
+    pub board_margin_top: u16,
+    /// Blank columns left of the board, reduced when the terminal is too narrow for it
+    #[serde(default = "get_default_board_margin")]
+    pub board_margin_left: u16,
+    /// One of the theme presets (`classic`, `dark`, `high_contrast`, `paper`)
+    #[serde(default = "get_default_theme_preset")]
+    pub theme_preset: String,
+    /// Draws the stones as `X`/`O`, for terminals whose font lacks `●`
+    #[serde(default = "get_default_ascii_stones")]
+    pub ascii_stones: bool,
+    /// Draws the grid with `+`/`-`, for terminals that mangle box-drawing characters
+    #[serde(default = "get_default_ascii_grid")]
+    pub ascii_grid: bool,
+    /// How many of the latest moves are marked on the board: 0, 1 (the last move) or 2 (also
+    /// the opponent's previous move)
+    #[serde(default = "get_default_marked_moves")]
+    pub marked_moves: usize,
+    /// Komi sent to the engine, 6.5 by default. Set it to `~` to keep the engine's own default.
+    /// A loaded SGF's komi takes precedence.
+    #[serde(default = "get_default_komi")]
+    pub komi: Option<f32>,
+    /// Shows the color to move and the move number in the terminal title, the original title
+    /// is restored on exit
+    #[serde(default = "get_default_terminal_title")]
+    pub terminal_title: bool,
+    /// Who moves first in a game without handicap (`black` by convention)
+    #[serde(default = "get_default_first_player")]
+    pub first_player: StoneColor,
+    #[serde(default = "get_default_two_passes")]
+    pub two_passes: TwoPassesRule,
+    #[serde(default = "get_default_on_resign")]
+    pub on_resign: ResignRule,
+    /// Asks before playing the first stone on an empty board, against stray keypresses
+    #[serde(default = "get_default_confirm_first_move")]
+    pub confirm_first_move: bool,
+    /// How moves are typed, `gtp` (`D4`) or `sgf` letter pairs (`dp`). In `sgf` mode the pair
+    /// is typed after `:` (`:dp`), so letters keep working as commands.
+    #[serde(default = "get_default_input_mode")]
+    pub input_mode: InputMode,
+    /// The engine replies as soon as the player moves, otherwise it waits for `g`
+    #[serde(default = "get_default_auto_respond")]
+    pub auto_respond: bool,
+    /// Passes for the player when no legal move is left, instead of waiting for a pass
+    #[serde(default = "get_default_auto_pass")]
+    pub auto_pass: bool,
+    /// Marks the stones of groups left with a single liberty after each move
+    #[serde(default = "get_default_atari_warnings")]
+    pub atari_warnings: bool,
+    /// Shows in the panel how many moves were played since the last capture
+    #[serde(default = "get_default_show_moves_since_capture")]
+    pub show_moves_since_capture: bool,
+    /// Main time of each player in a timed game, untimed when not set. Running out of time
+    /// loses the game.
+    #[serde(default = "get_default_main_time_secs")]
+    pub main_time_secs: Option<u64>,
+    /// Below this many seconds the clock flashes
+    #[serde(default = "get_default_low_time_secs")]
+    pub low_time_secs: u64,
+    /// Points the first moves of the player and of the engine must be played on (GTP vertices
+    /// such as `D4`), for structured lessons. Moves are not restricted when empty.
+    #[serde(default = "get_default_opening_points")]
+    pub opening_points: Vec<String>,
+    /// How many of each side's moves `opening_points` applies to
+    #[serde(default = "get_default_opening_points_moves")]
+    pub opening_points_moves: usize,
+    /// Reveals the stones of a loaded game one at a time in move order, waiting this many
+    /// milliseconds between them. Loaded positions show at once when not set.
+    #[serde(default = "get_default_replay_stone_ms")]
+    pub replay_stone_ms: Option<u64>,
+    /// How many moves back undo can go, unlimited when not set
+    #[serde(default = "get_default_max_undo")]
+    pub max_undo: Option<usize>,
+    /// File the game is saved to with `w`, overwritten on every save
+    #[serde(default = "get_default_sgf_save_path")]
+    pub sgf_save_path: String,
+    /// Two people play on the same terminal, the engine only keeps the position
+    #[serde(default = "get_default_two_player")]
+    pub two_player: bool,
+}
+
+/// Engine command the position is read with
+#[derive(Debug, Copy, Clone, PartialEq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StoneSource {
+    /// `list_stones black` and `list_stones white`
+    ListStones,
+    /// The board drawn by `showboard`, for engines without `list_stones`
+    Showboard,
+}
+
+/// How typed coordinates are read
+#[derive(Debug, Copy, Clone, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum InputMode {
+    /// Column letter (skipping `I`) then row number from the bottom, as in `D4`
+    Gtp,
+    /// Two lowercase letters, column then row from the top, as in SGF files
+    Sgf,
+}
+
+/// What two consecutive passes lead to
+#[derive(Debug, Copy, Clone, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TwoPassesRule {
+    /// The engine scores the game right away
+    Score,
+    /// The game is scored once the player confirms, or goes on
+    Confirm,
+}
+
+/// What a resignation leads to
+#[derive(Debug, Copy, Clone, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ResignRule {
+    /// The game is over and no more moves are accepted
+    End,
+    /// The result is shown, but moves are still accepted to review the game
+    Review,
+}
+
+/// Where the info panel goes relative to the board
+#[derive(Debug, Copy, Clone, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PanelPosition {
+    Right,
+    Left,
+    Bottom,
+    Hidden,
+}
 
 #[derive(Debug, PartialEq, Deserialize)]
 pub struct EngineConfig {
@@ -24,6 +203,78 @@ pub struct EngineConfig {
     pub bin: String,
     #[serde(default = "get_default_engine_args")]
     pub args: Vec<String>,
+    /// How many times to wait for the startup probe before giving up
+    #[serde(default = "get_default_engine_startup_attempts")]
+    pub startup_attempts: u32,
+    #[serde(default = "get_default_engine_startup_timeout_ms")]
+    pub startup_timeout_ms: u64,
+    /// How long to wait for the answer to commands other than genmove
+    #[serde(default = "get_default_engine_default_timeout_ms")]
+    pub default_timeout_ms: u64,
+    /// How long to wait for the engine's move. Strong engines such as KataGo can think longer
+    /// than the default, and a move that arrives late fails with "Error calling command
+    /// 'genmove ...'".
+    #[serde(default = "get_default_engine_genmove_timeout_ms")]
+    pub genmove_timeout_ms: u64,
+    /// Genmove timeout for the first `opening_moves` moves, strong engines can take longer
+    /// while their search warms up
+    #[serde(default = "get_default_engine_opening_genmove_ms")]
+    pub opening_genmove_ms: u64,
+    #[serde(default = "get_default_engine_opening_moves")]
+    pub opening_moves: usize,
+    /// Random seed for reproducible games, passed as `--seed` to gnugo and Leela Zero and as a
+    /// `randSeed` override to KataGo. Other engines don't get it, and not every engine honors it
+    /// (e.g. when searching with several threads).
+    #[serde(default = "get_default_engine_seed")]
+    pub seed: Option<u64>,
+    /// Board size set on the engine for new games, the engine's current size when not set. A
+    /// loaded SGF's size takes precedence.
+    #[serde(default = "get_default_engine_board_size")]
+    pub board_size: Option<u8>,
+    /// How the position is read from the engine, `list_stones` or `showboard`
+    #[serde(default = "get_default_engine_stone_source")]
+    pub stone_source: StoneSource,
+    /// Engine specific commands sent after every `clear_board` (e.g. `clear_cache`)
+    #[serde(default = "get_default_engine_reset_commands")]
+    pub reset_commands: Vec<String>,
+    /// Color the human plays in new games, the engine plays the other and opens when the human
+    /// is white
+    #[serde(default = "get_default_engine_player_color")]
+    pub player_color: StoneColor,
+    /// Black stones the engine places with `fixed_handicap` in new games, white moves first
+    /// then. A loaded SGF has its own setup stones instead.
+    #[serde(default = "get_default_engine_handicap")]
+    pub handicap: u8,
+}
+
+impl EngineConfig {
+    /// Configured args, plus the seed in the form the engine expects
+    pub fn startup_args(&self) -> Vec<String> {
+        let mut args = self.args.clone();
+
+        if let Some(seed) = self.seed {
+            let engine_name = Path::new(&self.bin)
+                .file_stem()
+                .map(|name| name.to_string_lossy().to_lowercase())
+                .unwrap_or_default();
+
+            match engine_name.as_str() {
+                "gnugo" | "leelaz" | "leela-zero" => {
+                    args.extend(vec!["--seed".to_string(), seed.to_string()])
+                }
+                "katago" => args.extend(vec![
+                    "-override-config".to_string(),
+                    format!("randSeed={}", seed),
+                ]),
+                _ => warn!(
+                    "don't know how to pass a seed to '{}', it's not used",
+                    self.bin
+                ),
+            }
+        }
+
+        args
+    }
 }
 
 pub fn get_app_config(config_path: Option<&str>) -> Result<AppConfig, AppError> {
@@ -70,6 +321,46 @@ pub fn get_app_config(config_path: Option<&str>) -> Result<AppConfig, AppError>
     })
 }
 
+/// Sizes GTP engines can be expected to play, up to the 25 columns the vertex letters allow
+pub fn check_board_size(board_size: u8) -> Result<u8, AppError> {
+    if (2..=25).contains(&board_size) {
+        Ok(board_size)
+    } else {
+        Err(AppError {
+            message: format!("Invalid board size {}, expected 2 to 25", board_size),
+        })
+    }
+}
+
+/// Handicaps `fixed_handicap` places: 2 to 9 stones, at most 4 on 7x7 and even sized boards
+/// and none below 7x7. 0 is no handicap.
+pub fn check_handicap(handicap: u8, board_size: u8) -> Result<u8, AppError> {
+    let max = match board_size {
+        0..=6 => 0,
+        7 => 4,
+        size if size % 2 == 0 => 4,
+        _ => 9,
+    };
+
+    if handicap == 0 || (2..=max).contains(&handicap) {
+        Ok(handicap)
+    } else if max == 0 {
+        Err(AppError {
+            message: format!(
+                "Invalid handicap {}, a {}x{} board takes no handicap stones",
+                handicap, board_size, board_size
+            ),
+        })
+    } else {
+        Err(AppError {
+            message: format!(
+                "Invalid handicap {}, expected 2 to {} on a {}x{} board",
+                handicap, max, board_size, board_size
+            ),
+        })
+    }
+}
+
 fn get_default_app_config_path() -> Result<PathBuf, AppError> {
     match ProjectDirs::from("Com", "Anderson Danilo", env!("CARGO_PKG_NAME")) {
         Some(proj_dirs) => {
@@ -87,11 +378,159 @@ fn get_default_engine_config() -> EngineConfig {
     EngineConfig {
         bin: get_default_engine_bin(),
         args: get_default_engine_args(),
+        startup_attempts: get_default_engine_startup_attempts(),
+        startup_timeout_ms: get_default_engine_startup_timeout_ms(),
+        default_timeout_ms: get_default_engine_default_timeout_ms(),
+        genmove_timeout_ms: get_default_engine_genmove_timeout_ms(),
+        opening_genmove_ms: get_default_engine_opening_genmove_ms(),
+        opening_moves: get_default_engine_opening_moves(),
+        seed: get_default_engine_seed(),
+        board_size: get_default_engine_board_size(),
+        stone_source: get_default_engine_stone_source(),
+        reset_commands: get_default_engine_reset_commands(),
+        player_color: get_default_engine_player_color(),
+        handicap: get_default_engine_handicap(),
     }
 }
 
+fn get_default_theme_config() -> ThemeConfig {
+    ThemeConfig::default()
+}
+
 fn get_default_general_config() -> GeneralConfig {
-    GeneralConfig {}
+    GeneralConfig {
+        panel_position: get_default_panel_position(),
+        compact_board: get_default_compact_board(),
+        board_margin_top: get_default_board_margin(),
+        board_margin_left: get_default_board_margin(),
+        theme_preset: get_default_theme_preset(),
+        ascii_stones: get_default_ascii_stones(),
+        ascii_grid: get_default_ascii_grid(),
+        marked_moves: get_default_marked_moves(),
+        komi: get_default_komi(),
+        terminal_title: get_default_terminal_title(),
+        first_player: get_default_first_player(),
+        two_passes: get_default_two_passes(),
+        on_resign: get_default_on_resign(),
+        confirm_first_move: get_default_confirm_first_move(),
+        input_mode: get_default_input_mode(),
+        auto_respond: get_default_auto_respond(),
+        auto_pass: get_default_auto_pass(),
+        atari_warnings: get_default_atari_warnings(),
+        show_moves_since_capture: get_default_show_moves_since_capture(),
+        main_time_secs: get_default_main_time_secs(),
+        low_time_secs: get_default_low_time_secs(),
+        opening_points: get_default_opening_points(),
+        opening_points_moves: get_default_opening_points_moves(),
+        replay_stone_ms: get_default_replay_stone_ms(),
+        max_undo: get_default_max_undo(),
+        sgf_save_path: get_default_sgf_save_path(),
+        two_player: get_default_two_player(),
+    }
+}
+
+fn get_default_panel_position() -> PanelPosition {
+    PanelPosition::Right
+}
+
+fn get_default_compact_board() -> bool {
+    false
+}
+
+fn get_default_board_margin() -> u16 {
+    0
+}
+
+fn get_default_theme_preset() -> String {
+    "classic".to_string()
+}
+
+fn get_default_ascii_stones() -> bool {
+    false
+}
+
+fn get_default_ascii_grid() -> bool {
+    false
+}
+
+fn get_default_marked_moves() -> usize {
+    1
+}
+
+fn get_default_komi() -> Option<f32> {
+    Some(6.5)
+}
+
+fn get_default_terminal_title() -> bool {
+    false
+}
+
+fn get_default_first_player() -> StoneColor {
+    StoneColor::Black
+}
+
+fn get_default_two_passes() -> TwoPassesRule {
+    TwoPassesRule::Confirm
+}
+
+fn get_default_on_resign() -> ResignRule {
+    ResignRule::End
+}
+
+fn get_default_confirm_first_move() -> bool {
+    false
+}
+
+fn get_default_input_mode() -> InputMode {
+    InputMode::Gtp
+}
+
+fn get_default_auto_respond() -> bool {
+    true
+}
+
+fn get_default_auto_pass() -> bool {
+    false
+}
+
+fn get_default_atari_warnings() -> bool {
+    false
+}
+
+fn get_default_show_moves_since_capture() -> bool {
+    false
+}
+
+fn get_default_main_time_secs() -> Option<u64> {
+    None
+}
+
+fn get_default_low_time_secs() -> u64 {
+    30
+}
+
+fn get_default_opening_points() -> Vec<String> {
+    vec![]
+}
+
+fn get_default_opening_points_moves() -> usize {
+    1
+}
+
+fn get_default_replay_stone_ms() -> Option<u64> {
+    None
+}
+
+fn get_default_max_undo() -> Option<usize> {
+    None
+}
+
+fn get_default_sgf_save_path() -> String {
+    "gogame.sgf".to_string()
+}
+
+fn get_default_two_player() -> bool {
+    false
 }
 
 fn get_default_engine_bin() -> String {
@@ -101,3 +540,152 @@ fn get_default_engine_bin() -> String {
 fn get_default_engine_args() -> Vec<String> {
     vec![]
 }
+
+fn get_default_engine_startup_attempts() -> u32 {
+    10
+}
+
+fn get_default_engine_startup_timeout_ms() -> u64 {
+    1000
+}
+
+fn get_default_engine_default_timeout_ms() -> u64 {
+    100
+}
+
+fn get_default_engine_genmove_timeout_ms() -> u64 {
+    2000
+}
+
+fn get_default_engine_opening_genmove_ms() -> u64 {
+    10000
+}
+
+fn get_default_engine_opening_moves() -> usize {
+    6
+}
+
+fn get_default_engine_seed() -> Option<u64> {
+    None
+}
+
+fn get_default_engine_board_size() -> Option<u8> {
+    None
+}
+
+fn get_default_engine_stone_source() -> StoneSource {
+    StoneSource::ListStones
+}
+
+fn get_default_engine_reset_commands() -> Vec<String> {
+    vec![]
+}
+
+fn get_default_engine_player_color() -> StoneColor {
+    StoneColor::Black
+}
+
+fn get_default_engine_handicap() -> u8 {
+    0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn theme_overrides_are_read_from_theme_section() {
+        let config: AppConfig =
+            serde_yaml::from_str("theme:\n  board_bg_color: '#202020'\n  black_stone_char: '@'")
+                .unwrap();
+
+        assert_eq!(Some("#202020".to_string()), config.theme.board_bg_color);
+        assert_eq!(Some("@".to_string()), config.theme.black_stone_char);
+        assert_eq!(None, config.theme.white_stone_char);
+    }
+
+    #[test]
+    fn panel_position_defaults_to_right() {
+        let config: AppConfig = serde_yaml::from_str("empty: true").unwrap();
+
+        assert_eq!(PanelPosition::Right, config.general.panel_position);
+    }
+
+    #[test]
+    fn panel_position_is_read_from_general_section() {
+        for (value, position) in [
+            ("right", PanelPosition::Right),
+            ("left", PanelPosition::Left),
+            ("bottom", PanelPosition::Bottom),
+            ("hidden", PanelPosition::Hidden),
+        ] {
+            let config: AppConfig =
+                serde_yaml::from_str(&format!("general:\n  panel_position: {}", value)).unwrap();
+
+            assert_eq!(position, config.general.panel_position);
+        }
+    }
+
+    #[test]
+    fn black_moves_first_unless_configured() {
+        let config: AppConfig = serde_yaml::from_str("empty: true").unwrap();
+        assert_eq!(StoneColor::Black, config.general.first_player);
+
+        let config: AppConfig = serde_yaml::from_str("general:\n  first_player: white").unwrap();
+        assert_eq!(StoneColor::White, config.general.first_player);
+    }
+
+    #[test]
+    fn end_rules_default_to_confirmed_scoring_and_ending_on_resign() {
+        let config: AppConfig = serde_yaml::from_str("empty: true").unwrap();
+        assert_eq!(TwoPassesRule::Confirm, config.general.two_passes);
+        assert_eq!(ResignRule::End, config.general.on_resign);
+
+        let config: AppConfig =
+            serde_yaml::from_str("general:\n  two_passes: score\n  on_resign: review").unwrap();
+        assert_eq!(TwoPassesRule::Score, config.general.two_passes);
+        assert_eq!(ResignRule::Review, config.general.on_resign);
+    }
+
+    #[test]
+    fn seed_is_passed_the_way_each_engine_expects() {
+        let startup_args = |yaml: &str| {
+            let config: AppConfig = serde_yaml::from_str(yaml).unwrap();
+            config.engine.startup_args()
+        };
+
+        assert_eq!(
+            vec!["--level", "1", "--seed", "42"],
+            startup_args("engine:\n  args: [--level, '1']\n  seed: 42")
+        );
+        assert_eq!(
+            vec!["-override-config", "randSeed=42"],
+            startup_args("engine:\n  bin: /opt/katago/katago\n  seed: 42")
+        );
+        assert!(startup_args("engine:\n  bin: pachi\n  seed: 42").is_empty());
+        assert!(startup_args("empty: true").is_empty());
+    }
+
+    #[test]
+    fn handicap_fits_the_board_size() {
+        assert_eq!(9, check_handicap(9, 19).unwrap());
+        assert_eq!(0, check_handicap(0, 5).unwrap());
+        assert_eq!(
+            "Invalid handicap 5, expected 2 to 4 on a 10x10 board",
+            check_handicap(5, 10).unwrap_err().message
+        );
+        assert!(check_handicap(1, 19).is_err());
+        assert!(check_handicap(2, 5).is_err());
+    }
+
+    #[test]
+    fn engine_timeouts_default_to_100ms_and_2s() {
+        let config: AppConfig = serde_yaml::from_str("empty: true").unwrap();
+        assert_eq!(100, config.engine.default_timeout_ms);
+        assert_eq!(2000, config.engine.genmove_timeout_ms);
+
+        let config: AppConfig =
+            serde_yaml::from_str("engine:\n  genmove_timeout_ms: 30000").unwrap();
+        assert_eq!(30000, config.engine.genmove_timeout_ms);
+    }
+}
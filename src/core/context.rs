@@ -0,0 +1,267 @@
+use super::errors::AppError;
+use crate::core::config::{self, AppConfig};
+use crate::core::entities::StoneColor;
+use crate::core::sgf::{self, SgfGame};
+#[cfg(feature = "serve")]
+use crate::core::status_server::StatusServer;
+use crate::core::theme::Theme;
+use clap::ArgMatches;
+#[cfg(feature = "serve")]
+use std::sync::Mutex;
+use std::sync::OnceLock;
+
+static CONTEXT: OnceLock<AppContext> = OnceLock::new();
+
+/// What the game is started with, read once from the command line and the config file. The UI
+/// can't be handed arguments when it starts, so `main` installs the context for it to pick up.
+#[derive(Debug)]
+pub struct AppContext {
+    pub app_config: AppConfig,
+    /// SGF game record to continue, read before the UI starts so a bad file is reported
+    pub load: Option<SgfGame>,
+    /// Where `load` was read from when the engine should read it too, with `--sgf`
+    pub sgf_path: Option<String>,
+    /// `black`, `white` or `to-move`, only with `load`
+    pub continue_as: Option<String>,
+    /// Bound before the UI starts so a busy port is reported like any other bad argument, and
+    /// taken by the game when it starts
+    #[cfg(feature = "serve")]
+    pub status_server: Mutex<Option<StatusServer>>,
+}
+
+impl AppContext {
+    pub fn from_matches(matches: &ArgMatches) -> Result<Self, AppError> {
+        let mut app_config = config::get_app_config(matches.value_of("config"))?;
+
+        if let Some(board_size) = matches.value_of("boardsize") {
+            app_config.engine.board_size = Some(board_size.parse().map_err(|_| AppError {
+                message: format!("Invalid board size '{}'", board_size),
+            })?);
+        }
+        if let Some(komi) = matches.value_of("komi") {
+            app_config.general.komi = Some(komi.parse().map_err(|_| AppError {
+                message: format!("Invalid komi '{}'", komi),
+            })?);
+        }
+        if let Some(handicap) = matches.value_of("handicap") {
+            app_config.engine.handicap = handicap.parse().map_err(|_| AppError {
+                message: format!("Invalid handicap '{}'", handicap),
+            })?;
+        }
+        if let Some(color) = matches.value_of("color") {
+            app_config.engine.player_color = match color {
+                "white" => StoneColor::White,
+                _ => StoneColor::Black,
+            };
+        }
+        if matches.is_present("two-player") {
+            app_config.general.two_player = true;
+        }
+        if let Some(board_size) = app_config.engine.board_size {
+            config::check_board_size(board_size)?;
+            config::check_handicap(app_config.engine.handicap, board_size)?;
+        }
+        // reported before the UI starts, rather than when the theme is built
+        Theme::preset(&app_config.general.theme_preset)?.with_overrides(&app_config.theme)?;
+
+        Ok(Self {
+            app_config,
+            load: matches
+                .value_of("load")
+                .or_else(|| matches.value_of("sgf"))
+                .map(sgf::read_sgf_file)
+                .transpose()?,
+            sgf_path: matches.value_of("sgf").map(String::from),
+            continue_as: matches.value_of("continue-as").map(String::from),
+            #[cfg(feature = "serve")]
+            status_server: Mutex::new(
+                matches
+                    .value_of("serve")
+                    .map(StatusServer::bind)
+                    .transpose()?,
+            ),
+        })
+    }
+
+    pub fn install(context: AppContext) {
+        let _ = CONTEXT.set(context);
+    }
+
+    /// The installed context, `main` does it before the UI starts
+    pub fn get() -> &'static AppContext {
+        CONTEXT
+            .get()
+            .expect("the app context is installed before the UI starts")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::cli;
+
+    #[test]
+    fn options_are_read_from_the_command_line_and_the_config() {
+        let path = std::env::temp_dir().join(format!("gogame-load-{}.sgf", std::process::id()));
+        std::fs::write(&path, "(;SZ[9];B[ee])").unwrap();
+        let matches = cli::app().get_matches_from(vec![
+            "gogame-term",
+            "--config",
+            "/nonexistent/gogame-term.yml",
+            "--load",
+            path.to_str().unwrap(),
+            "--continue-as",
+            "white",
+        ]);
+
+        let context = AppContext::from_matches(&matches).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(Some(9), context.load.map(|sgf_game| sgf_game.board_size));
+        assert_eq!(Some("white".to_string()), context.continue_as);
+        assert_eq!(StoneColor::Black, context.app_config.general.first_player);
+        assert_eq!(Some(6.5), context.app_config.general.komi);
+    }
+
+    #[test]
+    fn sgf_flag_loads_the_record_and_keeps_its_path_for_the_engine() {
+        let path = std::env::temp_dir().join(format!("gogame-sgf-{}.sgf", std::process::id()));
+        std::fs::write(&path, "(;SZ[13];B[ee])").unwrap();
+        let args = |flag| {
+            vec![
+                "gogame-term",
+                "--config",
+                "/nonexistent/gogame-term.yml",
+                flag,
+                path.to_str().unwrap(),
+            ]
+        };
+
+        let context =
+            AppContext::from_matches(&cli::app().get_matches_from(args("--sgf"))).unwrap();
+        let loaded = AppContext::from_matches(&cli::app().get_matches_from(args("--load")));
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(Some(13), context.load.map(|sgf_game| sgf_game.board_size));
+        assert_eq!(path.to_str().map(String::from), context.sgf_path);
+        assert_eq!(None, loaded.unwrap().sgf_path);
+    }
+
+    #[test]
+    fn komi_flag_overrides_the_config() {
+        let matches = cli::app().get_matches_from(vec![
+            "gogame-term",
+            "--config",
+            "/nonexistent/gogame-term.yml",
+            "--komi",
+            "0.5",
+        ]);
+
+        let context = AppContext::from_matches(&matches).unwrap();
+
+        assert_eq!(Some(0.5), context.app_config.general.komi);
+    }
+
+    #[test]
+    fn unreadable_game_records_are_reported() {
+        let matches = cli::app().get_matches_from(vec![
+            "gogame-term",
+            "--config",
+            "/nonexistent/gogame-term.yml",
+            "--load",
+            "/nonexistent/game.sgf",
+        ]);
+
+        let app_error = AppContext::from_matches(&matches).unwrap_err();
+
+        assert!(app_error
+            .message
+            .starts_with("Error reading the file '/nonexistent/game.sgf'"));
+    }
+
+    #[test]
+    fn unknown_theme_presets_are_reported() {
+        let path = std::env::temp_dir().join(format!("gogame-preset-{}.yml", std::process::id()));
+        std::fs::write(&path, "general:\n  theme_preset: neon\n").unwrap();
+        let matches =
+            cli::app().get_matches_from(vec!["gogame-term", "--config", path.to_str().unwrap()]);
+
+        let app_error = AppContext::from_matches(&matches).unwrap_err();
+        std::fs::remove_file(&path).unwrap();
+
+        assert!(app_error.message.starts_with("Unknown theme preset 'neon'"));
+    }
+
+    #[test]
+    fn color_flag_overrides_the_config() {
+        let matches = cli::app().get_matches_from(vec![
+            "gogame-term",
+            "--config",
+            "/nonexistent/gogame-term.yml",
+            "--color",
+            "white",
+        ]);
+
+        let context = AppContext::from_matches(&matches).unwrap();
+
+        assert_eq!(StoneColor::White, context.app_config.engine.player_color);
+    }
+
+    #[test]
+    fn two_player_flag_overrides_the_config() {
+        let matches = cli::app().get_matches_from(vec![
+            "gogame-term",
+            "--config",
+            "/nonexistent/gogame-term.yml",
+            "--two-player",
+        ]);
+
+        let context = AppContext::from_matches(&matches).unwrap();
+
+        assert!(context.app_config.general.two_player);
+    }
+
+    #[test]
+    fn handicap_is_checked_against_the_configured_board_size() {
+        let context = |args: &[&str]| {
+            let mut all_args = vec!["gogame-term", "--config", "/nonexistent/gogame-term.yml"];
+            all_args.extend(args);
+            AppContext::from_matches(&cli::app().get_matches_from(all_args))
+        };
+
+        assert_eq!(
+            4,
+            context(&["--handicap", "4"])
+                .unwrap()
+                .app_config
+                .engine
+                .handicap
+        );
+        assert_eq!(
+            "Invalid handicap 6, expected 2 to 4 on a 10x10 board",
+            context(&["--handicap", "6", "-b", "10"])
+                .unwrap_err()
+                .message
+        );
+    }
+
+    #[test]
+    fn board_size_must_be_between_2_and_25() {
+        let context = |board_size: &str| {
+            AppContext::from_matches(&cli::app().get_matches_from(vec![
+                "gogame-term",
+                "--config",
+                "/nonexistent/gogame-term.yml",
+                "-b",
+                board_size,
+            ]))
+        };
+
+        assert_eq!(Some(9), context("9").unwrap().app_config.engine.board_size);
+        assert_eq!(
+            "Invalid board size 26, expected 2 to 25",
+            context("26").unwrap_err().message
+        );
+        assert!(context("nine").is_err());
+    }
+}
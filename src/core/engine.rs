@@ -1,19 +1,72 @@
 use super::errors::AppError;
-use crate::core::entities::{Coords, Stone, StoneColor};
-use gtp::{controller, Command, Entity, EntityBuilder, Response};
+use crate::core::builtin_engine::BuiltinEngine;
+use crate::core::config::StoneSource;
+use crate::core::entities::{Coords, GroupStatus, ScoreResult, Stone, StoneColor, Territory};
+use crate::core::process_engine::ProcessEngine;
+use crate::core::sgf::SgfGame;
+use gtp::{Command, Entity, EntityBuilder, Response};
 use log::{debug, warn};
-use std::thread;
 use std::time::{Duration, Instant};
 
+pub trait GtpEngine: Send {
+    fn send(&mut self, cmd: Command);
+    fn wait_response(&mut self, timeout: Duration) -> Result<Response, AppError>;
+
+    /// Last lines the engine wrote to stderr, used to explain why it failed
+    fn stderr_tail(&self) -> Vec<String> {
+        vec![]
+    }
+
+    /// `false` once the engine can't answer anymore, such as a process that exited
+    fn is_alive(&mut self) -> bool {
+        true
+    }
+}
+
+/// Starts a new engine in place of one that died
+type Restarter = Box<dyn Fn() -> Result<Box<dyn GtpEngine>, AppError> + Send>;
+
+/// What the engine told about itself once it started
+#[derive(Debug, Clone, Default)]
+pub struct EngineInfo {
+    /// `None` when the engine can't tell
+    pub komi: Option<f32>,
+    /// Name and version such as `GNU Go 3.8`, `None` when the engine doesn't tell its name
+    pub name: Option<String>,
+    /// Answer to `list_commands`, `None` when the engine doesn't implement it
+    pub commands: Option<Vec<String>>,
+}
+
+impl EngineInfo {
+    /// Commands are assumed supported when the engine can't list them
+    pub fn supports(&self, command: &str) -> bool {
+        match &self.commands {
+            Some(commands) => commands.iter().any(|c| c == command),
+            None => true,
+        }
+    }
+}
+
+/// Commands of optional features, asked with `known_command` when the engine can't list its
+/// commands
+const PROBED_COMMANDS: [&str; 3] = ["undo", "final_score", "final_status_list"];
+
 pub struct Engine {
-    gtp_engine: gtp::controller::Engine,
+    gtp_engine: Box<dyn GtpEngine>,
     default_timeout: Duration,
     genmove_timeout: Duration,
+    /// Longer genmove timeout for the first moves, while strong engines warm up their search
+    opening_genmove_timeout: Duration,
+    opening_moves: usize,
+    stone_source: StoneSource,
+    /// `None` for engines that can't be restarted
+    restarter: Option<Restarter>,
 }
 
 struct ResponseWrapper {
     cmd_name: String,
     response: gtp::Response,
+    elapsed: Duration,
 }
 
 enum ExpectedEntity {
@@ -26,6 +79,26 @@ pub enum GenMoveResponse {
     Pass,
 }
 
+/// Answer to `play`, a move refused by the engine isn't a failed exchange
+#[derive(Debug, PartialEq)]
+pub enum PlayResponse {
+    Played,
+    /// The engine's reason, such as `illegal move`
+    Illegal(String),
+}
+
+/// Longest part of a response quoted in an error, `list_stones` can be a whole board
+const MAX_QUOTED_RESPONSE_CHARS: usize = 80;
+
+fn truncate_response(text: &str) -> String {
+    if text.chars().count() > MAX_QUOTED_RESPONSE_CHARS {
+        let quoted: String = text.chars().take(MAX_QUOTED_RESPONSE_CHARS).collect();
+        format!("{}...", quoted)
+    } else {
+        text.to_string()
+    }
+}
+
 impl ResponseWrapper {
     fn success_text(&self) -> Result<String, AppError> {
         match &self.response {
@@ -60,7 +133,11 @@ impl ResponseWrapper {
                     ep
                 })
                 .map_err(|_| {
-                    let err_msg = format!("cmd '{}' returned '{}'", &self.cmd_name, &text);
+                    let err_msg = format!(
+                        "cmd '{}' returned a response that can't be parsed: '{}'",
+                        &self.cmd_name,
+                        truncate_response(text)
+                    );
 
                     warn!("{}", &err_msg);
 
@@ -69,6 +146,21 @@ impl ResponseWrapper {
         }
     }
 
+    fn success_coords_list(&self) -> Result<Vec<Coords>, AppError> {
+        self.success_entities(ExpectedEntity::Vertex)?
+            .iter()
+            .map(|entity| match entity {
+                Entity::Vertex((col, row)) => Ok(Coords {
+                    col: *col as u8,
+                    row: *row as u8,
+                }),
+                _ => Err(AppError {
+                    message: "Failed to parse vertex entity".to_string(),
+                }),
+            })
+            .collect()
+    }
+
     fn success_entity(&self, expected: ExpectedEntity) -> Result<Entity, AppError> {
         let mut entities = self.success_entities(expected)?;
 
@@ -97,19 +189,110 @@ impl Engine {
         let mut args: Vec<String> = vec!["--mode".to_string(), "gtp".to_string()];
         args.append(&mut additional_args.clone());
 
-        let str_args: Vec<&str> = args.iter().map(|s| s as &str).collect();
-        let default_timeout = Duration::from_millis(100);
-        let genmove_timeout = Duration::from_millis(2000);
-        let mut gtp_engine = controller::Engine::new(bin_path, &str_args);
+        let gtp_engine = ProcessEngine::start(bin_path, &args)?;
+        let bin_path = bin_path.to_string();
 
-        gtp_engine.start().map_err(|_| AppError {
-            message: format!("Error starting engine '{}'", &bin_path),
-        })?;
         Ok(Self {
+            restarter: Some(Box::new(move || {
+                Ok(Box::new(ProcessEngine::start(&bin_path, &args)?) as Box<dyn GtpEngine>)
+            })),
+            ..Self::with_gtp_engine(Box::new(gtp_engine))
+        })
+    }
+
+    /// The very weak opponent played in-process, for when no engine can be started
+    pub fn builtin() -> Self {
+        Self::with_gtp_engine(Box::new(BuiltinEngine::new()))
+    }
+
+    fn with_gtp_engine(gtp_engine: Box<dyn GtpEngine>) -> Self {
+        let genmove_timeout = Duration::from_millis(2000);
+
+        Self {
             gtp_engine,
-            default_timeout,
+            default_timeout: Duration::from_millis(100),
             genmove_timeout,
+            opening_genmove_timeout: genmove_timeout,
+            opening_moves: 0,
+            stone_source: StoneSource::ListStones,
+            restarter: None,
+        }
+    }
+
+    /// `genmove` also applies to the opening moves, unless
+    /// [`with_opening_genmove_timeout`](Self::with_opening_genmove_timeout) follows
+    pub fn with_timeouts(mut self, default: Duration, genmove: Duration) -> Self {
+        self.default_timeout = default;
+        self.genmove_timeout = genmove;
+        self.opening_genmove_timeout = genmove;
+        self
+    }
+
+    /// Uses `timeout` for genmove on moves 1 to `moves`
+    pub fn with_opening_genmove_timeout(mut self, timeout: Duration, moves: usize) -> Self {
+        self.opening_genmove_timeout = timeout;
+        self.opening_moves = moves;
+        self
+    }
+
+    pub fn with_stone_source(mut self, stone_source: StoneSource) -> Self {
+        self.stone_source = stone_source;
+        self
+    }
+
+    fn genmove_timeout(&self, move_number: usize) -> Duration {
+        if move_number <= self.opening_moves {
+            self.opening_genmove_timeout
+        } else {
+            self.genmove_timeout
+        }
+    }
+
+    pub fn is_alive(&mut self) -> bool {
+        self.gtp_engine.is_alive()
+    }
+
+    /// Starts the engine again after it died. The new one is on an empty board, the position
+    /// has to be set up again.
+    pub fn restart(&mut self, attempts: u32, timeout: Duration) -> Result<(), AppError> {
+        let restarter = self.restarter.as_ref().ok_or_else(|| AppError {
+            message: "The engine can't be restarted".to_string(),
+        })?;
+
+        self.gtp_engine = restarter()?;
+        self.wait_until_ready(attempts, timeout)
+    }
+
+    /// Sends `protocol_version` once and keeps waiting for the answer, since slow engines may
+    /// still be initializing when the first command arrives. The probe is not resent on retry,
+    /// otherwise the late answers would be read as responses to the following commands.
+    pub fn wait_until_ready(&mut self, attempts: u32, timeout: Duration) -> Result<(), AppError> {
+        let start_instant = Instant::now();
+        let gtp_engine = &mut self.gtp_engine;
+
+        gtp_engine.send(Command::cmd("protocol_version", |e| e));
+
+        let response = retry(attempts, |attempt| {
+            debug!("EngineActor [wait_until_ready]: attempt {}", attempt);
+            gtp_engine.wait_response(timeout)
         })
+        .map_err(|_| AppError {
+            message: format!(
+                "Engine not ready after {}ms ({} attempts){}",
+                start_instant.elapsed().as_millis(),
+                attempts,
+                stderr_suffix(gtp_engine.stderr_tail())
+            ),
+        })?;
+
+        ResponseWrapper {
+            cmd_name: "protocol_version".to_string(),
+            response,
+            elapsed: start_instant.elapsed(),
+        }
+        .success_text()?;
+
+        Ok(())
     }
 
     fn parser_stone_with_color(
@@ -139,7 +322,93 @@ impl Engine {
         })
     }
 
+    pub fn list_commands(&mut self) -> Result<Vec<String>, AppError> {
+        let resp = self.send_and_await("list_commands", |e| e, self.default_timeout)?;
+
+        Ok(resp
+            .success_text()?
+            .lines()
+            .map(|line| line.trim().to_string())
+            .filter(|line| !line.is_empty())
+            .collect())
+    }
+
+    pub fn known_command(&mut self, command: &str) -> Result<bool, AppError> {
+        let resp = self.send_and_await(
+            &format!("known_command {}", command),
+            |e| e,
+            self.default_timeout,
+        )?;
+
+        Ok(resp.success_text()?.trim() == "true")
+    }
+
+    /// Answer to `list_commands`, or else the [`PROBED_COMMANDS`] the engine knows. `None` when
+    /// it answers neither.
+    pub fn supported_commands(&mut self) -> Option<Vec<String>> {
+        if let Ok(commands) = self.list_commands() {
+            return Some(commands);
+        }
+
+        let mut commands = vec![];
+        for command in PROBED_COMMANDS {
+            if self.known_command(command).ok()? {
+                commands.push(command.to_string());
+            }
+        }
+
+        Some(commands)
+    }
+
+    pub fn name(&mut self) -> Result<String, AppError> {
+        let resp = self.send_and_await("name", |e| e, self.default_timeout)?;
+
+        Ok(resp.success_text()?.trim().to_string())
+    }
+
+    pub fn version(&mut self) -> Result<String, AppError> {
+        let resp = self.send_and_await("version", |e| e, self.default_timeout)?;
+
+        Ok(resp.success_text()?.trim().to_string())
+    }
+
+    /// Name followed by the version when the engine has one, to tell which engine a wrapper
+    /// script started
+    pub fn identity(&mut self) -> Option<String> {
+        let name = self.name().ok().filter(|name| !name.is_empty())?;
+
+        match self.version() {
+            Ok(version) if !version.is_empty() => Some(format!("{} {}", name, version)),
+            _ => Some(name),
+        }
+    }
+
+    /// `get_komi` is not in the GTP spec, but gnugo and KataGo answer it
+    pub fn query_komi(&mut self) -> Result<f32, AppError> {
+        let resp = self.send_and_await("get_komi", |e| e, self.default_timeout)?;
+
+        let text: String = resp.success_text()?;
+
+        text.parse().map_err(|_| AppError {
+            message: format!("invalid komi: {}", text),
+        })
+    }
+
     pub fn play(&mut self, color: StoneColor, position: Coords) -> Result<(), AppError> {
+        match self.try_play(color, position)? {
+            PlayResponse::Played => Ok(()),
+            PlayResponse::Illegal(reason) => Err(AppError {
+                message: format!("cmd 'play' returned '{}'", reason),
+            }),
+        }
+    }
+
+    /// Like [`play`](Self::play), telling a refused move (occupied point, suicide, ko) apart
+    pub fn try_play(
+        &mut self,
+        color: StoneColor,
+        position: Coords,
+    ) -> Result<PlayResponse, AppError> {
         debug!("EngineActor [play-message]: started");
         let resp = self.send_and_await(
             "play",
@@ -154,12 +423,164 @@ impl Engine {
             self.default_timeout,
         )?;
 
-        resp.success_text()?;
+        if let Response::Error((_, reason)) = &resp.response {
+            warn!("cmd 'play' returned '{}'", reason);
+            return Ok(PlayResponse::Illegal(reason.clone()));
+        }
 
         debug!("EngineActor [play-message]: finished");
+        Ok(PlayResponse::Played)
+    }
+
+    pub fn undo(&mut self) -> Result<(), AppError> {
+        let resp = self.send_and_await("undo", |e| e, self.default_timeout)?;
+
+        resp.success_text()?;
+        Ok(())
+    }
+
+    /// Asks the engine to exit, without waiting long since it's only used on the way out
+    pub fn quit(&mut self) {
+        if let Err(app_error) = self.send_and_await("quit", |e| e, self.default_timeout) {
+            warn!("engine didn't acknowledge quit: {}", app_error.message);
+        }
+    }
+
+    pub fn clear_board(&mut self) -> Result<(), AppError> {
+        let resp = self.send_and_await("clear_board", |e| e, self.default_timeout)?;
+
+        resp.success_text()?;
+        Ok(())
+    }
+
+    /// Clears the board and then sends the configured reset commands in order. Engines that don't
+    /// know a reset command just answer with an error, so failures there are only logged.
+    pub fn new_game(&mut self, reset_commands: &[String]) -> Result<(), AppError> {
+        self.clear_board()?;
+
+        for reset_command in reset_commands {
+            let result = self
+                .send_and_await(reset_command, |e| e, self.default_timeout)
+                .and_then(|resp| resp.success_text());
+
+            if let Err(app_error) = result {
+                warn!(
+                    "ignoring reset command '{}': {}",
+                    reset_command, app_error.message
+                );
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn set_board_size(&mut self, board_size: u8) -> Result<(), AppError> {
+        let resp = self.send_and_await(
+            &format!("boardsize {}", board_size),
+            |e| e,
+            self.default_timeout,
+        )?;
+
+        resp.success_text()?;
+        Ok(())
+    }
+
+    pub fn set_komi(&mut self, komi: f32) -> Result<(), AppError> {
+        let resp = self.send_and_await(&format!("komi {}", komi), |e| e, self.default_timeout)?;
+
+        resp.success_text()?;
+        Ok(())
+    }
+
+    /// Main time only, with no byo-yomi
+    pub fn time_settings(&mut self, main_time: Duration) -> Result<(), AppError> {
+        let resp = self.send_and_await(
+            &format!("time_settings {} 0 0", main_time.as_secs()),
+            |e| e,
+            self.default_timeout,
+        )?;
+
+        resp.success_text()?;
+        Ok(())
+    }
+
+    pub fn time_left(&mut self, color: StoneColor, remaining: Duration) -> Result<(), AppError> {
+        let resp = self.send_and_await(
+            &format!("time_left {} {} 0", color.name(), remaining.as_secs()),
+            |e| e,
+            self.default_timeout,
+        )?;
+
+        resp.success_text()?;
+        Ok(())
+    }
+
+    pub fn pass(&mut self, color: StoneColor) -> Result<(), AppError> {
+        let resp = self.send_and_await(
+            &format!("play {} pass", color.name()),
+            |e| e,
+            self.default_timeout,
+        )?;
+
+        resp.success_text()?;
+        Ok(())
+    }
+
+    /// Has the engine read the game record at `path` itself, which it can only do when it knows
+    /// `loadsgf`
+    pub fn load_sgf(&mut self, path: &str) -> Result<(), AppError> {
+        if !self.known_command("loadsgf")? {
+            return Err(AppError {
+                message: "The engine can't load SGF files (no loadsgf command)".to_string(),
+            });
+        }
+
+        let resp =
+            self.send_and_await(&format!("loadsgf {}", path), |e| e, self.default_timeout)?;
+
+        resp.success_text()?;
         Ok(())
     }
 
+    /// Sets up the engine with the position of a game record: board size, setup stones and then
+    /// every move of the main line
+    pub fn load_game(&mut self, game: &SgfGame) -> Result<(), AppError> {
+        self.set_board_size(game.board_size)?;
+        self.clear_board()?;
+
+        for coords in &game.setup_black {
+            self.play(StoneColor::Black, *coords)?;
+        }
+
+        for coords in &game.setup_white {
+            self.play(StoneColor::White, *coords)?;
+        }
+
+        for sgf_move in &game.moves {
+            match sgf_move.coords {
+                Some(coords) => self.play(sgf_move.color, coords)?,
+                None => self.pass(sgf_move.color)?,
+            }
+        }
+
+        Ok(())
+    }
+
+    /// `(black, white)` stones, read with the configured [`StoneSource`]
+    pub fn list_all_stones(&mut self) -> Result<(Vec<Stone>, Vec<Stone>), AppError> {
+        match self.stone_source {
+            StoneSource::ListStones => Ok((
+                self.list_stones(StoneColor::Black)?,
+                self.list_stones(StoneColor::White)?,
+            )),
+            StoneSource::Showboard => {
+                let resp = self.send_and_await("showboard", |e| e, self.default_timeout)?;
+
+                parse_showboard(&resp.success_text()?)
+            }
+        }
+    }
+
     pub fn list_stones(&mut self, color: StoneColor) -> Result<Vec<Stone>, AppError> {
         let resp = self.send_and_await(
             "list_stones",
@@ -180,14 +601,21 @@ impl Engine {
         Ok(stones)
     }
 
-    pub fn gen_move(&mut self, color: StoneColor) -> Result<GenMoveResponse, AppError> {
+    /// Returns the generated move along with how long the engine took to answer.
+    /// `move_number` counts from 1 and decides whether the opening timeout applies.
+    pub fn gen_move(
+        &mut self,
+        color: StoneColor,
+        move_number: usize,
+    ) -> Result<(GenMoveResponse, Duration), AppError> {
+        let timeout = self.genmove_timeout(move_number);
         let resp = self.send_and_await(
             "genmove",
             |e| match color {
                 StoneColor::White => e.w(),
                 StoneColor::Black => e.b(),
             },
-            self.genmove_timeout,
+            timeout,
         )?;
 
         let response = match resp.success_text()?.to_lowercase().as_str() {
@@ -196,7 +624,72 @@ impl Engine {
             _ => Ok(GenMoveResponse::Position(resp.success_coords()?)),
         };
 
-        return response;
+        response.map(|r| (r, resp.elapsed))
+    }
+
+    /// Move the engine would play for `color`, without playing it
+    pub fn suggest_move(&mut self, color: StoneColor) -> Result<GenMoveResponse, AppError> {
+        let resp = self.send_and_await(
+            "reg_genmove",
+            |e| match color {
+                StoneColor::White => e.w(),
+                StoneColor::Black => e.b(),
+            },
+            self.genmove_timeout,
+        )?;
+
+        match resp.success_text()?.to_lowercase().as_str() {
+            "pass" => Ok(GenMoveResponse::Pass),
+            "resign" => Ok(GenMoveResponse::Resign),
+            _ => Ok(GenMoveResponse::Position(resp.success_coords()?)),
+        }
+    }
+
+    /// Black stones on the handicap points, returned by the engine
+    pub fn fixed_handicap(&mut self, stones: u8) -> Result<Vec<Coords>, AppError> {
+        let resp = self.send_and_await(
+            &format!("fixed_handicap {}", stones),
+            |e| e,
+            self.default_timeout,
+        )?;
+
+        resp.success_coords_list()
+    }
+
+    pub fn final_status_list(&mut self, status: &str) -> Result<Vec<Coords>, AppError> {
+        let resp = self.send_and_await(
+            &format!("final_status_list {}", status),
+            |e| e,
+            self.genmove_timeout,
+        )?;
+
+        resp.success_coords_list()
+    }
+
+    /// Status of the group with a stone at `coords`, `Unknown` when the engine lists it as
+    /// neither dead, alive nor seki
+    pub fn group_status(&mut self, coords: Coords) -> Result<GroupStatus, AppError> {
+        for status in [GroupStatus::Dead, GroupStatus::Alive, GroupStatus::Seki] {
+            if self.final_status_list(status.name())?.contains(&coords) {
+                return Ok(status);
+            }
+        }
+
+        Ok(GroupStatus::Unknown)
+    }
+
+    pub fn estimate_territory(&mut self) -> Result<Territory, AppError> {
+        Ok(Territory {
+            black: self.final_status_list("black_territory")?,
+            white: self.final_status_list("white_territory")?,
+            dame: self.final_status_list("dame")?,
+        })
+    }
+
+    pub fn final_score(&mut self) -> Result<ScoreResult, AppError> {
+        let resp = self.send_and_await("final_score", |e| e, self.genmove_timeout)?;
+
+        parse_score(&resp.success_text()?)
     }
 
     fn send_and_await<T>(
@@ -216,9 +709,7 @@ impl Engine {
         debug!("EngineActor [send_and_await]: {}", cmd_name);
 
         self.gtp_engine.send(cmd);
-        // the pool interval is the timeout / 4, and we never have the result in the first pool, so
-        // it's better to delay the first pool (or change the gtp library, or even implment another)
-        thread::sleep(Duration::from_millis(5));
+        // engines hand over each response line as it arrives, so there's no need to wait first
         let response = self.gtp_engine.wait_response(timeout);
 
         Ok(ResponseWrapper {
@@ -226,9 +717,10 @@ impl Engine {
             response: response
                 .map_err(|_| {
                     let error_message = format!(
-                        "Error calling command '{}', after {}ms",
+                        "Error calling command '{}', after {}ms{}",
                         &cmd_string,
-                        start_instant.elapsed().as_millis()
+                        start_instant.elapsed().as_millis(),
+                        stderr_suffix(self.gtp_engine.stderr_tail())
                     );
 
                     warn!("{}", &error_message);
@@ -237,6 +729,7 @@ impl Engine {
                         message: error_message,
                     }
                 })
+                .map(strip_diagnostics)
                 .map(|resp| {
                     debug!(
                         "cmd '{}' returned text: '{}', after {}ms",
@@ -246,6 +739,583 @@ impl Engine {
                     );
                     resp
                 })?,
+            elapsed: start_instant.elapsed(),
+        })
+    }
+}
+
+/// Parses a `final_score` answer such as `B+3.5` or `W+12`
+fn parse_score(text: &str) -> Result<ScoreResult, AppError> {
+    let invalid = || AppError {
+        message: format!("Can't parse score '{}'", text),
+    };
+    if text.trim() == "0" {
+        return Ok(ScoreResult::Draw);
+    }
+
+    let mut parts = text.trim().splitn(2, '+');
+
+    let winner = match parts.next().map(|p| p.to_uppercase()).as_deref() {
+        Some("B") => StoneColor::Black,
+        Some("W") => StoneColor::White,
+        _ => return Err(invalid()),
+    };
+    let margin = parts
+        .next()
+        .and_then(|margin| margin.parse::<f32>().ok())
+        .ok_or_else(invalid)?;
+
+    Ok(ScoreResult::Win { winner, margin })
+}
+
+/// Reads the stones from a `showboard` drawing. Board lines start with their row number followed
+/// by one token per column, `X` for black and `O` for white (other text after the row, such as
+/// gnugo's capture counts, is ignored).
+fn parse_showboard(text: &str) -> Result<(Vec<Stone>, Vec<Stone>), AppError> {
+    let rows: Vec<(u8, Vec<&str>)> = text
+        .lines()
+        .filter_map(|line| {
+            let mut tokens = line.split_whitespace();
+            let row = tokens.next()?.parse::<u8>().ok()?;
+
+            Some((row, tokens.collect()))
+        })
+        .collect();
+
+    let board_size = rows
+        .iter()
+        .map(|(row, _)| *row)
+        .max()
+        .ok_or_else(|| AppError {
+            message: format!(
+                "No board in showboard response '{}'",
+                truncate_response(text)
+            ),
+        })?;
+    let mut black_stones: Vec<Stone> = vec![];
+    let mut white_stones: Vec<Stone> = vec![];
+
+    for (row, tokens) in rows {
+        for (index, token) in tokens.iter().take(board_size as usize).enumerate() {
+            let col = index as u8 + 1;
+
+            match token.chars().next() {
+                Some('X') | Some('x') => black_stones.push(Stone {
+                    color: StoneColor::Black,
+                    row,
+                    col,
+                }),
+                Some('O') | Some('o') => white_stones.push(Stone {
+                    color: StoneColor::White,
+                    row,
+                    col,
+                }),
+                _ => {}
+            }
+        }
+    }
+
+    Ok((black_stones, white_stones))
+}
+
+/// Chatty engines print diagnostic lines (`# ...`, blank lines) before the answer, which then
+/// show up in the response text, sometimes along with the real `=`/`?` status line. Everything
+/// up to the first line of the answer is dropped, and the status line decides success or error.
+fn strip_diagnostics(response: Response) -> Response {
+    let (id, text, is_error) = match response {
+        Response::Result((id, text)) => (id, text, false),
+        Response::Error((id, text)) => (id, text, true),
+    };
+
+    let mut lines = text
+        .lines()
+        .map(str::trim)
+        .skip_while(|line| line.is_empty() || line.starts_with('#'));
+
+    let first_line = lines.next().unwrap_or("");
+    let (is_error, first_line) = match first_line.chars().next() {
+        Some('=') => (false, first_line[1..].trim_start_matches(char::is_numeric)),
+        Some('?') => (true, first_line[1..].trim_start_matches(char::is_numeric)),
+        _ => (is_error, first_line),
+    };
+
+    let text = std::iter::once(first_line)
+        .chain(lines)
+        .collect::<Vec<&str>>()
+        .join("\n")
+        .trim()
+        .to_string();
+
+    if is_error {
+        Response::Error((id, text))
+    } else {
+        Response::Result((id, text))
+    }
+}
+
+/// Engines usually explain fatal errors (missing model, bad arguments) only on stderr
+fn stderr_suffix(stderr_lines: Vec<String>) -> String {
+    if stderr_lines.is_empty() {
+        String::new()
+    } else {
+        format!(" (engine stderr: {})", stderr_lines.join(" | "))
+    }
+}
+
+fn retry<T, F>(attempts: u32, mut f: F) -> Result<T, AppError>
+where
+    F: FnMut(u32) -> Result<T, AppError>,
+{
+    let mut attempt = 1;
+
+    loop {
+        match f(attempt) {
+            Ok(value) => return Ok(value),
+            Err(e) if attempt >= attempts => return Err(e),
+            Err(e) => warn!("attempt {} failed: {}", attempt, e.message),
+        }
+
+        attempt += 1;
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use crate::core::entities::TerritoryOwner;
+    use std::collections::VecDeque;
+    use std::sync::{Arc, Mutex};
+
+    /// Scripted engine: answers each `wait_response` with the next queued item, and records the
+    /// commands it received.
+    pub struct MockGtpEngine {
+        pub responses: VecDeque<Result<Response, AppError>>,
+        pub sent: Arc<Mutex<Vec<String>>>,
+        pub stderr: Vec<String>,
+        pub alive: bool,
+    }
+
+    impl MockGtpEngine {
+        pub fn new(responses: Vec<Result<Response, AppError>>) -> Self {
+            Self {
+                responses: responses.into(),
+                sent: Arc::new(Mutex::new(vec![])),
+                stderr: vec![],
+                alive: true,
+            }
+        }
+    }
+
+    impl GtpEngine for MockGtpEngine {
+        fn send(&mut self, cmd: Command) {
+            self.sent.lock().unwrap().push(cmd.to_string());
+        }
+
+        fn wait_response(&mut self, timeout: Duration) -> Result<Response, AppError> {
+            self.responses.pop_front().unwrap_or(Err(AppError {
+                message: format!("No response from engine after {}ms", timeout.as_millis()),
+            }))
+        }
+
+        fn stderr_tail(&self) -> Vec<String> {
+            self.stderr.clone()
+        }
+
+        fn is_alive(&mut self) -> bool {
+            self.alive
+        }
+    }
+
+    pub fn ok(text: &str) -> Result<Response, AppError> {
+        Ok(Response::Result((None, text.to_string())))
+    }
+
+    pub fn err(text: &str) -> Result<Response, AppError> {
+        Ok(Response::Error((None, text.to_string())))
+    }
+
+    pub fn timeout() -> Result<Response, AppError> {
+        Err(AppError {
+            message: "timeout".to_string(),
         })
     }
+
+    pub fn mock_engine(
+        responses: Vec<Result<Response, AppError>>,
+    ) -> (Engine, Arc<Mutex<Vec<String>>>) {
+        let mock = MockGtpEngine::new(responses);
+        let sent = mock.sent.clone();
+
+        (
+            Engine {
+                gtp_engine: Box::new(mock),
+                default_timeout: Duration::from_millis(1),
+                genmove_timeout: Duration::from_millis(1),
+                opening_genmove_timeout: Duration::from_millis(1),
+                opening_moves: 0,
+                stone_source: StoneSource::ListStones,
+                restarter: None,
+            },
+            sent,
+        )
+    }
+
+    /// Engine that died, restarted as a mock answering `responses`
+    pub fn dead_engine(responses: Vec<Result<Response, AppError>>) -> Engine {
+        let mut mock = MockGtpEngine::new(vec![]);
+        mock.alive = false;
+        let responses = Mutex::new(Some(responses));
+        let (engine, _) = mock_engine(vec![]);
+
+        Engine {
+            gtp_engine: Box::new(mock),
+            restarter: Some(Box::new(move || {
+                let responses = responses.lock().unwrap().take().unwrap_or_default();
+                Ok(Box::new(MockGtpEngine::new(responses)) as Box<dyn GtpEngine>)
+            })),
+            ..engine
+        }
+    }
+
+    #[test]
+    fn only_engines_with_a_restarter_come_back() {
+        let (mut engine, _) = mock_engine(vec![]);
+        assert_eq!(
+            "The engine can't be restarted",
+            engine
+                .restart(1, Duration::from_millis(1))
+                .unwrap_err()
+                .message
+        );
+
+        let mut engine = dead_engine(vec![ok("2")]);
+        assert!(!engine.is_alive());
+        engine.restart(1, Duration::from_millis(1)).unwrap();
+        assert!(engine.is_alive());
+    }
+
+    #[test]
+    fn unparsable_responses_are_quoted_in_the_error() {
+        let (mut engine, _) = mock_engine(vec![ok("D4 Dx")]);
+
+        let message = engine.list_stones(StoneColor::Black).unwrap_err().message;
+        assert!(message.ends_with("returned a response that can't be parsed: 'D4 Dx'"));
+
+        let long_response = "Dx ".repeat(40);
+        let (mut engine, _) = mock_engine(vec![ok(&long_response)]);
+
+        let message = engine.list_stones(StoneColor::Black).unwrap_err().message;
+        assert!(message.ends_with(&format!("'{}...'", &long_response[..80])));
+    }
+
+    #[test]
+    fn estimate_territory_assigns_each_list_to_its_owner() {
+        let (mut engine, sent) = mock_engine(vec![ok("A1 B1"), ok("T19"), ok("")]);
+        let territory = engine.estimate_territory().unwrap();
+
+        assert_eq!(
+            Some(TerritoryOwner::Black),
+            territory.owner(&Coords::from(1, 2))
+        );
+        assert_eq!(
+            Some(TerritoryOwner::White),
+            territory.owner(&Coords::from(19, 19))
+        );
+        assert_eq!(None, territory.owner(&Coords::from(10, 10)));
+        assert_eq!(1, territory.area_difference(0, 0));
+        assert_eq!(
+            vec![
+                "final_status_list black_territory",
+                "final_status_list white_territory",
+                "final_status_list dame"
+            ],
+            sent.lock()
+                .unwrap()
+                .iter()
+                .map(|c| c.trim())
+                .collect::<Vec<&str>>()
+        );
+    }
+
+    #[test]
+    fn group_in_the_dead_list_is_dead() {
+        let (mut engine, sent) = mock_engine(vec![ok("C3 D3\nQ16")]);
+
+        assert_eq!(
+            GroupStatus::Dead,
+            engine.group_status(Coords::from(3, 4)).unwrap()
+        );
+        assert_eq!(1, sent.lock().unwrap().len());
+
+        let (mut engine, _) = mock_engine(vec![ok(""), ok("C3 D3")]);
+        assert_eq!(
+            GroupStatus::Alive,
+            engine.group_status(Coords::from(3, 3)).unwrap()
+        );
+
+        let (mut engine, sent) = mock_engine(vec![ok(""), ok(""), ok("C3 D3")]);
+        assert_eq!(
+            GroupStatus::Seki,
+            engine.group_status(Coords::from(3, 3)).unwrap()
+        );
+        assert_eq!("final_status_list seki", sent.lock().unwrap()[2].trim());
+
+        let (mut engine, _) = mock_engine(vec![ok(""), ok(""), ok("")]);
+        assert_eq!(
+            GroupStatus::Unknown,
+            engine.group_status(Coords::from(3, 3)).unwrap()
+        );
+    }
+
+    #[test]
+    fn new_game_sends_reset_commands_in_order_after_clear_board() {
+        let (mut engine, sent) = mock_engine(vec![ok(""), err("unknown command"), ok("")]);
+        let reset_commands = vec!["clear_cache".to_string(), "kata-reset".to_string()];
+
+        assert!(engine.new_game(&reset_commands).is_ok());
+        assert_eq!(
+            vec!["clear_board", "clear_cache", "kata-reset"],
+            sent.lock()
+                .unwrap()
+                .iter()
+                .map(|c| c.trim())
+                .collect::<Vec<&str>>()
+        );
+    }
+
+    #[test]
+    fn sgf_files_are_only_sent_to_engines_that_know_loadsgf() {
+        let (mut engine, sent) = mock_engine(vec![ok("true"), ok("black")]);
+        assert!(engine.load_sgf("game.sgf").is_ok());
+        assert_eq!("loadsgf game.sgf", sent.lock().unwrap()[1].trim());
+
+        let (mut engine, sent) = mock_engine(vec![ok("false")]);
+        assert_eq!(
+            "The engine can't load SGF files (no loadsgf command)",
+            engine.load_sgf("game.sgf").unwrap_err().message
+        );
+        assert_eq!(1, sent.lock().unwrap().len());
+    }
+
+    #[test]
+    fn load_game_sets_up_the_board_size_before_the_moves() {
+        let game = crate::core::sgf::parse_sgf("(;SZ[13]AB[jd];W[dj];B[])").unwrap();
+        let (mut engine, sent) = mock_engine(vec![ok(""), ok(""), ok(""), ok(""), ok("")]);
+
+        assert!(engine.load_game(&game).is_ok());
+
+        let sent = sent.lock().unwrap();
+
+        assert_eq!(5, sent.len());
+        assert_eq!("boardsize 13", sent[0].trim());
+        assert_eq!("clear_board", sent[1].trim());
+        assert_eq!("play black pass", sent[4].trim());
+    }
+
+    #[test]
+    fn diagnostic_lines_before_the_answer_are_skipped() {
+        let (mut engine, _) = mock_engine(vec![
+            ok("# visits 1600, winrate 0.52\n\n= D4"),
+            ok("  # komi 6.5\n? illegal move"),
+        ]);
+
+        match engine.gen_move(StoneColor::Black, 1).unwrap().0 {
+            GenMoveResponse::Position(coords) => assert_eq!(Coords::from(4, 4), coords),
+            _ => panic!("expected a position"),
+        }
+        assert!(engine.play(StoneColor::White, Coords::from(4, 4)).is_err());
+    }
+
+    #[test]
+    fn refused_moves_are_told_apart_from_failed_exchanges() {
+        let (mut engine, _) = mock_engine(vec![ok(""), err("illegal move"), timeout()]);
+
+        assert_eq!(
+            PlayResponse::Played,
+            engine
+                .try_play(StoneColor::Black, Coords::from(4, 4))
+                .unwrap()
+        );
+        assert_eq!(
+            PlayResponse::Illegal("illegal move".to_string()),
+            engine
+                .try_play(StoneColor::White, Coords::from(4, 4))
+                .unwrap()
+        );
+        assert!(engine
+            .try_play(StoneColor::White, Coords::from(5, 5))
+            .is_err());
+    }
+
+    #[test]
+    fn opening_moves_get_the_longer_genmove_timeout() {
+        let (engine, _) = mock_engine(vec![]);
+        let engine = engine.with_opening_genmove_timeout(Duration::from_secs(10), 4);
+
+        assert_eq!(Duration::from_secs(10), engine.genmove_timeout(1));
+        assert_eq!(Duration::from_secs(10), engine.genmove_timeout(4));
+        assert_eq!(Duration::from_millis(1), engine.genmove_timeout(5));
+    }
+
+    #[test]
+    fn final_score_reads_winner_and_margin() {
+        let (mut engine, _) = mock_engine(vec![ok("W+12"), ok("B+3.5"), ok("W+R")]);
+
+        let score = engine.final_score().unwrap();
+        assert_eq!(
+            ScoreResult::Win {
+                winner: StoneColor::White,
+                margin: 12.0
+            },
+            score
+        );
+        assert_eq!("W+12", score.to_string());
+        assert_eq!("B+3.5", engine.final_score().unwrap().to_string());
+        assert!(engine.final_score().is_err());
+    }
+
+    #[test]
+    fn showboard_and_list_stones_read_the_same_position() {
+        let showboard = "\n   A B C D E\n 5 . . . . . 5\n 4 . O . . . 4     WHITE (O) has captured 1 stones\n 3 . . + X . 3     BLACK (X) has captured 0 stones\n 2 . . . . . 2\n 1 X . . . O 1\n   A B C D E";
+        let (engine, _) = mock_engine(vec![ok(showboard)]);
+        let mut engine = engine.with_stone_source(StoneSource::Showboard);
+        let from_showboard = engine.list_all_stones().unwrap();
+
+        let (mut engine, _) = mock_engine(vec![ok("D3 A1"), ok("B4 E1")]);
+        let from_list_stones = engine.list_all_stones().unwrap();
+
+        let sorted = |mut stones: Vec<Stone>| {
+            stones.sort_by_key(|stone| (stone.row, stone.col));
+            stones
+        };
+        assert_eq!(2, from_showboard.0.len());
+        assert_eq!(sorted(from_list_stones.0), sorted(from_showboard.0));
+        assert_eq!(sorted(from_list_stones.1), sorted(from_showboard.1));
+    }
+
+    #[test]
+    fn zero_score_is_a_draw() {
+        let (mut engine, _) = mock_engine(vec![ok("0")]);
+
+        let score = engine.final_score().unwrap();
+        assert_eq!(ScoreResult::Draw, score);
+        assert_eq!("Draw (jigo)", score.to_string());
+    }
+
+    #[test]
+    fn list_commands_reads_one_command_per_line() {
+        let (mut engine, _) = mock_engine(vec![ok("play\nundo\n\nfinal_score")]);
+        let info = EngineInfo {
+            komi: None,
+            name: None,
+            commands: engine.list_commands().ok(),
+        };
+
+        assert!(info.supports("undo"));
+        assert!(!info.supports("fixed_handicap"));
+        assert!(EngineInfo::default().supports("fixed_handicap"));
+    }
+
+    #[test]
+    fn commands_are_probed_when_the_engine_cant_list_them() {
+        let (mut engine, sent) = mock_engine(vec![
+            err("unknown command"),
+            ok("true"),
+            ok("false"),
+            ok("true"),
+        ]);
+        let info = EngineInfo {
+            komi: None,
+            name: None,
+            commands: engine.supported_commands(),
+        };
+
+        assert!(info.supports("undo"));
+        assert!(!info.supports("final_score"));
+        assert_eq!(
+            "known_command final_status_list",
+            sent.lock().unwrap().last().unwrap().trim()
+        );
+
+        let (mut engine, _) = mock_engine(vec![err("unknown command"), err("unknown command")]);
+        assert_eq!(None, engine.supported_commands());
+    }
+
+    #[test]
+    fn fixed_handicap_returns_the_placed_stones() {
+        let (mut engine, sent) = mock_engine(vec![ok("D4 Q16")]);
+
+        assert_eq!(
+            vec![Coords::from(4, 4), Coords::from(16, 16)],
+            engine.fixed_handicap(2).unwrap()
+        );
+        assert_eq!("fixed_handicap 2", sent.lock().unwrap()[0].trim());
+    }
+
+    #[test]
+    fn suggested_moves_are_not_played() {
+        let (mut engine, sent) = mock_engine(vec![ok("Q16")]);
+
+        match engine.suggest_move(StoneColor::White).unwrap() {
+            GenMoveResponse::Position(coords) => assert_eq!(Coords::from(16, 16), coords),
+            _ => panic!("expected a position"),
+        }
+        assert!(sent.lock().unwrap()[0].starts_with("reg_genmove"));
+    }
+
+    #[test]
+    fn fast_commands_return_without_a_fixed_delay() {
+        let (mut engine, _) = mock_engine((0..100).map(|_| ok("A1")).collect());
+        engine.default_timeout = Duration::from_secs(1);
+        let start = Instant::now();
+
+        for _ in 0..100 {
+            engine.list_stones(StoneColor::Black).unwrap();
+        }
+
+        // a 5ms floor per command would take at least 500ms, the mock alone takes microseconds,
+        // so the bound leaves plenty of room for a loaded machine
+        assert!(start.elapsed() < Duration::from_millis(450));
+    }
+
+    #[test]
+    fn identity_is_the_name_and_version() {
+        let (mut engine, _) = mock_engine(vec![ok("GNU Go"), ok("3.8")]);
+        assert_eq!(Some("GNU Go 3.8".to_string()), engine.identity());
+
+        let (mut engine, _) = mock_engine(vec![ok("Leela"), err("unknown command")]);
+        assert_eq!(Some("Leela".to_string()), engine.identity());
+    }
+
+    #[test]
+    fn wait_until_ready_retries_a_slow_engine() {
+        let (mut engine, sent) = mock_engine(vec![timeout(), timeout(), ok("2")]);
+
+        assert!(engine.wait_until_ready(3, Duration::from_millis(1)).is_ok());
+        assert_eq!(1, sent.lock().unwrap().len());
+    }
+
+    #[test]
+    fn wait_until_ready_gives_up_after_the_configured_attempts() {
+        let (mut engine, _) = mock_engine(vec![timeout(), timeout(), ok("2")]);
+
+        assert!(engine
+            .wait_until_ready(2, Duration::from_millis(1))
+            .is_err());
+    }
+
+    #[test]
+    fn engine_errors_include_the_last_stderr_lines() {
+        let mut mock = MockGtpEngine::new(vec![timeout()]);
+        mock.stderr = vec!["FATAL: model file not found".to_string()];
+
+        let (mut engine, _) = mock_engine(vec![]);
+        engine.gtp_engine = Box::new(mock);
+
+        let error = engine
+            .wait_until_ready(1, Duration::from_millis(1))
+            .unwrap_err();
+
+        assert!(error.message.contains("FATAL: model file not found"));
+    }
 }
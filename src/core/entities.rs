@@ -1,6 +1,10 @@
+use crate::core::helpers::{get_column_name, get_column_number};
 use core::convert::{TryFrom, TryInto};
+use serde::{Deserialize, Serialize};
+use std::fmt;
 
-#[derive(Debug, Copy, Clone)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
 pub enum StoneColor {
     White,
     Black,
@@ -14,6 +18,14 @@ impl StoneColor {
         }
     }
 
+    /// `B` or `W`, as in SGF properties and game results
+    pub fn letter(&self) -> &str {
+        match self {
+            Self::White => "W",
+            Self::Black => "B",
+        }
+    }
+
     pub fn inverse(&self) -> Self {
         match self {
             Self::White => Self::Black,
@@ -22,13 +34,14 @@ impl StoneColor {
     }
 }
 
-#[derive(Debug, Copy, Clone)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize)]
 pub struct Stone {
     pub color: StoneColor,
     pub row: u8,
     pub col: u8,
 }
 
+/// Partially typed coordinates, see [`Coords`] for the meaning of `row` and `col`
 #[derive(Debug, Copy, Clone)]
 pub struct OptCoords {
     pub row: Option<u8>,
@@ -44,20 +57,46 @@ impl Default for OptCoords {
     }
 }
 
-#[derive(Debug, Copy, Clone)]
+/// A board intersection, both 1-based as in GTP: `col` 1 is the `A` column (left) and `row` 1
+/// is the bottom line.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize)]
 pub struct Coords {
     pub row: u8,
     pub col: u8,
 }
 
 impl Coords {
+    /// Note the argument order: row first, then col
     pub fn from(row: u8, col: u8) -> Self {
         Self { row, col }
     }
 
+    /// GTP vertex, which is `(x, y)`, so col first, then row
     pub fn vertex(&self) -> (i32, i32) {
         (self.col as i32, self.row as i32)
     }
+
+    /// GTP vertex string such as `Q16`, the column letters skip `I`
+    pub fn to_gtp(self, board_size: u8) -> String {
+        debug_assert!(self.col <= board_size && self.row <= board_size);
+
+        format!("{}{}", get_column_name(self.col), self.row)
+    }
+
+    /// Parses a GTP vertex string (case insensitive), `None` when it's not an intersection of
+    /// the board, which includes `pass`
+    pub fn from_gtp(vertex: &str, board_size: u8) -> Option<Coords> {
+        let vertex = vertex.trim().to_uppercase();
+        let mut chars = vertex.chars();
+        let col = chars.next().and_then(get_column_number)?;
+        let row: u8 = chars.as_str().parse().ok()?;
+
+        if col > board_size || row < 1 || row > board_size {
+            return None;
+        }
+
+        Some(Coords::from(row, col))
+    }
 }
 
 impl TryFrom<&OptCoords> for Coords {
@@ -73,3 +112,159 @@ impl TryFrom<&OptCoords> for Coords {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn from_takes_row_then_col() {
+        let coords = Coords::from(3, 16);
+
+        assert_eq!(3, coords.row);
+        assert_eq!(16, coords.col);
+    }
+
+    #[test]
+    fn vertex_is_col_then_row() {
+        assert_eq!((16, 3), Coords::from(3, 16).vertex());
+    }
+
+    #[test]
+    fn gtp_strings_round_trip_on_every_column() {
+        assert_eq!("Q16", Coords::from(16, 16).to_gtp(19));
+        assert_eq!("J1", Coords::from(1, 9).to_gtp(19));
+        assert_eq!(Some(Coords::from(16, 16)), Coords::from_gtp("q16", 19));
+
+        for col in 1..=25 {
+            for row in [1, 9, 25] {
+                let coords = Coords::from(row, col);
+                let gtp = coords.to_gtp(25);
+
+                assert!(!gtp.starts_with('I'), "{} uses the I column", gtp);
+                assert_eq!(Some(coords), Coords::from_gtp(&gtp, 25));
+            }
+        }
+    }
+
+    #[test]
+    fn gtp_strings_outside_the_board_are_rejected() {
+        assert_eq!(None, Coords::from_gtp("I5", 19));
+        assert_eq!(None, Coords::from_gtp("U1", 19));
+        assert_eq!(None, Coords::from_gtp("A20", 19));
+        assert_eq!(None, Coords::from_gtp("A0", 19));
+        assert_eq!(None, Coords::from_gtp("pass", 19));
+        assert_eq!(None, Coords::from_gtp("", 19));
+    }
+
+    #[test]
+    fn try_from_opt_coords_keeps_row_and_col() {
+        let opt_coords = OptCoords {
+            row: Some(3),
+            col: Some(16),
+        };
+        let coords: Coords = (&opt_coords).try_into().unwrap();
+
+        assert_eq!(Coords::from(3, 16), coords);
+    }
+
+    #[test]
+    fn try_from_incomplete_opt_coords_fails() {
+        let only_col = OptCoords {
+            row: None,
+            col: Some(16),
+        };
+
+        assert!(Coords::try_from(&only_col).is_err());
+        assert!(Coords::try_from(&OptCoords::default()).is_err());
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum TerritoryOwner {
+    Black,
+    White,
+    Neutral,
+}
+
+/// Auxiliary mark drawn on an intersection, besides the highlight
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum MarkerKind {
+    /// Move suggested to the player
+    Hint,
+    /// Stone of a group with a single liberty
+    Atari,
+    /// SGF `TR`, `SQ` and `CR` markup
+    Triangle,
+    Square,
+    Circle,
+}
+
+/// Life and death of a group, as the engine judges it at the end of the game
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum GroupStatus {
+    Alive,
+    Dead,
+    /// Neither alive nor dead on its own
+    Seki,
+    /// In none of the lists the engine gave
+    Unknown,
+}
+
+impl GroupStatus {
+    pub fn name(&self) -> &str {
+        match self {
+            Self::Alive => "alive",
+            Self::Dead => "dead",
+            Self::Seki => "seki",
+            Self::Unknown => "unknown",
+        }
+    }
+}
+
+/// Territory estimate as reported by the engine `final_status_list`
+#[derive(Debug, Clone, Default)]
+pub struct Territory {
+    pub black: Vec<Coords>,
+    pub white: Vec<Coords>,
+    pub dame: Vec<Coords>,
+}
+
+impl Territory {
+    pub fn owner(&self, coords: &Coords) -> Option<TerritoryOwner> {
+        if self.black.contains(coords) {
+            Some(TerritoryOwner::Black)
+        } else if self.white.contains(coords) {
+            Some(TerritoryOwner::White)
+        } else if self.dame.contains(coords) {
+            Some(TerritoryOwner::Neutral)
+        } else {
+            None
+        }
+    }
+
+    /// Area (stones + territory) of black minus the area of white
+    pub fn area_difference(&self, black_stones: usize, white_stones: usize) -> i32 {
+        (self.black.len() + black_stones) as i32 - (self.white.len() + white_stones) as i32
+    }
+}
+
+/// Result of a scored game, as reported by the engine `final_score`
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum ScoreResult {
+    Win {
+        winner: StoneColor,
+        margin: f32,
+    },
+    /// Reported as `0`, possible with an integer komi
+    Draw,
+}
+
+impl fmt::Display for ScoreResult {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Win { winner, margin } => write!(f, "{}+{}", winner.letter(), margin),
+            Self::Draw => write!(f, "Draw (jigo)"),
+        }
+    }
+}
@@ -0,0 +1,548 @@
+use crate::core::config::{ResignRule, TwoPassesRule};
+use crate::core::entities::{Coords, StoneColor};
+use crate::core::errors::AppError;
+use crate::core::goban::Goban;
+use crate::core::sgf::{self, SgfGame, SgfMove};
+use std::time::Duration;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum MoveKind {
+    Play(Coords),
+    Pass,
+    Resign,
+}
+
+#[derive(Debug, Copy, Clone)]
+pub struct MoveRecord {
+    pub color: StoneColor,
+    pub kind: MoveKind,
+    /// How long the engine took to generate the move, `None` for human moves
+    pub think_time: Option<Duration>,
+    /// Opponent stones the move took off the board
+    pub captured: usize,
+    /// The mover's own stones the move took off the board (suicide), counted for the opponent
+    pub self_captured: usize,
+}
+
+/// Where the game stands after the last move
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum GameProgress {
+    Playing,
+    /// Both players passed and the game should be scored
+    Score,
+    /// Both players passed, scoring waits for the player to confirm
+    ConfirmScore,
+    /// The color resigned, and the game is over
+    Resigned(StoneColor),
+    /// The color resigned, but moves are still accepted to review the game
+    ResignedReviewing(StoneColor),
+}
+
+#[derive(Debug, Clone)]
+pub struct MoveHistory {
+    first_player: StoneColor,
+    /// Stones placed before the first move (handicap or a loaded record's setup)
+    setup_black: Vec<Coords>,
+    setup_white: Vec<Coords>,
+    moves: Vec<MoveRecord>,
+    max_undo: Option<usize>,
+    /// Undo never takes the history below this many moves
+    undo_floor: usize,
+}
+
+impl Default for MoveHistory {
+    fn default() -> Self {
+        Self::new(StoneColor::Black)
+    }
+}
+
+impl MoveHistory {
+    pub fn new(first_player: StoneColor) -> Self {
+        Self {
+            first_player,
+            setup_black: vec![],
+            setup_white: vec![],
+            moves: vec![],
+            max_undo: None,
+            undo_floor: 0,
+        }
+    }
+
+    pub fn with_setup(mut self, black: Vec<Coords>, white: Vec<Coords>) -> Self {
+        self.setup_black = black;
+        self.setup_white = white;
+        self
+    }
+
+    /// Limits undo to the last `max_undo` moves, counted from the furthest the game got
+    pub fn with_max_undo(mut self, max_undo: Option<usize>) -> Self {
+        self.max_undo = max_undo;
+        self.raise_undo_floor();
+        self
+    }
+
+    /// Moves and setup stones of a game record. The record's first move decides who started,
+    /// `first_player` is only used when it has no moves.
+    pub fn from_sgf(game: &SgfGame, first_player: StoneColor) -> Self {
+        // the record has no captures, so they're counted by replaying it
+        let mut goban = Goban::new(game.board_size);
+
+        for (color, setup) in [
+            (StoneColor::Black, &game.setup_black),
+            (StoneColor::White, &game.setup_white),
+        ] {
+            for coords in setup {
+                let _ = goban.play(color, *coords);
+            }
+        }
+
+        Self {
+            first_player: game
+                .moves
+                .first()
+                .map(|sgf_move| sgf_move.color)
+                .unwrap_or(first_player),
+            setup_black: game.setup_black.clone(),
+            setup_white: game.setup_white.clone(),
+            moves: game
+                .moves
+                .iter()
+                .map(|sgf_move| MoveRecord {
+                    color: sgf_move.color,
+                    kind: match sgf_move.coords {
+                        Some(coords) => MoveKind::Play(coords),
+                        None => MoveKind::Pass,
+                    },
+                    think_time: None,
+                    captured: match sgf_move.coords {
+                        Some(coords) => goban
+                            .play(sgf_move.color, coords)
+                            .map_or(0, |captured| captured.len()),
+                        None => {
+                            goban.pass();
+                            0
+                        }
+                    },
+                    self_captured: 0,
+                })
+                .collect(),
+            max_undo: None,
+            undo_floor: 0,
+        }
+    }
+
+    pub fn push(&mut self, record: MoveRecord) {
+        self.moves.push(record);
+        self.raise_undo_floor();
+    }
+
+    /// How many moves can still be taken back. Setup stones are not moves, so undo stops before
+    /// them.
+    pub fn undoable_moves(&self) -> usize {
+        self.moves.len() - self.undo_floor
+    }
+
+    pub fn undo(&mut self) -> Result<MoveRecord, AppError> {
+        if self.undoable_moves() == 0 {
+            return Err(AppError {
+                message: "Cannot undo further".to_string(),
+            });
+        }
+
+        Ok(self.moves.pop().unwrap())
+    }
+
+    fn raise_undo_floor(&mut self) {
+        if let Some(max_undo) = self.max_undo {
+            self.undo_floor = self
+                .undo_floor
+                .max(self.moves.len().saturating_sub(max_undo));
+        }
+    }
+
+    pub fn moves(&self) -> &[MoveRecord] {
+        &self.moves
+    }
+
+    pub fn len(&self) -> usize {
+        self.moves.len()
+    }
+
+    /// Colors alternate from the first player on, passes included
+    pub fn to_move(&self) -> StoneColor {
+        match self.moves.last() {
+            Some(record) => record.color.inverse(),
+            None => self.first_player,
+        }
+    }
+
+    pub fn check_turn(&self, color: StoneColor) -> Result<(), AppError> {
+        if color == self.to_move() {
+            Ok(())
+        } else {
+            Err(AppError {
+                message: format!("Not your turn, {} to move", self.to_move().name()),
+            })
+        }
+    }
+
+    pub fn progress(&self, two_passes: TwoPassesRule, on_resign: ResignRule) -> GameProgress {
+        let mut last_kinds = self.moves.iter().rev().map(|m| (m.color, m.kind));
+
+        match (last_kinds.next(), last_kinds.next()) {
+            (Some((color, MoveKind::Resign)), _) => match on_resign {
+                ResignRule::End => GameProgress::Resigned(color),
+                ResignRule::Review => GameProgress::ResignedReviewing(color),
+            },
+            (Some((_, MoveKind::Pass)), Some((_, MoveKind::Pass))) => match two_passes {
+                TwoPassesRule::Score => GameProgress::Score,
+                TwoPassesRule::Confirm => GameProgress::ConfirmScore,
+            },
+            _ => GameProgress::Playing,
+        }
+    }
+
+    /// Color the human plays after swapping, only allowed before the first move so colors can
+    /// be decided (nigiri) once the engine is ready
+    pub fn swap_colors(&self, player_color: StoneColor) -> Result<StoneColor, AppError> {
+        if self.moves.is_empty() {
+            Ok(player_color.inverse())
+        } else {
+            Err(AppError {
+                message: "Colors can only be swapped before the first move".to_string(),
+            })
+        }
+    }
+
+    /// Stones `color` captured so far, including those the opponent lost to suicide
+    pub fn captures(&self, color: StoneColor) -> usize {
+        self.moves
+            .iter()
+            .map(|m| {
+                if m.color == color {
+                    m.captured
+                } else {
+                    m.self_captured
+                }
+            })
+            .sum()
+    }
+
+    /// Moves played since the last one that captured, or since the start. A long quiet streak
+    /// hints that the game is winding down.
+    pub fn moves_since_capture(&self) -> usize {
+        self.moves
+            .iter()
+            .rev()
+            .take_while(|m| m.captured == 0 && m.self_captured == 0)
+            .count()
+    }
+
+    /// Points of the last `count` moves, the latest first. Passes and resignations take their
+    /// place in the list without a point.
+    pub fn recent_points(&self, count: usize) -> Vec<Option<Coords>> {
+        self.moves
+            .iter()
+            .rev()
+            .take(count)
+            .map(|m| match m.kind {
+                MoveKind::Play(coords) => Some(coords),
+                MoveKind::Pass | MoveKind::Resign => None,
+            })
+            .collect()
+    }
+
+    /// Game record of the setup stones and moves, resignations left out
+    pub fn to_sgf_game(&self, board_size: u8, komi: Option<f32>) -> SgfGame {
+        let mut game = SgfGame::new(board_size);
+        game.komi = komi;
+        game.setup_black = self.setup_black.clone();
+        game.setup_white = self.setup_white.clone();
+        game.moves = self
+            .moves
+            .iter()
+            .filter_map(|m| {
+                let coords = match m.kind {
+                    MoveKind::Play(coords) => Some(coords),
+                    MoveKind::Pass => None,
+                    MoveKind::Resign => return None,
+                };
+
+                Some(SgfMove {
+                    color: m.color,
+                    coords,
+                })
+            })
+            .collect();
+
+        game
+    }
+
+    pub fn to_sgf(&self, board_size: u8, komi: f32) -> String {
+        sgf::write_sgf(&self.to_sgf_game(board_size, Some(komi)))
+    }
+
+    pub fn last_think_time(&self) -> Option<Duration> {
+        self.moves.iter().rev().find_map(|m| m.think_time)
+    }
+
+    pub fn average_think_time(&self) -> Option<Duration> {
+        let times: Vec<Duration> = self.moves.iter().filter_map(|m| m.think_time).collect();
+
+        if times.is_empty() {
+            None
+        } else {
+            Some(times.iter().sum::<Duration>() / times.len() as u32)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn engine_move(millis: u64) -> MoveRecord {
+        MoveRecord {
+            color: StoneColor::White,
+            kind: MoveKind::Pass,
+            think_time: Some(Duration::from_millis(millis)),
+            captured: 0,
+            self_captured: 0,
+        }
+    }
+
+    #[test]
+    fn think_times_ignore_human_moves() {
+        let mut history = MoveHistory::default();
+
+        assert_eq!(None, history.average_think_time());
+
+        history.push(engine_move(100));
+        history.push(MoveRecord {
+            color: StoneColor::Black,
+            kind: MoveKind::Play(Coords::from(4, 4)),
+            think_time: None,
+            captured: 0,
+            self_captured: 0,
+        });
+        history.push(engine_move(300));
+        history.push(MoveRecord {
+            color: StoneColor::Black,
+            kind: MoveKind::Pass,
+            think_time: None,
+            captured: 0,
+            self_captured: 0,
+        });
+
+        assert_eq!(Some(Duration::from_millis(300)), history.last_think_time());
+        assert_eq!(
+            Some(Duration::from_millis(200)),
+            history.average_think_time()
+        );
+    }
+
+    #[test]
+    fn white_cannot_play_before_black() {
+        let mut history = MoveHistory::new(StoneColor::Black);
+
+        assert!(history.check_turn(StoneColor::White).is_err());
+        assert!(history.check_turn(StoneColor::Black).is_ok());
+
+        history.push(MoveRecord {
+            color: StoneColor::Black,
+            kind: MoveKind::Play(Coords::from(4, 4)),
+            think_time: None,
+            captured: 0,
+            self_captured: 0,
+        });
+
+        assert_eq!(StoneColor::White, history.to_move());
+        assert!(history.check_turn(StoneColor::Black).is_err());
+    }
+
+    fn pass(color: StoneColor) -> MoveRecord {
+        MoveRecord {
+            color,
+            kind: MoveKind::Pass,
+            think_time: None,
+            captured: 0,
+            self_captured: 0,
+        }
+    }
+
+    #[test]
+    fn two_passes_score_or_ask_for_confirmation() {
+        let mut history = MoveHistory::new(StoneColor::Black);
+        let progress = |history: &MoveHistory, rule| history.progress(rule, ResignRule::End);
+
+        history.push(pass(StoneColor::Black));
+        assert_eq!(
+            GameProgress::Playing,
+            progress(&history, TwoPassesRule::Score)
+        );
+
+        history.push(pass(StoneColor::White));
+        assert_eq!(
+            GameProgress::Score,
+            progress(&history, TwoPassesRule::Score)
+        );
+        assert_eq!(
+            GameProgress::ConfirmScore,
+            progress(&history, TwoPassesRule::Confirm)
+        );
+    }
+
+    #[test]
+    fn resign_ends_or_keeps_the_game_open_for_review() {
+        let mut history = MoveHistory::new(StoneColor::Black);
+        let progress = |history: &MoveHistory, rule| history.progress(TwoPassesRule::Score, rule);
+
+        history.push(MoveRecord {
+            color: StoneColor::Black,
+            kind: MoveKind::Resign,
+            think_time: None,
+            captured: 0,
+            self_captured: 0,
+        });
+
+        assert_eq!(
+            GameProgress::Resigned(StoneColor::Black),
+            progress(&history, ResignRule::End)
+        );
+        assert_eq!(
+            GameProgress::ResignedReviewing(StoneColor::Black),
+            progress(&history, ResignRule::Review)
+        );
+    }
+
+    fn play(color: StoneColor, row: u8, col: u8) -> MoveRecord {
+        MoveRecord {
+            color,
+            kind: MoveKind::Play(Coords::from(row, col)),
+            think_time: None,
+            captured: 0,
+            self_captured: 0,
+        }
+    }
+
+    #[test]
+    fn undo_stops_at_the_configured_depth() {
+        let mut history = MoveHistory::new(StoneColor::Black).with_max_undo(Some(2));
+
+        history.push(play(StoneColor::Black, 4, 4));
+        history.push(play(StoneColor::White, 16, 16));
+        history.push(play(StoneColor::Black, 16, 4));
+        assert_eq!(2, history.undoable_moves());
+
+        assert!(history.undo().is_ok());
+        assert!(history.undo().is_ok());
+        assert!(history.undo().is_err());
+        assert_eq!(1, history.len());
+
+        // playing again doesn't let undo reach further back than before
+        history.push(play(StoneColor::White, 3, 3));
+        assert_eq!(1, history.undoable_moves());
+    }
+
+    #[test]
+    fn undo_stops_at_the_setup_stones() {
+        let game = crate::core::sgf::parse_sgf("(;SZ[9]AB[cc][gg];W[ee])").unwrap();
+        let mut history = MoveHistory::from_sgf(&game, StoneColor::Black);
+
+        assert!(history.undo().is_ok());
+        assert!(history.undo().is_err());
+        assert_eq!(StoneColor::White, history.to_move());
+    }
+
+    #[test]
+    fn captures_are_counted_when_replaying_a_record() {
+        // white B1 has a single liberty left after the setup, black takes it at C1
+        let game =
+            crate::core::sgf::parse_sgf("(;SZ[9]AB[ai][bh]AW[bi];W[ee];B[ci];W[ff])").unwrap();
+        let history = MoveHistory::from_sgf(&game, StoneColor::Black);
+
+        assert_eq!(1, history.captures(StoneColor::Black));
+        assert_eq!(0, history.captures(StoneColor::White));
+    }
+
+    #[test]
+    fn passes_lift_the_ko_when_replaying_a_record() {
+        // black C2 takes the ko at B2, and white retakes it after both players pass
+        let game = crate::core::sgf::parse_sgf(
+            "(;SZ[9]AB[ba][bc][ab]AW[ca][cc][db][bb];B[cb];W[];B[];W[bb])",
+        )
+        .unwrap();
+        let history = MoveHistory::from_sgf(&game, StoneColor::Black);
+
+        assert_eq!(1, history.captures(StoneColor::Black));
+        assert_eq!(1, history.captures(StoneColor::White));
+    }
+
+    #[test]
+    fn moves_since_capture_restart_on_every_capture() {
+        let mut history = MoveHistory::new(StoneColor::Black);
+        assert_eq!(0, history.moves_since_capture());
+
+        history.push(play(StoneColor::Black, 4, 4));
+        history.push(play(StoneColor::White, 16, 16));
+        assert_eq!(2, history.moves_since_capture());
+
+        history.push(MoveRecord {
+            captured: 2,
+            self_captured: 0,
+            ..play(StoneColor::Black, 3, 3)
+        });
+        assert_eq!(0, history.moves_since_capture());
+
+        history.push(pass(StoneColor::White));
+        assert_eq!(1, history.moves_since_capture());
+    }
+
+    #[test]
+    fn swapping_before_the_first_move_hands_the_first_move_to_the_engine() {
+        let mut history = MoveHistory::new(StoneColor::Black);
+        let player_color = history.swap_colors(StoneColor::Black).unwrap();
+
+        assert_eq!(StoneColor::White, player_color);
+        // black still moves first, so the engine has to generate it
+        assert_eq!(StoneColor::Black, history.to_move());
+        assert!(history.check_turn(player_color).is_err());
+
+        history.push(MoveRecord {
+            color: StoneColor::Black,
+            kind: MoveKind::Play(Coords::from(4, 4)),
+            think_time: Some(Duration::from_millis(100)),
+            captured: 0,
+            self_captured: 0,
+        });
+
+        assert!(history.swap_colors(player_color).is_err());
+    }
+
+    #[test]
+    fn sgf_record_has_the_moves_and_komi() {
+        let mut history = MoveHistory::default();
+        for (color, kind) in [
+            (StoneColor::Black, MoveKind::Play(Coords::from(16, 4))),
+            (StoneColor::White, MoveKind::Play(Coords::from(1, 19))),
+            (StoneColor::Black, MoveKind::Pass),
+            (StoneColor::White, MoveKind::Resign),
+        ] {
+            history.push(MoveRecord {
+                color,
+                kind,
+                think_time: None,
+                captured: 0,
+                self_captured: 0,
+            });
+        }
+
+        let sgf = history.to_sgf(19, 6.5);
+        assert!(sgf.contains("SZ[19]KM[6.5];B[dd];W[ss];B[])"), "{}", sgf);
+
+        let history = MoveHistory::new(StoneColor::White)
+            .with_setup(vec![Coords::from(4, 4), Coords::from(16, 16)], vec![]);
+        let sgf = history.to_sgf(19, 0.5);
+        assert!(sgf.contains("KM[0.5]HA[2]AB[dp][pd])"), "{}", sgf);
+    }
+}
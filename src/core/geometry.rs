@@ -0,0 +1,58 @@
+use crate::core::entities::Coords;
+
+/// Hoshi: the 3-3 points (4-4 from 13x13 up), the center of odd boards and, on 19x19 and up,
+/// the middle of each side
+pub fn is_star_point(coords: &Coords, board_size: u8) -> bool {
+    if board_size < 5 {
+        return false;
+    }
+
+    let margin = if board_size >= 13 { 4 } else { 3 };
+    let near_edge = |n: u8| n == margin || n == board_size - margin + 1;
+    let middle = |n: u8| board_size % 2 == 1 && n == board_size / 2 + 1;
+
+    match (coords.row, coords.col) {
+        (row, col) if near_edge(row) && near_edge(col) => true,
+        (row, col) if middle(row) && middle(col) => true,
+        (row, col) if board_size >= 19 => {
+            (middle(row) && near_edge(col)) || (near_edge(row) && middle(col))
+        }
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn star_points(board_size: u8) -> Vec<(u8, u8)> {
+        (1..=board_size)
+            .flat_map(|row| (1..=board_size).map(move |col| (row, col)))
+            .filter(|&(row, col)| is_star_point(&Coords::from(row, col), board_size))
+            .collect()
+    }
+
+    #[test]
+    fn known_star_points() {
+        assert_eq!(vec![(3, 3), (3, 7), (5, 5), (7, 3), (7, 7)], star_points(9));
+        assert_eq!(
+            vec![(4, 4), (4, 10), (7, 7), (10, 4), (10, 10)],
+            star_points(13)
+        );
+        assert_eq!(
+            vec![
+                (4, 4),
+                (4, 10),
+                (4, 16),
+                (10, 4),
+                (10, 10),
+                (10, 16),
+                (16, 4),
+                (16, 10),
+                (16, 16)
+            ],
+            star_points(19)
+        );
+        assert!(star_points(4).is_empty());
+    }
+}
@@ -0,0 +1,260 @@
+use super::errors::AppError;
+use crate::core::entities::{Coords, Stone, StoneColor};
+
+/// Board position with the rules needed to check moves locally, without asking the engine
+#[derive(Debug, Clone)]
+pub struct Goban {
+    size: u8,
+    /// Indexed by `(row - 1) * size + (col - 1)`
+    points: Vec<Option<StoneColor>>,
+    /// Where retaking the single stone just captured would repeat the position
+    ko: Option<Coords>,
+}
+
+impl Goban {
+    pub fn new(size: u8) -> Self {
+        Self {
+            size,
+            points: vec![None; size as usize * size as usize],
+            ko: None,
+        }
+    }
+
+    /// Position as listed by the engine. Ko can't be known from the stones alone, see
+    /// [`Goban::with_ko`].
+    pub fn from_stones(size: u8, stones: &[Stone]) -> Self {
+        let mut goban = Self::new(size);
+
+        for stone in stones {
+            let coords = Coords::from(stone.row, stone.col);
+
+            if goban.contains(&coords) {
+                let index = goban.index(&coords);
+                goban.points[index] = Some(stone.color);
+            }
+        }
+
+        goban
+    }
+
+    /// Forbids playing at `ko`, where the single stone just captured would be retaken
+    pub fn with_ko(mut self, ko: Option<Coords>) -> Self {
+        self.ko = ko;
+        self
+    }
+
+    pub fn size(&self) -> u8 {
+        self.size
+    }
+
+    pub fn get(&self, coords: &Coords) -> Option<StoneColor> {
+        if self.contains(coords) {
+            self.points[self.index(coords)]
+        } else {
+            None
+        }
+    }
+
+    pub fn is_legal(&self, color: StoneColor, coords: &Coords) -> bool {
+        self.clone().play(color, *coords).is_ok()
+    }
+
+    pub fn legal_moves(&self, color: StoneColor) -> Vec<Coords> {
+        (1..=self.size)
+            .flat_map(|row| (1..=self.size).map(move |col| Coords::from(row, col)))
+            .filter(|coords| self.is_legal(color, coords))
+            .collect()
+    }
+
+    /// Places the stone and removes the groups it captured, which are returned
+    pub fn play(&mut self, color: StoneColor, coords: Coords) -> Result<Vec<Coords>, AppError> {
+        let vertex = coords.to_gtp(self.size);
+        let illegal = |reason: &str| {
+            Err(AppError {
+                message: format!("Illegal move at {}: {}", vertex, reason),
+            })
+        };
+
+        if !self.contains(&coords) {
+            return illegal("outside the board");
+        }
+        if self.get(&coords).is_some() {
+            return illegal("point is occupied");
+        }
+        if self.ko == Some(coords) {
+            return illegal("ko");
+        }
+
+        let index = self.index(&coords);
+        self.points[index] = Some(color);
+
+        let mut captured: Vec<Coords> = vec![];
+
+        for neighbor in self.neighbors(&coords) {
+            if self.get(&neighbor) == Some(color.inverse()) && !captured.contains(&neighbor) {
+                let (group, liberties) = self.group(&neighbor);
+
+                if liberties == 0 {
+                    captured.extend(group);
+                }
+            }
+        }
+
+        if captured.is_empty() && self.group(&coords).1 == 0 {
+            self.points[index] = None;
+            return illegal("suicide");
+        }
+
+        for captured_coords in &captured {
+            let captured_index = self.index(captured_coords);
+            self.points[captured_index] = None;
+        }
+
+        // a single stone capturing a single stone, left in atari, could be retaken right away
+        self.ko = if captured.len() == 1
+            && self.group(&coords).0.len() == 1
+            && self.liberties(&coords) == 1
+        {
+            Some(captured[0])
+        } else {
+            None
+        };
+
+        Ok(captured)
+    }
+
+    /// A pass lifts the ko ban
+    pub fn pass(&mut self) {
+        self.ko = None;
+    }
+
+    /// Liberties of the group with a stone at `coords`
+    pub fn liberties(&self, coords: &Coords) -> usize {
+        self.group(coords).1
+    }
+
+    fn contains(&self, coords: &Coords) -> bool {
+        (1..=self.size).contains(&coords.row) && (1..=self.size).contains(&coords.col)
+    }
+
+    fn index(&self, coords: &Coords) -> usize {
+        (coords.row as usize - 1) * self.size as usize + (coords.col as usize - 1)
+    }
+
+    pub fn neighbors(&self, coords: &Coords) -> Vec<Coords> {
+        let (row, col) = (coords.row as i16, coords.col as i16);
+
+        [
+            (row - 1, col),
+            (row + 1, col),
+            (row, col - 1),
+            (row, col + 1),
+        ]
+        .iter()
+        .filter(|(row, col)| {
+            (1..=self.size as i16).contains(row) && (1..=self.size as i16).contains(col)
+        })
+        .map(|&(row, col)| Coords::from(row as u8, col as u8))
+        .collect()
+    }
+
+    /// Stones connected to `coords` and how many liberties they share
+    fn group(&self, coords: &Coords) -> (Vec<Coords>, usize) {
+        let color = self.get(coords);
+        let mut group = vec![*coords];
+        let mut liberties: Vec<Coords> = vec![];
+        let mut pending = vec![*coords];
+
+        while let Some(current) = pending.pop() {
+            for neighbor in self.neighbors(&current) {
+                match self.get(&neighbor) {
+                    None if !liberties.contains(&neighbor) => liberties.push(neighbor),
+                    Some(neighbor_color)
+                        if Some(neighbor_color) == color && !group.contains(&neighbor) =>
+                    {
+                        group.push(neighbor);
+                        pending.push(neighbor);
+                    }
+                    _ => {}
+                }
+            }
+        }
+
+        (group, liberties.len())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn goban(size: u8, black: &[(u8, u8)], white: &[(u8, u8)]) -> Goban {
+        let stones: Vec<Stone> = black
+            .iter()
+            .map(|&(row, col)| (StoneColor::Black, row, col))
+            .chain(
+                white
+                    .iter()
+                    .map(|&(row, col)| (StoneColor::White, row, col)),
+            )
+            .map(|(color, row, col)| Stone { color, row, col })
+            .collect();
+
+        Goban::from_stones(size, &stones)
+    }
+
+    #[test]
+    fn occupied_points_and_suicide_are_illegal() {
+        // white A1 would have no liberties and capture nothing
+        let goban = goban(9, &[(1, 2), (2, 1), (8, 1)], &[(9, 2)]);
+
+        assert!(!goban.is_legal(StoneColor::White, &Coords::from(1, 2)));
+        assert!(!goban.is_legal(StoneColor::White, &Coords::from(1, 1)));
+        assert!(goban.is_legal(StoneColor::Black, &Coords::from(1, 1)));
+        assert!(goban.is_legal(StoneColor::Black, &Coords::from(9, 1)));
+        assert_eq!(76, goban.legal_moves(StoneColor::White).len());
+    }
+
+    #[test]
+    fn capturing_makes_a_move_without_liberties_legal() {
+        let mut goban = goban(9, &[(1, 2)], &[(1, 1), (2, 2)]);
+
+        // the black stone at B1 has a single liberty left, at C1
+        let captured = goban.play(StoneColor::White, Coords::from(1, 3)).unwrap();
+
+        assert_eq!(vec![Coords::from(1, 2)], captured);
+        assert_eq!(None, goban.get(&Coords::from(1, 2)));
+    }
+
+    #[test]
+    fn ko_cannot_be_retaken_right_away() {
+        // black C2 captures white B2, and white can't retake at B2 right away
+        let mut goban = goban(
+            9,
+            &[(1, 2), (3, 2), (2, 1)],
+            &[(1, 3), (3, 3), (2, 4), (2, 2)],
+        );
+
+        assert_eq!(
+            vec![Coords::from(2, 2)],
+            goban.play(StoneColor::Black, Coords::from(2, 3)).unwrap()
+        );
+        assert!(!goban.is_legal(StoneColor::White, &Coords::from(2, 2)));
+
+        goban.play(StoneColor::White, Coords::from(7, 7)).unwrap();
+        goban.play(StoneColor::Black, Coords::from(7, 3)).unwrap();
+        assert!(goban.is_legal(StoneColor::White, &Coords::from(2, 2)));
+    }
+    #[test]
+    fn capturing_stone_with_two_liberties_leaves_no_ko() {
+        // black C2 captures white B2 but keeps a liberty at D2, so B2 is open again
+        let mut goban = goban(9, &[(1, 2), (3, 2), (2, 1)], &[(1, 3), (3, 3), (2, 2)]);
+
+        assert_eq!(
+            vec![Coords::from(2, 2)],
+            goban.play(StoneColor::Black, Coords::from(2, 3)).unwrap()
+        );
+        assert_eq!(2, goban.liberties(&Coords::from(2, 3)));
+        assert!(goban.is_legal(StoneColor::Black, &Coords::from(2, 2)));
+    }
+}
@@ -1,38 +1,129 @@
 use super::errors::AppError;
 use crate::core::entities::OptCoords;
+use iced_native::keyboard::KeyCode;
 use iced_native::{Color, Column, Element, Row};
-use read_color::rgb;
 
+/// GTP column letter, from `A` up to `Z` for 25x25 boards
 pub fn get_column_name(col: u8) -> char {
     // skip I column
     let add = if col >= 9 { 1 } else { 0 };
     (64u8 + col + add) as char
 }
 
-pub fn get_column_number(col: char) -> u8 {
+/// Inverse of [`get_column_name`], `None` for anything but an uppercase letter other than `I`
+pub fn get_column_number(col: char) -> Option<u8> {
+    if !col.is_ascii_uppercase() || col == 'I' {
+        return None;
+    }
+
     let col_nr = col as u8 - 64u8;
     let remove = if col_nr >= 9 { 1 } else { 0 };
-    col_nr - remove
+    Some(col_nr - remove)
+}
+
+/// Infallible color from a `0xRRGGBB` literal, for colors known at compile time
+pub fn rgb(hex: u32) -> Color {
+    Color::from_rgb8((hex >> 16) as u8, (hex >> 8) as u8, hex as u8)
 }
 
+/// Reads `#rrggbb`, `#rgb`, `rgb(r, g, b)` or a basic CSS color name
 pub fn parse_color(text: &str) -> Result<Color, AppError> {
-    if text.starts_with("#") {
-        let mut chars = text.chars();
-        chars.next();
+    let text = text.trim();
+
+    if let Some(hex) = text.strip_prefix('#') {
+        // `#abc` is short for `#aabbcc`
+        let hex: String = if hex.chars().count() == 3 {
+            hex.chars().flat_map(|c| [c, c]).collect()
+        } else {
+            hex.to_string()
+        };
+        let mut chars = hex.chars();
 
-        return match rgb(&mut chars) {
-            Some([r, g, b]) => Ok(Color::from_rgb8(r, g, b)),
-            None => Err(AppError {
+        return match (read_color::rgb(&mut chars), chars.next()) {
+            (Some([r, g, b]), None) => Ok(Color::from_rgb8(r, g, b)),
+            _ => Err(AppError {
                 message: format!("Can't parse hex color {}", text),
             }),
         };
     }
 
-    Err(AppError {
+    let invalid = || AppError {
         message: format!("Can't parse color {}", text),
+    };
+
+    if let Some(channels) = text
+        .strip_prefix("rgb(")
+        .and_then(|rest| rest.strip_suffix(')'))
+    {
+        let channels: Vec<u8> = channels
+            .split(',')
+            .map(|channel| channel.trim().parse())
+            .collect::<Result<_, _>>()
+            .map_err(|_| invalid())?;
+
+        return match channels.as_slice() {
+            [r, g, b] => Ok(Color::from_rgb8(*r, *g, *b)),
+            _ => Err(invalid()),
+        };
+    }
+
+    named_color(&text.to_lowercase())
+        .map(rgb)
+        .ok_or_else(invalid)
+}
+
+/// The 16 basic CSS colors, plus `orange` and `grey`
+fn named_color(name: &str) -> Option<u32> {
+    Some(match name {
+        "black" => 0x000000,
+        "silver" => 0xc0c0c0,
+        "gray" | "grey" => 0x808080,
+        "white" => 0xffffff,
+        "maroon" => 0x800000,
+        "red" => 0xff0000,
+        "purple" => 0x800080,
+        "fuchsia" | "magenta" => 0xff00ff,
+        "green" => 0x008000,
+        "lime" => 0x00ff00,
+        "olive" => 0x808000,
+        "yellow" => 0xffff00,
+        "navy" => 0x000080,
+        "blue" => 0x0000ff,
+        "teal" => 0x008080,
+        "aqua" | "cyan" => 0x00ffff,
+        "orange" => 0xffa500,
+        _ => return None,
     })
 }
 
+/// Some terminals report keypad digits as key codes only, without the character event
+pub fn keypad_digit(key_code: KeyCode) -> Option<char> {
+    match key_code {
+        KeyCode::Numpad0 => Some('0'),
+        KeyCode::Numpad1 => Some('1'),
+        KeyCode::Numpad2 => Some('2'),
+        KeyCode::Numpad3 => Some('3'),
+        KeyCode::Numpad4 => Some('4'),
+        KeyCode::Numpad5 => Some('5'),
+        KeyCode::Numpad6 => Some('6'),
+        KeyCode::Numpad7 => Some('7'),
+        KeyCode::Numpad8 => Some('8'),
+        KeyCode::Numpad9 => Some('9'),
+        _ => None,
+    }
+}
+
+/// `(rows, columns)` an arrow key moves the board cursor, rows going up
+pub fn arrow_step(key_code: KeyCode) -> Option<(i8, i8)> {
+    match key_code {
+        KeyCode::Up => Some((1, 0)),
+        KeyCode::Down => Some((-1, 0)),
+        KeyCode::Left => Some((0, -1)),
+        KeyCode::Right => Some((0, 1)),
+        _ => None,
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -40,6 +131,9 @@ mod tests {
 
     #[test]
     fn correct_column_name_number_mapping() {
+        assert_eq!(None, get_column_number('I'));
+        assert_eq!(None, get_column_number('d'));
+
         let char_table = [
             ('A', 1),
             ('B', 2),
@@ -60,35 +154,114 @@ mod tests {
             ('R', 17),
             ('S', 18),
             ('T', 19),
+            ('U', 20),
+            ('V', 21),
+            ('W', 22),
+            ('X', 23),
+            ('Y', 24),
+            ('Z', 25),
         ];
 
         for (char_name, char_nr) in char_table {
             assert_eq!(char_name, get_column_name(char_nr));
-            assert_eq!(char_nr, get_column_number(char_name));
+            assert_eq!(Some(char_nr), get_column_number(char_name));
             assert_eq!(
                 format!("{}1", char_name),
                 Entity::Vertex((char_nr.into(), 1)).to_string()
             );
         }
     }
+
+    #[test]
+    fn input_off_the_board_is_rejected() {
+        let coords = parse_input_coords("D4", 9);
+        assert_eq!((Some(4), Some(4)), (coords.col, coords.row));
+
+        let coords = parse_input_coords("A50", 19);
+        assert_eq!((Some(1), None), (coords.col, coords.row));
+
+        let coords = parse_input_coords("K0", 9);
+        assert_eq!((None, None), (coords.col, coords.row));
+    }
+
+    #[test]
+    fn sgf_input_counts_rows_from_the_top() {
+        let coords = parse_sgf_input_coords("pd", 19);
+        assert_eq!((Some(16), Some(16)), (coords.col, coords.row));
+
+        let coords = parse_sgf_input_coords("a", 19);
+        assert_eq!((Some(1), None), (coords.col, coords.row));
+
+        let coords = parse_sgf_input_coords("jt", 19);
+        assert_eq!((Some(10), None), (coords.col, coords.row));
+    }
+
+    #[test]
+    fn colors_can_be_short_hex_rgb_or_named() {
+        let teal = Color::from_rgb8(0x00, 0x80, 0x80);
+
+        assert_eq!(teal, parse_color("#008080").unwrap());
+        assert_eq!(teal, parse_color("teal").unwrap());
+        assert_eq!(teal, parse_color("rgb(0, 128, 128)").unwrap());
+        assert_eq!(rgb(0xaabbcc), parse_color("#abc").unwrap());
+        assert_eq!(rgb(0xffffff), parse_color(" White ").unwrap());
+
+        for malformed in [
+            "#abcd",
+            "#12345g",
+            "#0080801",
+            "rgb(0, 128)",
+            "rgb(0, 256, 0)",
+            "reddish",
+        ] {
+            assert!(
+                parse_color(malformed).is_err(),
+                "{} was accepted",
+                malformed
+            );
+        }
+    }
+
+    #[test]
+    fn keypad_digits_map_to_top_row_digits() {
+        assert_eq!(Some('0'), keypad_digit(KeyCode::Numpad0));
+        assert_eq!(Some('9'), keypad_digit(KeyCode::Numpad9));
+        assert_eq!(None, keypad_digit(KeyCode::Key9));
+    }
 }
 
-pub fn parse_input_coords(mut input: String) -> OptCoords {
-    let col: Option<u8> = if !input.is_empty() {
-        Some(get_column_number(input.remove(0)))
-    } else {
-        None
-    };
+/// Typed GTP coordinates such as `D4`. A column or row off a `board_size` board is `None`.
+pub fn parse_input_coords(input: &str, board_size: u8) -> OptCoords {
+    let mut chars = input.chars();
+    let on_board = |n: &u8| (1..=board_size).contains(n);
 
-    let row: Option<u8> = if !input.is_empty() {
-        input.parse().ok()
-    } else {
-        None
-    };
+    let col = chars.next().and_then(get_column_number).filter(on_board);
+    let row = Some(chars.as_str())
+        .filter(|digits| !digits.is_empty())
+        .and_then(|digits| digits.parse().ok())
+        .filter(on_board);
 
     OptCoords { col, row }
 }
 
+/// Same as [`parse_input_coords`] for SGF letter pairs such as `pd`: the first letter is the
+/// column and the second the row counted from the top, neither skipping `i`
+pub fn parse_sgf_input_coords(input: &str, board_size: u8) -> OptCoords {
+    let mut letters = input.bytes().map(|b| {
+        b.checked_sub(b'a')
+            .map(|n| n + 1)
+            .filter(|&n| n <= board_size)
+    });
+
+    OptCoords {
+        col: letters.next().flatten(),
+        row: letters
+            .next()
+            .flatten()
+            .map(|row_from_top| board_size - row_from_top + 1),
+    }
+}
+
 pub trait TryPush<'a, Message, Renderer> {
     /// Adds an [`Element`] to the [`Row`].
     fn try_push<E>(self, child: Option<E>) -> Self
@@ -1,7 +1,21 @@
+pub mod builtin_engine;
+pub mod cli;
+pub mod clock;
 pub mod config;
+pub mod context;
 pub mod engine;
 pub mod entities;
 pub mod errors;
+pub mod game;
+pub mod geometry;
+pub mod goban;
 pub mod helpers;
 pub mod logger;
+pub mod process_engine;
+pub mod sgf;
+pub mod signals;
+#[cfg(feature = "serve")]
+pub mod status_server;
 pub mod theme;
+pub mod title;
+pub mod validator;
@@ -0,0 +1,285 @@
+use super::errors::AppError;
+use crate::core::engine::GtpEngine;
+use gtp::{Command, Response};
+use log::warn;
+use std::collections::VecDeque;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::process::{Child, ChildStdin, Command as ProcessCommand, Stdio};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// How many stderr lines are kept to explain a failure
+const STDERR_TAIL_LINES: usize = 5;
+
+/// Last lines written to a stream, usually the engine stderr
+#[derive(Debug, Clone, Default)]
+pub struct StderrTail {
+    lines: Arc<Mutex<VecDeque<String>>>,
+}
+
+impl StderrTail {
+    pub fn push(&self, line: String) {
+        let mut lines = self.lines.lock().unwrap();
+
+        lines.push_back(line);
+        if lines.len() > STDERR_TAIL_LINES {
+            lines.pop_front();
+        }
+    }
+
+    pub fn lines(&self) -> Vec<String> {
+        self.lines.lock().unwrap().iter().cloned().collect()
+    }
+
+    /// Reads `reader` on a thread until it's closed
+    pub fn capture<R: Read + Send + 'static>(&self, reader: R) {
+        let tail = self.clone();
+
+        thread::spawn(move || {
+            for_each_line(reader, |line| {
+                if !line.trim().is_empty() {
+                    tail.push(line);
+                }
+                true
+            })
+        });
+    }
+}
+
+/// Calls `handle` with each line read until `reader` is closed or `handle` returns false. Bytes
+/// that aren't UTF-8 are replaced instead of ending the stream, which `BufRead::lines` would do.
+fn for_each_line<R: Read>(reader: R, mut handle: impl FnMut(String) -> bool) {
+    let mut reader = BufReader::new(reader);
+    let mut buffer: Vec<u8> = vec![];
+
+    loop {
+        buffer.clear();
+        match reader.read_until(b'\n', &mut buffer) {
+            Ok(0) | Err(_) => break,
+            Ok(_) => {
+                let line = String::from_utf8_lossy(&buffer)
+                    .trim_end_matches(['\n', '\r'])
+                    .to_string();
+
+                if !handle(line) {
+                    break;
+                }
+            }
+        }
+    }
+}
+
+/// GTP engine subprocess. Stdout is read on a thread so waiting for a response can time out,
+/// and the end of stderr is kept since that's where engines explain why they failed.
+pub struct ProcessEngine {
+    child: Child,
+    stdin: ChildStdin,
+    stdout_lines: Receiver<String>,
+    stderr_tail: StderrTail,
+    /// Id sent with the last command, so a late answer to an earlier one isn't taken for its own
+    last_id: u32,
+}
+
+impl ProcessEngine {
+    pub fn start(bin_path: &str, args: &[String]) -> Result<Self, AppError> {
+        let mut child = ProcessCommand::new(bin_path)
+            .args(args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .map_err(|e| AppError {
+                message: format!("Error starting engine '{}': {}", bin_path, &e.to_string()),
+            })?;
+
+        // all three were piped above
+        let stdin = child.stdin.take().unwrap();
+        let stdout = child.stdout.take().unwrap();
+        let stderr_tail = StderrTail::default();
+        stderr_tail.capture(child.stderr.take().unwrap());
+
+        let (sender, stdout_lines) = mpsc::channel();
+
+        thread::spawn(move || for_each_line(stdout, |line| sender.send(line).is_ok()));
+
+        Ok(Self {
+            child,
+            stdin,
+            stdout_lines,
+            stderr_tail,
+            last_id: 0,
+        })
+    }
+}
+
+impl GtpEngine for ProcessEngine {
+    fn send(&mut self, cmd: Command) {
+        self.last_id += 1;
+        let line = format!("{} {}\n", self.last_id, cmd.to_string().trim_end());
+
+        if let Err(e) = self
+            .stdin
+            .write_all(line.as_bytes())
+            .and_then(|_| self.stdin.flush())
+        {
+            warn!("Error writing '{}' to the engine: {}", line.trim_end(), e);
+        }
+    }
+
+    fn wait_response(&mut self, timeout: Duration) -> Result<Response, AppError> {
+        let deadline = Instant::now() + timeout;
+        let mut response_lines: Vec<String> = vec![];
+
+        loop {
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            let line = match self.stdout_lines.recv_timeout(remaining) {
+                Ok(line) => line.trim_end().to_string(),
+                Err(RecvTimeoutError::Timeout) => {
+                    return Err(AppError {
+                        message: format!("No response from engine after {}ms", timeout.as_millis()),
+                    })
+                }
+                Err(RecvTimeoutError::Disconnected) => {
+                    return Err(AppError {
+                        message: "Engine process exited".to_string(),
+                    })
+                }
+            };
+
+            if !response_lines.is_empty() {
+                // a response ends with an empty line
+                if line.is_empty() {
+                    let response = parse_response(&response_lines);
+
+                    if response_id(&response) == Some(self.last_id) {
+                        return Ok(response);
+                    }
+                    // answer to a command that timed out before
+                    warn!(
+                        "Ignoring late engine response '{}'",
+                        response_lines.join("\n")
+                    );
+                    response_lines.clear();
+                    continue;
+                }
+                response_lines.push(line);
+            } else if line.starts_with('=') || line.starts_with('?') {
+                response_lines.push(line);
+            }
+        }
+    }
+
+    fn stderr_tail(&self) -> Vec<String> {
+        self.stderr_tail.lines()
+    }
+
+    fn is_alive(&mut self) -> bool {
+        matches!(self.child.try_wait(), Ok(None))
+    }
+}
+
+impl Drop for ProcessEngine {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+/// Builds a response from its lines, the first one starting with `=` or `?` and an optional id
+fn parse_response(lines: &[String]) -> Response {
+    let first_line = &lines[0];
+    let is_error = first_line.starts_with('?');
+    let rest = &first_line[1..];
+    let id_length = rest.chars().take_while(|c| c.is_ascii_digit()).count();
+    let id = rest[..id_length].parse::<u32>().ok();
+
+    let text = std::iter::once(rest[id_length..].trim())
+        .chain(lines[1..].iter().map(|line| line.as_str()))
+        .collect::<Vec<&str>>()
+        .join("\n");
+
+    if is_error {
+        Response::Error((id, text))
+    } else {
+        Response::Result((id, text))
+    }
+}
+
+fn response_id(response: &Response) -> Option<u32> {
+    match response {
+        Response::Result((id, _)) | Response::Error((id, _)) => *id,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn responses_keep_their_id_and_text() {
+        match parse_response(&["=12 D4".to_string()]) {
+            Response::Result((id, text)) => {
+                assert_eq!(Some(12), id);
+                assert_eq!("D4", text);
+            }
+            _ => panic!("expected a result"),
+        }
+
+        match parse_response(&["? unknown command".to_string()]) {
+            Response::Error((id, text)) => {
+                assert_eq!(None, id);
+                assert_eq!("unknown command", text);
+            }
+            _ => panic!("expected an error"),
+        }
+    }
+
+    #[test]
+    fn stderr_tail_keeps_the_last_lines() {
+        let tail = StderrTail::default();
+
+        for i in 0..8 {
+            tail.push(format!("line {}", i));
+        }
+
+        assert_eq!(STDERR_TAIL_LINES, tail.lines().len());
+        assert_eq!(Some(&"line 7".to_string()), tail.lines().last());
+    }
+
+    #[test]
+    fn lines_with_bad_bytes_are_kept() {
+        let mut lines: Vec<String> = vec![];
+
+        for_each_line(&b"bad \xff byte\r\n= D4\n"[..], |line| {
+            lines.push(line);
+            true
+        });
+
+        assert_eq!(
+            vec!["bad \u{fffd} byte".to_string(), "= D4".to_string()],
+            lines
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn late_responses_to_earlier_commands_are_skipped() {
+        // answers the first command only once the second one has been sent
+        let script = "read first; read second; printf '=1 late\\n\\n=2 D4\\n\\n'; read end";
+        let mut engine =
+            ProcessEngine::start("sh", &["-c".to_string(), script.to_string()]).unwrap();
+
+        engine.send(Command::new("genmove b"));
+        engine.send(Command::new("genmove w"));
+
+        match engine.wait_response(Duration::from_secs(5)).unwrap() {
+            Response::Result((id, text)) => {
+                assert_eq!(Some(2), id);
+                assert_eq!("D4", text);
+            }
+            _ => panic!("expected a result"),
+        }
+    }
+}
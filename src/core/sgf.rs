@@ -0,0 +1,360 @@
+use super::errors::AppError;
+use crate::core::entities::{Coords, MarkerKind, StoneColor};
+use std::fs;
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct SgfMove {
+    pub color: StoneColor,
+    /// `None` is a pass
+    pub coords: Option<Coords>,
+}
+
+/// Main line of a game record
+#[derive(Debug, Clone, PartialEq)]
+pub struct SgfGame {
+    pub board_size: u8,
+    pub komi: Option<f32>,
+    /// Setup stones (handicap), written as `AB`/`AW` in the root node
+    pub setup_black: Vec<Coords>,
+    pub setup_white: Vec<Coords>,
+    pub moves: Vec<SgfMove>,
+    /// `TR`, `SQ` and `CR` markup found along the main line, drawn once the game is loaded
+    pub markup: Vec<(Coords, MarkerKind)>,
+}
+
+impl SgfGame {
+    pub fn new(board_size: u8) -> Self {
+        Self {
+            board_size,
+            komi: None,
+            setup_black: vec![],
+            setup_white: vec![],
+            moves: vec![],
+            markup: vec![],
+        }
+    }
+}
+
+/// SGF points are two letters, col then row, counted from the top left corner with no `I` skip
+pub fn to_sgf_point(coords: &Coords, board_size: u8) -> String {
+    format!(
+        "{}{}",
+        (b'a' + coords.col - 1) as char,
+        (b'a' + board_size - coords.row) as char
+    )
+}
+
+pub fn from_sgf_point(point: &str, board_size: u8) -> Option<Coords> {
+    let bytes = point.as_bytes();
+
+    if bytes.len() != 2 || !bytes.iter().all(|b| b.is_ascii_lowercase()) {
+        return None;
+    }
+
+    let col = bytes[0] - b'a' + 1;
+    let row_from_top = bytes[1] - b'a' + 1;
+
+    if col > board_size || row_from_top > board_size {
+        return None;
+    }
+
+    Some(Coords::from(board_size - row_from_top + 1, col))
+}
+
+pub fn write_sgf(game: &SgfGame) -> String {
+    let mut sgf = format!(
+        "(;GM[1]FF[4]CA[UTF-8]AP[{}:{}]SZ[{}]",
+        env!("CARGO_PKG_NAME"),
+        env!("CARGO_PKG_VERSION"),
+        game.board_size
+    );
+
+    if let Some(komi) = game.komi {
+        sgf.push_str(&format!("KM[{}]", komi));
+    }
+
+    // moves don't need AE for captures, players recompute them, only setup stones are special
+    if !game.setup_black.is_empty() {
+        if game.setup_white.is_empty() {
+            sgf.push_str(&format!("HA[{}]", game.setup_black.len()));
+        }
+        sgf.push_str("AB");
+        for coords in &game.setup_black {
+            sgf.push_str(&format!("[{}]", to_sgf_point(coords, game.board_size)));
+        }
+    }
+
+    if !game.setup_white.is_empty() {
+        sgf.push_str("AW");
+        for coords in &game.setup_white {
+            sgf.push_str(&format!("[{}]", to_sgf_point(coords, game.board_size)));
+        }
+    }
+
+    for sgf_move in &game.moves {
+        sgf.push_str(&format!(
+            ";{}[{}]",
+            sgf_move.color.letter(),
+            sgf_move
+                .coords
+                .map(|coords| to_sgf_point(&coords, game.board_size))
+                .unwrap_or_default()
+        ));
+    }
+
+    sgf.push_str(")\n");
+    sgf
+}
+
+pub fn write_sgf_file(path: &str, text: &str) -> Result<(), AppError> {
+    fs::write(path, text).map_err(|e| AppError {
+        message: format!("Error writing the file '{}': {}", path, &e.to_string()),
+    })
+}
+
+pub fn read_sgf_file(path: &str) -> Result<SgfGame, AppError> {
+    let text = fs::read_to_string(path).map_err(|e| AppError {
+        message: format!("Error reading the file '{}': {}", path, &e.to_string()),
+    })?;
+
+    parse_sgf(&text).map_err(|e| AppError {
+        message: format!("Error parsing the file '{}': {}", path, e.message),
+    })
+}
+
+/// Reads the main line (first variation at every branch) of an SGF record
+pub fn parse_sgf(text: &str) -> Result<SgfGame, AppError> {
+    let properties = parse_main_line_properties(text)?;
+    let mut game = SgfGame::new(19);
+
+    if let Some((_, values)) = properties.iter().find(|(ident, _)| ident == "SZ") {
+        game.board_size = parse_board_size(&values[0])?;
+    }
+
+    for (ident, values) in &properties {
+        match ident.as_str() {
+            "KM" => {
+                game.komi = Some(values[0].trim().parse().map_err(|_| AppError {
+                    message: format!("Invalid SGF komi '{}'", values[0]),
+                })?)
+            }
+            "AB" => game
+                .setup_black
+                .append(&mut parse_point_list(values, game.board_size)?),
+            "AW" => game
+                .setup_white
+                .append(&mut parse_point_list(values, game.board_size)?),
+            "B" | "W" => game.moves.push(SgfMove {
+                color: if ident == "B" {
+                    StoneColor::Black
+                } else {
+                    StoneColor::White
+                },
+                coords: parse_move_point(&values[0], game.board_size)?,
+            }),
+            "TR" | "SQ" | "CR" => {
+                let kind = match ident.as_str() {
+                    "TR" => MarkerKind::Triangle,
+                    "SQ" => MarkerKind::Square,
+                    _ => MarkerKind::Circle,
+                };
+
+                game.markup.extend(
+                    parse_point_list(values, game.board_size)?
+                        .into_iter()
+                        .map(|coords| (coords, kind)),
+                );
+            }
+            _ => {}
+        }
+    }
+
+    Ok(game)
+}
+
+fn parse_board_size(value: &str) -> Result<u8, AppError> {
+    let invalid = || AppError {
+        message: format!("Invalid SGF board size '{}'", value),
+    };
+
+    match value.split_once(':') {
+        Some((cols, rows)) if cols.trim() != rows.trim() => Err(AppError {
+            message: format!("Non-square boards are not supported (SZ[{}])", value),
+        }),
+        Some((cols, _)) => cols.trim().parse().map_err(|_| invalid()),
+        None => value.trim().parse().map_err(|_| invalid()),
+    }
+}
+
+fn parse_move_point(value: &str, board_size: u8) -> Result<Option<Coords>, AppError> {
+    // an empty value is a pass, and so is "tt" on boards up to 19x19
+    if value.is_empty() || (value == "tt" && board_size <= 19) {
+        return Ok(None);
+    }
+
+    from_sgf_point(value, board_size).map(Some).ok_or(AppError {
+        message: format!("Invalid SGF point '{}'", value),
+    })
+}
+
+/// Point lists may use the compressed `aa:cc` rectangle form
+fn parse_point_list(values: &[String], board_size: u8) -> Result<Vec<Coords>, AppError> {
+    let mut points = vec![];
+
+    for value in values {
+        let invalid = || AppError {
+            message: format!("Invalid SGF point '{}'", value),
+        };
+
+        match value.split_once(':') {
+            Some((from, to)) => {
+                let from = from_sgf_point(from, board_size).ok_or_else(invalid)?;
+                let to = from_sgf_point(to, board_size).ok_or_else(invalid)?;
+
+                for row in (from.row.min(to.row)..=from.row.max(to.row)).rev() {
+                    for col in from.col.min(to.col)..=from.col.max(to.col) {
+                        points.push(Coords::from(row, col));
+                    }
+                }
+            }
+            None => points.push(from_sgf_point(value, board_size).ok_or_else(invalid)?),
+        }
+    }
+
+    Ok(points)
+}
+
+fn parse_main_line_properties(text: &str) -> Result<Vec<(String, Vec<String>)>, AppError> {
+    let mut properties: Vec<(String, Vec<String>)> = vec![];
+    let mut chars = text.chars().peekable();
+    let mut ident = String::new();
+    let mut started = false;
+
+    while let Some(c) = chars.next() {
+        match c {
+            '(' => started = true,
+            // following the first variation at every branch, the first closing parenthesis is
+            // where the main line ends
+            ')' if started => break,
+            ';' => ident.clear(),
+            '[' => {
+                let mut value = String::new();
+
+                loop {
+                    match chars.next() {
+                        Some('\\') => {
+                            if let Some(escaped) = chars.next() {
+                                value.push(escaped);
+                            }
+                        }
+                        Some(']') => break,
+                        Some(c) => value.push(c),
+                        None => {
+                            return Err(AppError {
+                                message: "Unterminated SGF property value".to_string(),
+                            })
+                        }
+                    }
+                }
+
+                match properties.last_mut() {
+                    // several values in a row belong to the same property
+                    Some((_, values)) if ident.is_empty() => values.push(value),
+                    _ => {
+                        if ident.is_empty() {
+                            return Err(AppError {
+                                message: "SGF property value without identifier".to_string(),
+                            });
+                        }
+                        properties.push((ident.clone(), vec![value]));
+                        ident.clear();
+                    }
+                }
+            }
+            c if c.is_ascii_uppercase() => ident.push(c),
+            _ => {}
+        }
+    }
+
+    if !started {
+        return Err(AppError {
+            message: "Not an SGF game tree".to_string(),
+        });
+    }
+
+    Ok(properties)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sgf_points_count_rows_from_the_top() {
+        assert_eq!("pd", to_sgf_point(&Coords::from(16, 16), 19));
+        assert_eq!("aa", to_sgf_point(&Coords::from(19, 1), 19));
+        assert_eq!("ss", to_sgf_point(&Coords::from(1, 19), 19));
+        assert_eq!(Some(Coords::from(16, 16)), from_sgf_point("pd", 19));
+        assert_eq!(None, from_sgf_point("zz", 19));
+    }
+
+    #[test]
+    fn handicap_stones_are_setup_and_moves_are_moves() {
+        let mut game = SgfGame::new(19);
+        game.komi = Some(0.5);
+        game.setup_black = vec![Coords::from(4, 4), Coords::from(16, 16)];
+        game.moves = vec![
+            SgfMove {
+                color: StoneColor::White,
+                coords: Some(Coords::from(17, 4)),
+            },
+            SgfMove {
+                color: StoneColor::Black,
+                coords: None,
+            },
+        ];
+
+        let sgf = write_sgf(&game);
+
+        assert!(sgf.contains("HA[2]AB[dp][pd]"));
+        assert!(sgf.contains(";W[dc];B[]"));
+        assert!(!sgf.contains("AE"));
+        assert_eq!(game, parse_sgf(&sgf).unwrap());
+        assert_eq!(sgf, write_sgf(&parse_sgf(&sgf).unwrap()));
+    }
+
+    #[test]
+    fn parse_follows_the_main_line_only() {
+        let game = parse_sgf("(;SZ[9]AB[aa:ab];B[ee](;W[cc];B[tt])(;W[gg]))").unwrap();
+
+        assert_eq!(9, game.board_size);
+        assert_eq!(
+            vec![Coords::from(9, 1), Coords::from(8, 1)],
+            game.setup_black
+        );
+        assert_eq!(3, game.moves.len());
+        assert_eq!(Some(Coords::from(7, 3)), game.moves[1].coords);
+    }
+
+    #[test]
+    fn markup_is_read_as_markers() {
+        let game = parse_sgf("(;SZ[9]TR[aa][bb];B[ee]SQ[ee]CR[ca:cb])").unwrap();
+
+        assert_eq!(
+            vec![
+                (Coords::from(9, 1), MarkerKind::Triangle),
+                (Coords::from(8, 2), MarkerKind::Triangle),
+                (Coords::from(5, 5), MarkerKind::Square),
+                (Coords::from(9, 3), MarkerKind::Circle),
+                (Coords::from(8, 3), MarkerKind::Circle),
+            ],
+            game.markup
+        );
+    }
+
+    #[test]
+    fn non_square_boards_are_rejected() {
+        assert!(parse_sgf("(;SZ[19:13])").is_err());
+        assert_eq!(13, parse_sgf("(;SZ[13:13])").unwrap().board_size);
+    }
+}
@@ -0,0 +1,89 @@
+use super::errors::AppError;
+use crate::core::engine::Engine;
+use crate::core::title;
+use log::warn;
+use signal_hook::consts::{SIGINT, SIGTERM};
+use signal_hook::iterator::Signals;
+use std::io::{self, Write};
+use std::process;
+use std::sync::{Arc, Mutex, OnceLock};
+use std::thread;
+use termios::{tcsetattr, Termios, TCSANOW};
+
+/// Disables mouse reporting, shows the cursor and leaves the alternate screen
+const RESTORE_SEQUENCE: &str = "\x1b[?1000l\x1b[?1002l\x1b[?1006l\x1b[?25h\x1b[?1049l";
+
+static ENGINE: OnceLock<Arc<Mutex<Engine>>> = OnceLock::new();
+
+/// Restores the terminal and quits the engine on SIGINT/SIGTERM, which would otherwise leave
+/// the terminal in raw mode on the alternate screen. Must be called before the UI starts, since
+/// the terminal mode saved here is the one restored.
+pub fn install_terminal_restore() -> Result<(), AppError> {
+    let stdin_fd = 0;
+    let original_mode = Termios::from_fd(stdin_fd).map_err(|e| AppError {
+        message: format!("Can't read the terminal mode: {}", e),
+    })?;
+    let mut signals = Signals::new([SIGINT, SIGTERM]).map_err(|e| AppError {
+        message: format!("Can't install the signal handler: {}", e),
+    })?;
+
+    thread::spawn(move || {
+        if let Some(signal) = signals.forever().next() {
+            title::restore_original(&mut io::stdout());
+            cleanup(
+                &mut io::stdout(),
+                || {
+                    if let Err(e) = tcsetattr(stdin_fd, TCSANOW, &original_mode) {
+                        warn!("can't restore the terminal mode: {}", e);
+                    }
+                },
+                quit_engine,
+            );
+
+            process::exit(128 + signal);
+        }
+    });
+
+    Ok(())
+}
+
+/// Lets the signal handler quit the engine, the engine is only created once the UI starts
+pub fn register_engine(engine: Arc<Mutex<Engine>>) {
+    let _ = ENGINE.set(engine);
+}
+
+fn quit_engine() {
+    // the engine may be locked by a long genmove, it gets EOF on stdin once we exit anyway
+    if let Some(Ok(mut engine)) = ENGINE.get().map(|engine| engine.try_lock()) {
+        engine.quit();
+    }
+}
+
+fn cleanup<W: Write>(out: &mut W, restore_mode: impl FnOnce(), quit_engine: impl FnOnce()) {
+    let _ = out.write_all(RESTORE_SEQUENCE.as_bytes());
+    let _ = out.flush();
+    restore_mode();
+    quit_engine();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::RefCell;
+
+    #[test]
+    fn cleanup_restores_the_terminal_before_quitting_the_engine() {
+        let steps = RefCell::new(vec![]);
+        let mut out: Vec<u8> = vec![];
+
+        cleanup(
+            &mut out,
+            || steps.borrow_mut().push("restore mode"),
+            || steps.borrow_mut().push("quit engine"),
+        );
+
+        assert_eq!(RESTORE_SEQUENCE.as_bytes(), &out[..]);
+        assert!(String::from_utf8(out).unwrap().ends_with("\x1b[?1049l"));
+        assert_eq!(vec!["restore mode", "quit engine"], steps.into_inner());
+    }
+}
@@ -0,0 +1,176 @@
+use super::errors::AppError;
+use crate::core::entities::{Stone, StoneColor};
+use log::{info, warn};
+use serde::Serialize;
+use std::io::Write;
+#[cfg(test)]
+use std::net::SocketAddr;
+use std::net::{TcpListener, TcpStream};
+use std::sync::mpsc::{self, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+#[derive(Debug, Serialize)]
+pub struct GameStatus<'a> {
+    pub board_size: u8,
+    pub to_move: StoneColor,
+    pub black_stones: &'a [Stone],
+    pub white_stones: &'a [Stone],
+    /// Stones captured by black
+    pub black_captures: usize,
+    /// Stones captured by white
+    pub white_captures: usize,
+    pub last_move: Option<LastMove>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct LastMove {
+    pub color: StoneColor,
+    /// GTP vertex such as `D4`, or `pass`/`resign`
+    pub vertex: String,
+}
+
+/// Broadcasts the game status as one JSON document per line to every connected client. The
+/// writes happen on a thread of their own, so a slow client doesn't hold up the UI.
+#[derive(Debug)]
+pub struct StatusServer {
+    #[cfg(test)]
+    local_addr: SocketAddr,
+    lines: Sender<String>,
+}
+
+impl StatusServer {
+    pub fn bind(addr: &str) -> Result<Self, AppError> {
+        let listener = TcpListener::bind(addr).map_err(|e| AppError {
+            message: format!(
+                "Error binding the status server to '{}': {}",
+                addr,
+                &e.to_string()
+            ),
+        })?;
+        let local_addr = listener.local_addr().map_err(|e| AppError {
+            message: format!(
+                "Error binding the status server to '{}': {}",
+                addr,
+                &e.to_string()
+            ),
+        })?;
+        let clients: Arc<Mutex<Vec<TcpStream>>> = Arc::new(Mutex::new(vec![]));
+        let last_status: Arc<Mutex<Option<String>>> = Arc::new(Mutex::new(None));
+
+        info!("serving game status on {}", local_addr);
+
+        let (lines, pending_lines) = mpsc::channel::<String>();
+        let accept_clients = clients.clone();
+        let accept_last_status = last_status.clone();
+
+        thread::spawn(move || {
+            for stream in listener.incoming() {
+                match stream {
+                    Ok(mut stream) => {
+                        // new clients get the current state right away
+                        if let Some(line) = accept_last_status.lock().unwrap().as_ref() {
+                            if stream.write_all(line.as_bytes()).is_err() {
+                                continue;
+                            }
+                        }
+                        accept_clients.lock().unwrap().push(stream);
+                    }
+                    Err(e) => warn!("status server: failed to accept client: {}", e),
+                }
+            }
+        });
+
+        // ends once the server is dropped
+        thread::spawn(move || {
+            for line in pending_lines {
+                // clients that went away are dropped on the first failed write
+                clients
+                    .lock()
+                    .unwrap()
+                    .retain(|mut client| client.write_all(line.as_bytes()).is_ok());
+
+                *last_status.lock().unwrap() = Some(line);
+            }
+        });
+
+        Ok(Self {
+            #[cfg(test)]
+            local_addr,
+            lines,
+        })
+    }
+
+    #[cfg(test)]
+    pub fn local_addr(&self) -> SocketAddr {
+        self.local_addr
+    }
+
+    pub fn publish(&self, status: &GameStatus) {
+        let line = match serde_json::to_string(status) {
+            Ok(json) => format!("{}\n", json),
+            Err(e) => {
+                warn!("status server: failed to serialize status: {}", e);
+                return;
+            }
+        };
+
+        if self.lines.send(line).is_err() {
+            warn!("status server: the writer thread is gone");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{BufRead, BufReader};
+    use std::time::Duration;
+
+    #[test]
+    fn binding_a_port_in_use_is_an_error() {
+        let server = StatusServer::bind("127.0.0.1:0").unwrap();
+        let addr = server.local_addr().to_string();
+
+        let app_error = StatusServer::bind(&addr).unwrap_err();
+        assert!(app_error
+            .message
+            .starts_with(&format!("Error binding the status server to '{}'", addr)));
+    }
+
+    #[test]
+    fn connected_clients_receive_published_status() {
+        let server = StatusServer::bind("127.0.0.1:0").unwrap();
+        let client = TcpStream::connect(server.local_addr()).unwrap();
+
+        // give the accept thread time to register the client
+        thread::sleep(Duration::from_millis(50));
+
+        let black_stones = vec![Stone {
+            color: StoneColor::Black,
+            row: 4,
+            col: 4,
+        }];
+
+        server.publish(&GameStatus {
+            board_size: 9,
+            to_move: StoneColor::White,
+            black_stones: &black_stones,
+            white_stones: &[],
+            black_captures: 0,
+            white_captures: 2,
+            last_move: Some(LastMove {
+                color: StoneColor::Black,
+                vertex: "D4".to_string(),
+            }),
+        });
+
+        let mut line = String::new();
+        BufReader::new(client).read_line(&mut line).unwrap();
+
+        assert_eq!(
+            "{\"board_size\":9,\"to_move\":\"white\",\"black_stones\":[{\"color\":\"black\",\"row\":4,\"col\":4}],\"white_stones\":[],\"black_captures\":0,\"white_captures\":2,\"last_move\":{\"color\":\"black\",\"vertex\":\"D4\"}}\n",
+            line
+        );
+    }
+}
@@ -1,4 +1,6 @@
-use crate::core::helpers::parse_color;
+use crate::core::config::ThemeConfig;
+use crate::core::errors::AppError;
+use crate::core::helpers::{parse_color, rgb};
 use iced_native::Color;
 use iced_tui::Style;
 
@@ -13,6 +15,8 @@ pub struct Theme {
     pub intersection_star_color: Color,
     pub white_stone_char: String,
     pub black_stone_char: String,
+    /// Replaces the stone char of dead stones, which keep their color
+    pub dead_stone_char: String,
     pub white_stone_color: Color,
     pub black_stone_color: Color,
     pub intersection_horiz_char: String,
@@ -21,28 +25,393 @@ pub struct Theme {
     pub error_message_bg: Color,
     pub loading_label_fg: Color,
     pub loading_label_bg: Color,
+    pub black_territory_color: Color,
+    pub white_territory_color: Color,
+    pub dame_color: Color,
+    pub status_idle_color: Color,
+    pub status_busy_color: Color,
+    pub status_error_color: Color,
+    /// Highlighted intersection when the player can play there
+    pub legal_highlight_color: Color,
+    /// Highlighted intersection when it's occupied, suicide or ko
+    pub illegal_highlight_color: Color,
+    /// Menu entries that can't be used with the current engine
+    pub disabled_fg_color: Color,
+    pub hint_marker_color: Color,
+    pub atari_marker_color: Color,
+    /// SGF markup (triangles, squares and circles)
+    pub markup_color: Color,
+    /// Behind the stone of the latest move
+    pub last_move_color: Color,
+    /// Behind the stone of the move before it, the opponent's previous one
+    pub previous_move_color: Color,
 }
 
-impl Default for Theme {
-    fn default() -> Self {
+/// Names accepted by [`Theme::preset`], in cycling order
+pub const THEME_PRESETS: [&str; 4] = ["classic", "dark", "high_contrast", "paper"];
+
+impl Theme {
+    pub fn preset(name: &str) -> Result<Theme, AppError> {
+        match name {
+            "classic" => Ok(Theme::classic()),
+            "dark" => Ok(Theme {
+                board_bg_color: rgb(0x2b2b2b),
+                board_bg_hl_color: rgb(0x4a4a4a),
+                text_fg_color: rgb(0xd0d0d0),
+                intersection_star_color: rgb(0x9e9e9e),
+                intersection_color: rgb(0x6e6e6e),
+                white_stone_color: rgb(0xf5f5f5),
+                black_stone_color: rgb(0x0a0a0a),
+                black_territory_color: rgb(0x1a1a1a),
+                white_territory_color: rgb(0x5c5c5c),
+                ..Theme::classic()
+            }),
+            "high_contrast" => Ok(Theme {
+                board_bg_color: rgb(0xffff00),
+                board_bg_hl_color: rgb(0x00ffff),
+                text_fg_color: rgb(0x000000),
+                intersection_star_color: rgb(0xff0000),
+                intersection_color: rgb(0x000000),
+                white_stone_color: rgb(0xffffff),
+                black_stone_color: rgb(0x000000),
+                black_territory_color: rgb(0x808000),
+                white_territory_color: rgb(0xffffa0),
+                ..Theme::classic()
+            }),
+            "paper" => Ok(Theme {
+                board_bg_color: rgb(0xf4efe1),
+                board_bg_hl_color: rgb(0xe0d8c0),
+                text_fg_color: rgb(0x3a3a3a),
+                intersection_star_color: rgb(0x3a3a3a),
+                intersection_color: rgb(0x9a9486),
+                white_stone_color: rgb(0xffffff),
+                black_stone_color: rgb(0x202020),
+                black_territory_color: rgb(0xc9c1ab),
+                white_territory_color: rgb(0xfffaf0),
+                ..Theme::classic()
+            }),
+            _ => Err(AppError {
+                message: format!(
+                    "Unknown theme preset '{}', expected one of: {}",
+                    name,
+                    THEME_PRESETS.join(", ")
+                ),
+            }),
+        }
+    }
+
+    /// Swaps the stone and grid glyphs for ASCII ones, each on its own since a font may have
+    /// one but not the other
+    pub fn with_ascii(self, stones: bool, grid: bool) -> Theme {
+        let mut theme = self;
+
+        if stones {
+            theme.black_stone_char = "X".to_string();
+            theme.white_stone_char = "O".to_string();
+            theme.dead_stone_char = "#".to_string();
+        }
+        if grid {
+            theme.intersection_char = "+".to_string();
+            theme.intersection_star_char = "*".to_string();
+            theme.intersection_horiz_char = "-".to_string();
+        }
+
+        theme
+    }
+
+    /// Applies the `theme` section of the config, naming the field of a color that can't be
+    /// parsed
+    pub fn with_overrides(self, theme_config: &ThemeConfig) -> Result<Theme, AppError> {
+        let mut theme = self;
+
+        for (name, value, color) in [
+            (
+                "board_bg_color",
+                &theme_config.board_bg_color,
+                &mut theme.board_bg_color,
+            ),
+            (
+                "board_bg_hl_color",
+                &theme_config.board_bg_hl_color,
+                &mut theme.board_bg_hl_color,
+            ),
+            (
+                "text_fg_color",
+                &theme_config.text_fg_color,
+                &mut theme.text_fg_color,
+            ),
+            (
+                "intersection_star_color",
+                &theme_config.intersection_star_color,
+                &mut theme.intersection_star_color,
+            ),
+            (
+                "intersection_color",
+                &theme_config.intersection_color,
+                &mut theme.intersection_color,
+            ),
+            (
+                "white_stone_color",
+                &theme_config.white_stone_color,
+                &mut theme.white_stone_color,
+            ),
+            (
+                "black_stone_color",
+                &theme_config.black_stone_color,
+                &mut theme.black_stone_color,
+            ),
+            (
+                "error_message_fg",
+                &theme_config.error_message_fg,
+                &mut theme.error_message_fg,
+            ),
+            (
+                "error_message_bg",
+                &theme_config.error_message_bg,
+                &mut theme.error_message_bg,
+            ),
+            (
+                "loading_label_fg",
+                &theme_config.loading_label_fg,
+                &mut theme.loading_label_fg,
+            ),
+            (
+                "loading_label_bg",
+                &theme_config.loading_label_bg,
+                &mut theme.loading_label_bg,
+            ),
+            (
+                "black_territory_color",
+                &theme_config.black_territory_color,
+                &mut theme.black_territory_color,
+            ),
+            (
+                "white_territory_color",
+                &theme_config.white_territory_color,
+                &mut theme.white_territory_color,
+            ),
+            (
+                "dame_color",
+                &theme_config.dame_color,
+                &mut theme.dame_color,
+            ),
+            (
+                "status_idle_color",
+                &theme_config.status_idle_color,
+                &mut theme.status_idle_color,
+            ),
+            (
+                "status_busy_color",
+                &theme_config.status_busy_color,
+                &mut theme.status_busy_color,
+            ),
+            (
+                "status_error_color",
+                &theme_config.status_error_color,
+                &mut theme.status_error_color,
+            ),
+            (
+                "legal_highlight_color",
+                &theme_config.legal_highlight_color,
+                &mut theme.legal_highlight_color,
+            ),
+            (
+                "illegal_highlight_color",
+                &theme_config.illegal_highlight_color,
+                &mut theme.illegal_highlight_color,
+            ),
+            (
+                "disabled_fg_color",
+                &theme_config.disabled_fg_color,
+                &mut theme.disabled_fg_color,
+            ),
+            (
+                "hint_marker_color",
+                &theme_config.hint_marker_color,
+                &mut theme.hint_marker_color,
+            ),
+            (
+                "atari_marker_color",
+                &theme_config.atari_marker_color,
+                &mut theme.atari_marker_color,
+            ),
+            (
+                "markup_color",
+                &theme_config.markup_color,
+                &mut theme.markup_color,
+            ),
+            (
+                "last_move_color",
+                &theme_config.last_move_color,
+                &mut theme.last_move_color,
+            ),
+            (
+                "previous_move_color",
+                &theme_config.previous_move_color,
+                &mut theme.previous_move_color,
+            ),
+        ] {
+            if let Some(value) = value {
+                *color = parse_color(value).map_err(|app_error| AppError {
+                    message: format!("Invalid theme color {}: {}", name, app_error.message),
+                })?;
+            }
+        }
+
+        for (value, glyph) in [
+            (
+                &theme_config.intersection_char,
+                &mut theme.intersection_char,
+            ),
+            (
+                &theme_config.intersection_star_char,
+                &mut theme.intersection_star_char,
+            ),
+            (
+                &theme_config.intersection_horiz_char,
+                &mut theme.intersection_horiz_char,
+            ),
+            (&theme_config.white_stone_char, &mut theme.white_stone_char),
+            (&theme_config.black_stone_char, &mut theme.black_stone_char),
+            (&theme_config.dead_stone_char, &mut theme.dead_stone_char),
+        ] {
+            if let Some(value) = value {
+                *glyph = value.clone();
+            }
+        }
+
+        Ok(theme)
+    }
+
+    /// Preset that follows `name` in [`THEME_PRESETS`], wrapping around
+    pub fn next_preset_name(name: &str) -> &'static str {
+        let position = THEME_PRESETS.iter().position(|p| *p == name).unwrap_or(0);
+
+        THEME_PRESETS[(position + 1) % THEME_PRESETS.len()]
+    }
+
+    fn classic() -> Theme {
         Theme {
-            board_bg_color: parse_color("#af9769").unwrap(),
-            board_bg_hl_color: parse_color("#E3C388").unwrap(),
-            text_fg_color: parse_color("#1c1f25").unwrap(),
+            board_bg_color: rgb(0xaf9769),
+            board_bg_hl_color: rgb(0xe3c388),
+            text_fg_color: rgb(0x1c1f25),
             header_text_style: Style::default().bold(),
             intersection_char: "┼".to_string(),
             intersection_star_char: "╋".to_string(),
-            intersection_star_color: parse_color("#7d6c4b").unwrap(),
+            intersection_star_color: rgb(0x7d6c4b),
             intersection_horiz_char: "─".to_string(),
-            intersection_color: parse_color("#7d6c4b").unwrap(),
+            intersection_color: rgb(0x7d6c4b),
             white_stone_char: "●".to_string(),
             black_stone_char: "●".to_string(),
-            white_stone_color: parse_color("#FFFFFF").unwrap(),
-            black_stone_color: parse_color("#000000").unwrap(),
-            error_message_fg: parse_color("#FFFFFF").unwrap(),
-            error_message_bg: parse_color("#FF0000").unwrap(),
-            loading_label_fg: parse_color("#FFFFFF").unwrap(),
-            loading_label_bg: parse_color("#00FF00").unwrap(),
+            dead_stone_char: "×".to_string(),
+            white_stone_color: rgb(0xffffff),
+            black_stone_color: rgb(0x000000),
+            error_message_fg: rgb(0xffffff),
+            error_message_bg: rgb(0xff0000),
+            loading_label_fg: rgb(0xffffff),
+            loading_label_bg: rgb(0x00ff00),
+            black_territory_color: rgb(0x8f7b55),
+            white_territory_color: rgb(0xcdb583),
+            dame_color: rgb(0xc0392b),
+            status_idle_color: rgb(0x2ecc40),
+            status_busy_color: rgb(0xf1c40f),
+            status_error_color: rgb(0xe74c3c),
+            legal_highlight_color: rgb(0x3d9970),
+            illegal_highlight_color: rgb(0xc0392b),
+            disabled_fg_color: rgb(0x808080),
+            hint_marker_color: rgb(0x0074d9),
+            atari_marker_color: rgb(0xff851b),
+            markup_color: rgb(0x1c1f25),
+            last_move_color: rgb(0xd62828),
+            previous_move_color: rgb(0x7a8b99),
         }
     }
 }
+
+impl Default for Theme {
+    fn default() -> Self {
+        Theme::classic()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn every_preset_builds() {
+        for name in THEME_PRESETS {
+            assert!(Theme::preset(name).is_ok(), "preset {} failed", name);
+        }
+    }
+
+    #[test]
+    fn default_theme_colors_match_their_hex_strings() {
+        let theme = Theme::default();
+
+        assert_eq!(parse_color("#af9769").unwrap(), theme.board_bg_color);
+        assert_eq!(parse_color("#E3C388").unwrap(), theme.board_bg_hl_color);
+        assert_eq!(parse_color("#1c1f25").unwrap(), theme.text_fg_color);
+        assert_eq!(parse_color("#7d6c4b").unwrap(), theme.intersection_color);
+        assert_eq!(parse_color("#FFFFFF").unwrap(), theme.white_stone_color);
+        assert_eq!(parse_color("#000000").unwrap(), theme.black_stone_color);
+        assert_eq!(parse_color("#FF0000").unwrap(), theme.error_message_bg);
+        assert_eq!(parse_color("#00FF00").unwrap(), theme.loading_label_bg);
+    }
+
+    #[test]
+    fn overrides_replace_only_the_fields_set() {
+        let theme_config = ThemeConfig {
+            board_bg_color: Some("#202020".to_string()),
+            black_stone_char: Some("@".to_string()),
+            ..ThemeConfig::default()
+        };
+
+        let theme = Theme::default().with_overrides(&theme_config).unwrap();
+
+        assert_eq!(rgb(0x202020), theme.board_bg_color);
+        assert_eq!("@", theme.black_stone_char);
+        assert_eq!(Theme::default().board_bg_hl_color, theme.board_bg_hl_color);
+        assert_eq!("●", theme.white_stone_char);
+
+        let bad_color = ThemeConfig {
+            dame_color: Some("reddish".to_string()),
+            ..ThemeConfig::default()
+        };
+        assert_eq!(
+            "Invalid theme color dame_color: Can't parse color reddish",
+            Theme::default()
+                .with_overrides(&bad_color)
+                .unwrap_err()
+                .message
+        );
+    }
+
+    #[test]
+    fn ascii_grid_keeps_unicode_stones() {
+        let theme = Theme::default().with_ascii(false, true);
+
+        assert_eq!("●", theme.black_stone_char);
+        assert_eq!("●", theme.white_stone_char);
+        assert_eq!("+", theme.intersection_char);
+        assert_eq!("*", theme.intersection_star_char);
+        assert_eq!("-", theme.intersection_horiz_char);
+
+        let theme = Theme::default().with_ascii(true, false);
+
+        assert_eq!("X", theme.black_stone_char);
+        assert_eq!("O", theme.white_stone_char);
+        assert_eq!("┼", theme.intersection_char);
+    }
+
+    #[test]
+    fn unknown_preset_is_an_error() {
+        assert!(Theme::preset("neon").is_err());
+    }
+
+    #[test]
+    fn next_preset_wraps_around() {
+        assert_eq!("dark", Theme::next_preset_name("classic"));
+        assert_eq!("classic", Theme::next_preset_name("paper"));
+    }
+}
@@ -0,0 +1,93 @@
+use crate::core::entities::StoneColor;
+use std::io::Write;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// Saves the current title on the terminal's title stack (xterm `CSI 22 t`)
+const PUSH_TITLE: &str = "\x1b[22;0t";
+/// Restores the title saved by [`PUSH_TITLE`]
+const POP_TITLE: &str = "\x1b[23;0t";
+
+static ORIGINAL_SAVED: AtomicBool = AtomicBool::new(false);
+
+/// Terminal window title, only written when it changes. The original title is saved before the
+/// first change and restored by [`restore_original`].
+#[derive(Debug, Default)]
+pub struct TerminalTitle {
+    current: Option<String>,
+}
+
+impl TerminalTitle {
+    pub fn set<W: Write>(&mut self, out: &mut W, title: &str) {
+        if self.current.as_deref() == Some(title) {
+            return;
+        }
+
+        if !ORIGINAL_SAVED.swap(true, Ordering::SeqCst) {
+            let _ = out.write_all(PUSH_TITLE.as_bytes());
+        }
+        // OSC 0 sets both the window and the icon title
+        let _ = write!(out, "\x1b]0;{}\x07", title);
+        let _ = out.flush();
+
+        self.current = Some(title.to_string());
+    }
+}
+
+/// Puts back the title the terminal had before the game, if it was changed
+pub fn restore_original<W: Write>(out: &mut W) {
+    if ORIGINAL_SAVED.swap(false, Ordering::SeqCst) {
+        let _ = out.write_all(POP_TITLE.as_bytes());
+        let _ = out.flush();
+    }
+}
+
+pub fn format_title(to_move: StoneColor, moves: usize, result: Option<&str>) -> String {
+    match result {
+        Some(result) => format!("{} — Game over: {}", env!("CARGO_PKG_NAME"), result),
+        None => format!(
+            "{} — {} to move — move {}",
+            env!("CARGO_PKG_NAME"),
+            capitalize(to_move.name()),
+            moves
+        ),
+    }
+}
+
+fn capitalize(text: &str) -> String {
+    let mut chars = text.chars();
+
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn title_is_written_once_per_change_and_restored() {
+        let mut title = TerminalTitle::default();
+        let mut out: Vec<u8> = vec![];
+        let text = format_title(StoneColor::Black, 42, None);
+
+        title.set(&mut out, &text);
+        title.set(&mut out, &text);
+        restore_original(&mut out);
+
+        assert_eq!("gogame-term — Black to move — move 42", text);
+        assert_eq!(
+            format!("{}\x1b]0;{}\x07{}", PUSH_TITLE, text, POP_TITLE),
+            String::from_utf8(out).unwrap()
+        );
+    }
+
+    #[test]
+    fn finished_games_show_the_result() {
+        assert_eq!(
+            "gogame-term — Game over: W+R",
+            format_title(StoneColor::Black, 80, Some("W+R"))
+        );
+    }
+}
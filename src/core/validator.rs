@@ -0,0 +1,62 @@
+use super::errors::AppError;
+use crate::core::entities::{Coords, StoneColor};
+use crate::core::goban::Goban;
+
+/// Consulted before the player's move is sent to the engine, so rule variants (forbidden
+/// points, no passing...) can be added without changing the game loop. The error message is
+/// shown to the player as the reason.
+pub trait MoveValidator {
+    fn validate_play(
+        &self,
+        goban: &Goban,
+        color: StoneColor,
+        coords: Coords,
+    ) -> Result<(), AppError>;
+
+    fn validate_pass(&self, _color: StoneColor) -> Result<(), AppError> {
+        Ok(())
+    }
+}
+
+/// Plain Go rules as far as the position tells: occupied points, suicide and the ko point the
+/// game sets with [`Goban::with_ko`].
+#[derive(Debug, Default)]
+pub struct GobanValidator;
+
+impl MoveValidator for GobanValidator {
+    fn validate_play(
+        &self,
+        goban: &Goban,
+        color: StoneColor,
+        coords: Coords,
+    ) -> Result<(), AppError> {
+        goban.clone().play(color, coords).map(|_| ())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::entities::Stone;
+
+    #[test]
+    fn goban_validator_rejects_occupied_points() {
+        let goban = Goban::from_stones(
+            9,
+            &[Stone {
+                color: StoneColor::White,
+                row: 3,
+                col: 3,
+            }],
+        );
+
+        let result = GobanValidator.validate_play(&goban, StoneColor::Black, Coords::from(3, 3));
+        assert_eq!(
+            "Illegal move at C3: point is occupied",
+            result.unwrap_err().message
+        );
+        assert!(GobanValidator
+            .validate_play(&goban, StoneColor::Black, Coords::from(4, 4))
+            .is_ok());
+    }
+}
@@ -0,0 +1,117 @@
+use crate::core::entities::{Stone, StoneColor};
+use crate::core::errors::AppError;
+use crate::core::theme::Theme;
+use crate::gogame::board::Board;
+use iced_native::layout::{self, Layout};
+use iced_native::{Point, Rectangle, Size};
+use iced_tui::TuiRenderer;
+use std::time::{Duration, Instant};
+
+const BENCH_BOARD_SIZE: u8 = 19;
+
+/// Times `frames` full board rebuilds (stones replaced, the whole view built again, laid out and
+/// drawn) and prints the mean and p95 frame time
+pub fn bench_render(frames: &str) -> Result<(), AppError> {
+    let frames: usize = frames.parse().map_err(|_| AppError {
+        message: format!("Invalid number of frames '{}'", frames),
+    })?;
+    let mut board = Board::new(BENCH_BOARD_SIZE, Theme::default());
+    let mut renderer = TuiRenderer::default();
+    let size = Size::new(board.width() as f32, board.height() as f32);
+    let viewport = Rectangle::with_size(size);
+    let mut frame_times: Vec<Duration> = Vec::with_capacity(frames);
+
+    for frame in 0..frames {
+        let (black_stones, white_stones) = bench_position(frame);
+        let start_instant = Instant::now();
+
+        board.set_stones(black_stones, white_stones);
+        let element = board.view();
+        let node = element.layout(&renderer, &layout::Limits::new(Size::ZERO, size));
+        element.draw(
+            &mut renderer,
+            &Default::default(),
+            Layout::new(&node),
+            Point::ORIGIN,
+            &viewport,
+        );
+
+        frame_times.push(start_instant.elapsed());
+    }
+
+    match frame_stats(&mut frame_times) {
+        Some((mean, p95)) => println!(
+            "{} frames, mean {}µs, p95 {}µs",
+            frames,
+            mean.as_micros(),
+            p95.as_micros()
+        ),
+        None => println!("no frames rendered"),
+    }
+
+    Ok(())
+}
+
+/// Half filled board in a checkered pattern, shifted each frame so every row changes
+fn bench_position(frame: usize) -> (Vec<Stone>, Vec<Stone>) {
+    let mut black_stones = vec![];
+    let mut white_stones = vec![];
+
+    for row in 1..=BENCH_BOARD_SIZE {
+        for col in 1..=BENCH_BOARD_SIZE {
+            match (row as usize + col as usize + frame) % 4 {
+                0 => black_stones.push(Stone {
+                    color: StoneColor::Black,
+                    row,
+                    col,
+                }),
+                1 => white_stones.push(Stone {
+                    color: StoneColor::White,
+                    row,
+                    col,
+                }),
+                _ => {}
+            }
+        }
+    }
+
+    (black_stones, white_stones)
+}
+
+/// Mean and 95th percentile
+fn frame_stats(frame_times: &mut [Duration]) -> Option<(Duration, Duration)> {
+    if frame_times.is_empty() {
+        return None;
+    }
+
+    frame_times.sort();
+
+    let mean = frame_times.iter().sum::<Duration>() / frame_times.len() as u32;
+    let p95_index = ((frame_times.len() as f64 * 0.95).ceil() as usize).max(1) - 1;
+
+    Some((mean, frame_times[p95_index]))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn frame_stats_mean_and_p95() {
+        let mut frame_times: Vec<Duration> = (1..=100).rev().map(Duration::from_millis).collect();
+
+        assert_eq!(
+            Some((Duration::from_micros(50500), Duration::from_millis(95))),
+            frame_stats(&mut frame_times)
+        );
+        assert_eq!(None, frame_stats(&mut []));
+    }
+
+    #[test]
+    fn frames_must_be_a_number() {
+        assert_eq!(
+            "Invalid number of frames 'ten'",
+            bench_render("ten").unwrap_err().message
+        );
+    }
+}
@@ -1,8 +1,14 @@
-use crate::core::entities::{Coords, OptCoords, Stone, StoneColor};
+use crate::core::entities::{
+    Coords, MarkerKind, OptCoords, Stone, StoneColor, Territory, TerritoryOwner,
+};
+use crate::core::geometry::is_star_point;
+use crate::core::goban::Goban;
 use crate::core::helpers::get_column_name;
 use crate::core::theme::Theme;
 use crate::gogame::game_message::GameMessage;
-use iced_native::{Column, Container, Element, Row, Text};
+use crate::gogame::mouse_area::MouseArea;
+use core::convert::TryFrom;
+use iced_native::{Color, Column, Container, Element, Point, Row, Text};
 use iced_tui::{Style, TuiRenderer};
 
 #[derive(Debug, Clone)]
@@ -10,221 +16,1083 @@ pub struct Board {
     board_size: u8,
     number_column_size: u8,
     theme: Theme,
-    star_points: Vec<Coords>,
     white_stones: Vec<Stone>,
     black_stones: Vec<Stone>,
     highlight_coords: OptCoords,
+    /// Color the legality of the highlighted intersection is checked for
+    player_color: StoneColor,
+    territory: Option<Territory>,
+    /// Stones the engine judges dead, drawn apart without being taken off the board
+    dead_stones: Vec<Coords>,
+    /// Hints, atari warnings and SGF markup, any number at once
+    markers: Vec<(Coords, MarkerKind)>,
+    /// Point the last move made a ko of, and the color that can't retake there yet
+    ko: Option<(Coords, StoneColor)>,
+    /// Points of the latest moves, the last one first. Passes keep their slot as `None`.
+    recent_moves: Vec<Option<Coords>>,
+    /// Intersections packed next to each other, without the horizontal connectors
+    compact: bool,
+    /// Cells of each line (index 0 is row 1), only the lines affected by a change are rebuilt
+    lines: Vec<Vec<Cell>>,
+    /// `lines` with neighbouring cells of the same style merged, so a frame only creates one
+    /// widget per run instead of one per cell
+    runs: Vec<Vec<Cell>>,
+    /// Column labels, merged into runs like the lines
+    header: Vec<Cell>,
+}
+
+/// Text and colors of one piece of a board line
+#[derive(Debug, Clone, PartialEq)]
+struct Cell {
+    text: String,
+    fg: Option<Color>,
+    bg: Option<Color>,
+    bold: bool,
+}
+
+impl Cell {
+    fn plain(text: &str) -> Self {
+        Self {
+            text: text.to_string(),
+            fg: None,
+            bg: None,
+            bold: false,
+        }
+    }
+
+    fn same_style(&self, other: &Cell) -> bool {
+        (self.fg, self.bg, self.bold) == (other.fg, other.bg, other.bold)
+    }
+
+    fn style(&self) -> Style {
+        let mut style = Style::default();
+
+        if let Some(fg) = self.fg {
+            style = style.fg(fg);
+        }
+        if let Some(bg) = self.bg {
+            style = style.bg(bg);
+        }
+        if self.bold {
+            style = style.bold();
+        }
+
+        style
+    }
 }
 
 impl Board {
+    pub fn board_size(&self) -> u8 {
+        self.board_size
+    }
+
+    /// Columns taken by the board, labels included
+    pub fn width(&self) -> u16 {
+        line_number_width(self.board_size)
+            + self.board_size as u16 * self.cell_width()
+            + self.number_column_size as u16
+            + 1
+    }
+
+    /// Lines taken by the board, with the header and footer
+    pub fn height(&self) -> u16 {
+        self.board_size as u16 + 2
+    }
+
+    pub fn black_stones(&self) -> &[Stone] {
+        &self.black_stones
+    }
+
+    pub fn white_stones(&self) -> &[Stone] {
+        &self.white_stones
+    }
+
+    pub fn set_theme(&mut self, theme: Theme) {
+        self.theme = theme;
+        self.refresh_all_lines();
+    }
+
     pub fn highlight_coords(&mut self, coords: OptCoords) {
+        let dirty_lines = dirty_lines_for_highlight(self.highlight_coords, coords, self.board_size);
+
         self.highlight_coords = coords;
+        self.refresh_lines(&dirty_lines);
+        self.refresh_header();
     }
 
+    pub fn set_player_color(&mut self, player_color: StoneColor) {
+        self.player_color = player_color;
+
+        if let Some(row) = self.highlight_coords.row {
+            self.refresh_lines(&[row]);
+        }
+    }
+
+    /// Replaces the whole position, for loading and re-syncing. Moves are cheaper with
+    /// `add_stone` and `remove_stones`, which don't compare the old and new lists.
     pub fn set_stones(&mut self, black_stones: Vec<Stone>, white_stones: Vec<Stone>) {
+        let mut dirty_lines = changed_lines(&self.black_stones, &black_stones);
+        dirty_lines.append(&mut changed_lines(&self.white_stones, &white_stones));
+
         self.black_stones = black_stones;
         self.white_stones = white_stones;
+        self.position_changed(dirty_lines);
     }
 
-    pub fn get_valid_highlight_coords(&mut self) -> Option<Coords> {
-        match self.highlight_coords {
-            OptCoords {
-                row: Some(row),
-                col: Some(col),
-            } => Some(Coords { row, col }),
-            _ => None,
+    /// Puts a stone, replacing whatever was on its intersection
+    pub fn add_stone(&mut self, stone: Stone) {
+        self.black_stones
+            .retain(|s| (s.row, s.col) != (stone.row, stone.col));
+        self.white_stones
+            .retain(|s| (s.row, s.col) != (stone.row, stone.col));
+
+        match stone.color {
+            StoneColor::Black => self.black_stones.push(stone),
+            StoneColor::White => self.white_stones.push(stone),
+        }
+        self.position_changed(vec![stone.row]);
+    }
+
+    /// Takes captured stones off the board
+    pub fn remove_stones(&mut self, removed: &[Coords]) {
+        let kept = |stone: &Stone| !removed.contains(&Coords::from(stone.row, stone.col));
+
+        self.black_stones.retain(kept);
+        self.white_stones.retain(kept);
+        self.position_changed(removed.iter().map(|coords| coords.row).collect());
+    }
+
+    fn position_changed(&mut self, mut dirty_lines: Vec<u8>) {
+        // captures anywhere can change whether the highlighted point is legal
+        dirty_lines.extend(self.highlight_coords.row);
+
+        // the estimates are stale once the position changes
+        dirty_lines.extend(self.dead_stones.drain(..).map(|coords| coords.row));
+
+        if self.territory.take().is_some() {
+            self.refresh_all_lines();
+        } else {
+            dirty_lines.retain(|line| (1..=self.board_size).contains(line));
+            dirty_lines.sort_unstable();
+            dirty_lines.dedup();
+            self.refresh_lines(&dirty_lines);
+        }
+    }
+
+    pub fn set_compact(&mut self, compact: bool) {
+        self.compact = compact;
+        self.refresh_all_lines();
+    }
+
+    pub fn set_territory(&mut self, territory: Option<Territory>) {
+        self.territory = territory;
+        self.refresh_all_lines();
+    }
+
+    /// Replaces all the markers, only the lines holding an old or a new one are rebuilt
+    pub fn set_markers(&mut self, markers: Vec<(Coords, MarkerKind)>) {
+        let mut dirty_lines: Vec<u8> = self
+            .markers
+            .iter()
+            .chain(markers.iter())
+            .map(|(coords, _)| coords.row)
+            .filter(|row| (1..=self.board_size).contains(row))
+            .collect();
+        dirty_lines.sort_unstable();
+        dirty_lines.dedup();
+
+        self.markers = markers;
+        self.refresh_lines(&dirty_lines);
+    }
+
+    pub fn set_ko(&mut self, ko: Option<(Coords, StoneColor)>) {
+        self.ko = ko;
+
+        if let Some(row) = self.highlight_coords.row {
+            self.refresh_lines(&[row]);
         }
     }
+
+    pub fn set_recent_moves(&mut self, recent_moves: Vec<Option<Coords>>) {
+        let mut dirty_lines: Vec<u8> = self
+            .recent_moves
+            .iter()
+            .chain(recent_moves.iter())
+            .flatten()
+            .map(|coords| coords.row)
+            .filter(|row| (1..=self.board_size).contains(row))
+            .collect();
+        dirty_lines.sort_unstable();
+        dirty_lines.dedup();
+
+        self.recent_moves = recent_moves;
+        self.refresh_lines(&dirty_lines);
+    }
+
+    pub fn set_dead_stones(&mut self, dead_stones: Vec<Coords>) {
+        let mut dirty_lines: Vec<u8> = self
+            .dead_stones
+            .iter()
+            .chain(dead_stones.iter())
+            .map(|coords| coords.row)
+            .filter(|row| (1..=self.board_size).contains(row))
+            .collect();
+        dirty_lines.sort_unstable();
+        dirty_lines.dedup();
+
+        self.dead_stones = dead_stones;
+        self.refresh_lines(&dirty_lines);
+    }
+
+    pub fn is_showing_dead_stones(&self) -> bool {
+        !self.dead_stones.is_empty()
+    }
+
+    pub fn is_showing_territory(&self) -> bool {
+        self.territory.is_some()
+    }
+
+    /// `(black, white)` territory points of the estimate shown
+    pub fn territory_points(&self) -> Option<(usize, usize)> {
+        self.territory
+            .as_ref()
+            .map(|t| (t.black.len(), t.white.len()))
+    }
+
+    pub fn area_difference(&self) -> Option<i32> {
+        self.territory
+            .as_ref()
+            .map(|t| t.area_difference(self.black_stones.len(), self.white_stones.len()))
+    }
+
+    pub fn get_valid_highlight_coords(&mut self) -> Option<Coords> {
+        Coords::try_from(&self.highlight_coords).ok()
+    }
 }
 
 impl Board {
     pub fn new(board_size: u8, theme: Theme) -> Self {
-        Board {
+        let mut board = Board {
             board_size,
-            number_column_size: 2,
+            number_column_size: number_column_size(board_size),
             theme,
-            star_points: gen_star_points(board_size),
             white_stones: vec![],
             black_stones: vec![],
             highlight_coords: OptCoords::default(),
-        }
+            player_color: StoneColor::Black,
+            territory: None,
+            dead_stones: vec![],
+            markers: vec![],
+            ko: None,
+            recent_moves: vec![],
+            compact: false,
+            lines: vec![vec![]; board_size as usize],
+            runs: vec![vec![]; board_size as usize],
+            header: vec![],
+        };
+
+        board.refresh_all_lines();
+        board
     }
 
     pub fn view(&self) -> Element<GameMessage, TuiRenderer> {
-        let header_style = Style::default().bold();
-        let mut column = Column::new();
-        column = self.add_header_line(column, header_style);
+        let mut column = Column::new().push(runs_view(&self.header));
 
-        for line_number in 1..=self.board_size {
-            let mut line_stones = self
-                .white_stones
-                .iter()
-                .chain(self.black_stones.iter())
-                .filter(|stone| stone.row == line_number)
-                .copied()
-                .collect::<Vec<Stone>>();
+        // GTP row 1 is the bottom line, so rows are drawn from the top down
+        for line_runs in self.runs.iter().rev() {
+            column = column.push(runs_view(line_runs));
+        }
 
-            let mut line_star_points = self
-                .star_points
-                .iter()
-                .filter(|stone| stone.row == line_number)
-                .copied()
-                .collect::<Vec<Coords>>();
+        column = column.push(runs_view(&self.header));
 
-            line_stones.sort_by(|a, b| b.col.cmp(&a.col));
-            line_star_points.sort_by(|a, b| b.col.cmp(&a.col));
+        let board_size = self.board_size;
+        let cell_width = self.cell_width();
 
-            let mut line_stones_next: Option<Stone> = line_stones.pop();
-            let mut line_star_next: Option<Coords> = line_star_points.pop();
+        MouseArea::new(
+            Container::new(column).style(
+                Style::default()
+                    .bg(self.theme.board_bg_color)
+                    .fg(self.theme.text_fg_color),
+            ),
+        )
+        .on_move(move |position| {
+            GameMessage::BoardHovered(coords_at(board_size, cell_width, position))
+        })
+        .on_press(move |position| {
+            GameMessage::BoardClicked(coords_at(board_size, cell_width, position))
+        })
+        .into()
+    }
 
-            let line_focused = match self.highlight_coords.row {
-                Some(row) => row == line_number,
-                None => false,
-            };
+    fn build_line(&self, line_number: u8) -> Vec<Cell> {
+        let mut line_stones: Vec<Option<StoneColor>> = vec![None; self.board_size as usize + 1];
 
-            let mut line_style = Style::default();
+        for stone in self
+            .white_stones
+            .iter()
+            .chain(self.black_stones.iter())
+            .filter(|stone| stone.row == line_number)
+        {
+            line_stones[stone.col as usize] = Some(stone.color);
+        }
 
-            if line_focused {
-                line_style = line_style.bg(self.theme.board_bg_hl_color);
+        let line_focused = self.highlight_coords.row == Some(line_number);
+        let line_bg = if line_focused {
+            Some(self.theme.board_bg_hl_color)
+        } else {
+            None
+        };
+        let highlight_bg = self.highlight_legality().map(|legal| {
+            if legal {
+                self.theme.legal_highlight_color
+            } else {
+                self.theme.illegal_highlight_color
             }
+        });
 
-            let mut board_line = Row::new().push(Text::new(" ")).push(
-                Text::new(format!("{: >2} ", line_number)).font(line_style.merge(&header_style)),
-            );
-
-            for column_number in 1..=self.board_size {
-                let stone: Option<Stone> = match line_stones_next {
-                    Some(s) => {
-                        if s.col == column_number {
-                            line_stones_next = line_stones.pop();
-                            Some(s)
-                        } else {
-                            None
-                        }
-                    }
-                    None => None,
-                };
-
-                let is_star_point: bool = match line_star_next {
-                    Some(s) => {
-                        if s.col == column_number {
-                            line_star_next = line_star_points.pop();
-                            true
-                        } else {
-                            false
-                        }
-                    }
-                    None => false,
-                };
-
-                let column_focused = match self.highlight_coords.col {
-                    Some(col) => col == column_number,
-                    None => false,
-                };
-
-                let mut cell_style = Style::default().fg(self.theme.intersection_color);
-
-                if line_focused || column_focused {
-                    cell_style = cell_style.bg(self.theme.board_bg_hl_color);
-                }
+        let mut cells = vec![
+            Cell::plain(" "),
+            Cell {
+                text: format!(
+                    "{: >width$} ",
+                    line_number,
+                    width = self.number_column_size as usize
+                ),
+                fg: None,
+                bg: line_bg,
+                bold: true,
+            },
+        ];
+
+        for column_number in 1..=self.board_size {
+            let coords = Coords::from(line_number, column_number);
+            let stone = line_stones[column_number as usize];
+            let is_star_point = is_star_point(&coords, self.board_size);
+            let column_focused = self.highlight_coords.col == Some(column_number);
+
+            let mut cell = Cell {
+                text: match stone {
+                    Some(StoneColor::Black) => self.theme.black_stone_char.clone(),
+                    Some(StoneColor::White) => self.theme.white_stone_char.clone(),
+                    None if is_star_point => self.theme.intersection_star_char.clone(),
+                    None => self.theme.intersection_char.clone(),
+                },
+                fg: Some(match stone {
+                    Some(StoneColor::Black) => self.theme.black_stone_color,
+                    Some(StoneColor::White) => self.theme.white_stone_color,
+                    None if is_star_point => self.theme.intersection_star_color,
+                    None => self.theme.intersection_color,
+                }),
+                bg: if line_focused && column_focused {
+                    highlight_bg
+                } else if line_focused || column_focused {
+                    Some(self.theme.board_bg_hl_color)
+                } else {
+                    None
+                },
+                bold: false,
+            };
 
-                if let Some(stone) = stone {
-                    cell_style = match stone.color {
-                        StoneColor::Black => cell_style.fg(self.theme.black_stone_color),
-                        StoneColor::White => cell_style.fg(self.theme.white_stone_color),
-                    };
-                } else if is_star_point {
-                    cell_style = cell_style.fg(self.theme.intersection_star_color);
+            if stone.is_some() && self.dead_stones.contains(&coords) {
+                cell.text = self.theme.dead_stone_char.clone();
+            }
+
+            if stone.is_none() {
+                match self.territory.as_ref().and_then(|t| t.owner(&coords)) {
+                    Some(TerritoryOwner::Black) => cell.bg = Some(self.theme.black_territory_color),
+                    Some(TerritoryOwner::White) => cell.bg = Some(self.theme.white_territory_color),
+                    Some(TerritoryOwner::Neutral) => cell.fg = Some(self.theme.dame_color),
+                    None => {}
                 }
+            }
 
-                board_line = board_line.push(
-                    Text::new(match stone {
-                        Some(stone) => match stone.color {
-                            StoneColor::Black => self.theme.black_stone_char.clone(),
-                            StoneColor::White => self.theme.white_stone_char.clone(),
-                        },
-                        None => {
-                            if is_star_point {
-                                self.theme.intersection_star_char.clone()
-                            } else {
-                                self.theme.intersection_char.clone()
-                            }
-                        }
-                    })
-                    .font(cell_style),
-                );
-
-                if column_number < self.board_size {
-                    board_line = board_line.push(
-                        Text::new(self.theme.intersection_horiz_char.clone())
-                            .font(line_style.fg(self.theme.intersection_color)),
-                    )
+            if let Some((_, kind)) = self.markers.iter().find(|(c, _)| *c == coords) {
+                self.apply_marker(&mut cell, *kind, stone.is_some());
+            } else if stone.is_some() {
+                match self.recent_moves.iter().position(|c| *c == Some(coords)) {
+                    Some(0) => cell.bg = Some(self.theme.last_move_color),
+                    Some(_) => cell.bg = Some(self.theme.previous_move_color),
+                    None => {}
                 }
             }
 
-            board_line =
-                board_line.push(Text::new(format!(" {: <2}", line_number)).font(header_style));
+            cells.push(cell);
 
-            column = column.push(board_line);
+            if column_number < self.board_size && !self.compact {
+                cells.push(Cell {
+                    text: self.theme.intersection_horiz_char.clone(),
+                    fg: Some(self.theme.intersection_color),
+                    bg: line_bg,
+                    bold: false,
+                });
+            }
         }
 
-        column = self.add_header_line(column, header_style);
+        cells.push(Cell {
+            text: format!(
+                " {: <width$}",
+                line_number,
+                width = self.number_column_size as usize
+            ),
+            fg: None,
+            bg: None,
+            bold: true,
+        });
 
-        Container::new(column)
-            .style(
-                Style::default()
-                    .bg(self.theme.board_bg_color)
-                    .fg(self.theme.text_fg_color),
-            )
-            .into()
+        cells
+    }
+
+    /// Empty points show the marker glyph, stones are kept and get the marker color behind them
+    fn apply_marker(&self, cell: &mut Cell, kind: MarkerKind, on_stone: bool) {
+        let color = match kind {
+            MarkerKind::Hint => self.theme.hint_marker_color,
+            MarkerKind::Atari => self.theme.atari_marker_color,
+            MarkerKind::Triangle | MarkerKind::Square | MarkerKind::Circle => {
+                self.theme.markup_color
+            }
+        };
+
+        if on_stone {
+            cell.bg = Some(color);
+        } else {
+            cell.text = marker_glyph(kind).to_string();
+            cell.fg = Some(color);
+            cell.bold = true;
+        }
+    }
+
+    /// Whether the player can play on the highlighted intersection, `None` until both the row and
+    /// the column are chosen
+    fn highlight_legality(&self) -> Option<bool> {
+        let coords = Coords::try_from(&self.highlight_coords).ok()?;
+        let stones: Vec<Stone> = self
+            .black_stones
+            .iter()
+            .chain(self.white_stones.iter())
+            .copied()
+            .collect();
+
+        let ko = self
+            .ko
+            .filter(|(_, color)| *color == self.player_color)
+            .map(|(coords, _)| coords);
+
+        Some(
+            Goban::from_stones(self.board_size, &stones)
+                .with_ko(ko)
+                .is_legal(self.player_color, &coords),
+        )
+    }
+
+    /// Columns taken by each intersection, including the connector that follows it
+    fn cell_width(&self) -> u16 {
+        if self.compact {
+            1
+        } else {
+            2
+        }
     }
 
-    fn add_header_line<'a>(
-        &self,
-        column: Column<'a, GameMessage, TuiRenderer>,
-        header_style: Style,
-    ) -> Column<'a, GameMessage, TuiRenderer> {
-        let line_column_space_left = "    ";
-        let line_column_space_right = "   ";
+    fn refresh_lines(&mut self, line_numbers: &[u8]) {
+        for &line_number in line_numbers {
+            let line = self.build_line(line_number);
+            self.runs[line_number as usize - 1] = merge_runs(&line);
+            self.lines[line_number as usize - 1] = line;
+        }
+    }
+
+    fn refresh_all_lines(&mut self) {
+        let all_lines: Vec<u8> = (1..=self.board_size).collect();
+        self.refresh_lines(&all_lines);
+        self.refresh_header();
+    }
+
+    fn refresh_header(&mut self) {
+        let label = |text: String| Cell {
+            text,
+            fg: None,
+            bg: None,
+            bold: true,
+        };
+        let mut cells = vec![Cell::plain(
+            &" ".repeat(line_number_width(self.board_size) as usize),
+        )];
 
-        let mut header_line = Row::new().push(Text::new(line_column_space_left));
         for column_number in 1..=self.board_size {
-            let mut style = header_style;
+            let mut cell = label(get_column_name(column_number).to_string());
 
-            if let Some(hl_col) = self.highlight_coords.col {
-                if column_number == hl_col {
-                    style = style.bg(self.theme.board_bg_hl_color);
-                }
+            if self.highlight_coords.col == Some(column_number) {
+                cell.bg = Some(self.theme.board_bg_hl_color);
             }
+            cells.push(cell);
 
-            header_line = header_line
-                .push(Text::new(get_column_name(column_number)).font(style))
-                .push(Text::new(" "));
+            if !self.compact {
+                cells.push(label(" ".to_string()));
+            }
+        }
+        cells.push(label(" ".repeat(self.number_column_size as usize + 1)));
+
+        self.header = merge_runs(&cells);
+    }
+}
+
+#[cfg(test)]
+impl Board {
+    /// Puts stones on the board without going through the engine, so the rendering can be
+    /// checked deterministically
+    pub fn debug_set_position(&mut self, black_stones: Vec<Stone>, white_stones: Vec<Stone>) {
+        self.set_stones(black_stones, white_stones);
+        self.refresh_all_lines();
+    }
+
+    /// Board lines from the top down, as plain text
+    pub fn render_text(&self) -> Vec<String> {
+        self.lines
+            .iter()
+            .rev()
+            .map(|line| line.iter().map(|cell| cell.text.as_str()).collect())
+            .collect()
+    }
+
+    pub fn markers(&self) -> &[(Coords, MarkerKind)] {
+        &self.markers
+    }
+
+    fn cell_at(&self, coords: &Coords) -> &Cell {
+        // a leading space and the line number come before the first intersection
+        &self.lines[coords.row as usize - 1][2 + (coords.col as usize - 1) * 2]
+    }
+}
+
+/// Neighbouring cells of the same style joined into one
+fn merge_runs(cells: &[Cell]) -> Vec<Cell> {
+    let mut runs: Vec<Cell> = vec![];
+
+    for cell in cells {
+        match runs.last_mut() {
+            Some(run) if run.same_style(cell) => run.text.push_str(&cell.text),
+            _ => runs.push(cell.clone()),
         }
-        header_line = header_line.push(Text::new(line_column_space_right).font(header_style));
+    }
+
+    runs
+}
+
+fn runs_view<'a>(runs: &[Cell]) -> Row<'a, GameMessage, TuiRenderer> {
+    runs.iter().fold(Row::new(), |row, run| {
+        row.push(Text::new(run.text.clone()).font(run.style()))
+    })
+}
+
+/// Rows holding a stone that is in one of the lists but not in the other
+fn changed_lines(old_stones: &[Stone], new_stones: &[Stone]) -> Vec<u8> {
+    old_stones
+        .iter()
+        .filter(|stone| !new_stones.contains(stone))
+        .chain(
+            new_stones
+                .iter()
+                .filter(|stone| !old_stones.contains(stone)),
+        )
+        .map(|stone| stone.row)
+        .collect()
+}
+
+/// The highlighted line changes its background, and so does one cell of every line when the
+/// highlighted column moves
+fn dirty_lines_for_highlight(old: OptCoords, new: OptCoords, board_size: u8) -> Vec<u8> {
+    if old.col != new.col {
+        return (1..=board_size).collect();
+    }
+
+    if old.row == new.row {
+        return vec![];
+    }
+
+    old.row
+        .into_iter()
+        .chain(new.row)
+        .filter(|row| *row >= 1 && *row <= board_size)
+        .collect()
+}
+
+fn marker_glyph(kind: MarkerKind) -> &'static str {
+    match kind {
+        MarkerKind::Hint => "◆",
+        MarkerKind::Atari => "!",
+        MarkerKind::Triangle => "▲",
+        MarkerKind::Square => "■",
+        MarkerKind::Circle => "○",
+    }
+}
+
+/// Digits of the largest line number
+fn number_column_size(board_size: u8) -> u8 {
+    board_size.to_string().len() as u8
+}
+
+/// Width of the line number column on the left of the board, including padding
+fn line_number_width(board_size: u8) -> u16 {
+    number_column_size(board_size) as u16 + 2
+}
+
+/// How far from the middle of an intersection a position still snaps to it, in cells
+const SNAP_RADIUS: f32 = 1.0;
+
+/// Intersection nearest to a position relative to the board's top left corner, `None` when the
+/// position is on a label or outside the board. Connectors snap to the nearer intersection, since
+/// hitting a one cell wide intersection is fiddly. `cell_width` is 2 when intersections are
+/// followed by connectors, 1 on a compact board.
+fn coords_at(board_size: u8, cell_width: u16, position: Point) -> Option<Coords> {
+    if position.x < 0.0 || position.y < 0.0 {
+        return None;
+    }
+
+    // the first line is the column header
+    let y = position.y as u16;
 
-        column.push(header_line)
+    if y < 1 || y > board_size as u16 {
+        return None;
     }
+
+    // positions are compared to the middle of each intersection cell, a position on a cell's
+    // left edge (as terminals report them) belongs to that cell
+    let line_number_width = line_number_width(board_size) as f32;
+    let cell_width = cell_width as f32;
+    let index = ((position.x - line_number_width - 0.5) / cell_width + 0.5).floor();
+    let center = line_number_width + index * cell_width + 0.5;
+
+    if index < 0.0 || index >= board_size as f32 || (position.x - center).abs() > SNAP_RADIUS {
+        return None;
+    }
+
+    let row = board_size as u16 - (y - 1);
+    let col = index as u16 + 1;
+
+    Some(Coords::from(row as u8, col as u8))
 }
 
-fn gen_star_points(board_size: u8) -> Vec<Coords> {
-    let margin = if board_size >= 13 { 4u8 } else { 3u8 };
-    let middle = board_size / 2;
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn stone(color: StoneColor, row: u8, col: u8) -> Stone {
+        Stone { color, row, col }
+    }
+
+    fn fresh_lines(board: &Board) -> Vec<Vec<Cell>> {
+        (1..=board.board_size)
+            .map(|l| board.build_line(l))
+            .collect()
+    }
+
+    #[test]
+    fn moving_the_highlight_across_rows_dirties_old_and_new_rows() {
+        let old = OptCoords {
+            row: Some(3),
+            col: Some(2),
+        };
+        let new = OptCoords {
+            row: Some(5),
+            col: Some(2),
+        };
 
-    let mut points: Vec<Coords> = vec![
-        Coords::from(margin, margin),                  // top left
-        Coords::from(margin, board_size - margin + 1), // top right
-        Coords::from(board_size - margin + 1, margin), // bottom left
-        Coords::from(board_size - margin + 1, board_size - margin + 1), // bottom right
-        Coords::from(middle, middle),                  // middle point
-    ];
+        assert_eq!(vec![3, 5], dirty_lines_for_highlight(old, new, 9));
+        assert_eq!(
+            vec![3],
+            dirty_lines_for_highlight(old, OptCoords { row: None, ..old }, 9)
+        );
+        assert_eq!(
+            9,
+            dirty_lines_for_highlight(old, OptCoords::default(), 9).len()
+        );
+    }
 
-    if board_size >= 19 {
-        points.push(Coords::from(margin, middle)); // top horiz middle
-        points.push(Coords::from(board_size - margin + 1, middle)); // bottom horiz middle
-        points.push(Coords::from(middle, margin)); // left vertical middle
-        points.push(Coords::from(middle, board_size - margin + 1)); // right vertical middle
+    #[test]
+    fn cached_lines_match_a_full_rebuild() {
+        let mut board = Board::new(9, Theme::default());
+
+        board.highlight_coords(OptCoords {
+            row: Some(3),
+            col: Some(2),
+        });
+        board.set_stones(vec![stone(StoneColor::Black, 3, 3)], vec![]);
+        board.highlight_coords(OptCoords {
+            row: Some(5),
+            col: Some(2),
+        });
+        board.set_stones(
+            vec![stone(StoneColor::Black, 7, 7)],
+            vec![stone(StoneColor::White, 3, 4)],
+        );
+
+        assert_eq!(fresh_lines(&board), board.lines);
+        assert_eq!(
+            board
+                .lines
+                .iter()
+                .map(|line| merge_runs(line))
+                .collect::<Vec<_>>(),
+            board.runs
+        );
     }
 
-    points
+    #[test]
+    fn lines_are_drawn_as_runs_of_the_same_style() {
+        let board = Board::new(9, Theme::default());
+        let text = |cells: &[Cell]| {
+            cells
+                .iter()
+                .map(|cell| cell.text.as_str())
+                .collect::<String>()
+        };
+
+        // an empty line off the star points: the labels, then one run of lines and connectors
+        let runs = &board.runs[1];
+        assert_eq!(text(&board.lines[1]), text(runs));
+        assert_eq!(4, runs.len());
+        // the left padding, then the labels
+        assert_eq!(2, board.header.len());
+    }
+
+    #[test]
+    fn rendered_position_shows_stones_and_star_points() {
+        let theme = Theme::default();
+        let mut board = Board::new(5, theme.clone());
+
+        board.debug_set_position(
+            vec![stone(StoneColor::Black, 1, 1)],
+            vec![stone(StoneColor::White, 5, 5)],
+        );
+
+        assert_eq!(
+            vec![
+                " 5 ┼─┼─┼─┼─● 5",
+                " 4 ┼─┼─┼─┼─┼ 4",
+                " 3 ┼─┼─╋─┼─┼ 3",
+                " 2 ┼─┼─┼─┼─┼ 2",
+                " 1 ●─┼─┼─┼─┼ 1",
+            ],
+            board.render_text()
+        );
+        assert_eq!(
+            Some(theme.black_stone_color),
+            board.cell_at(&Coords::from(1, 1)).fg
+        );
+        assert_eq!(
+            Some(theme.white_stone_color),
+            board.cell_at(&Coords::from(5, 5)).fg
+        );
+    }
+
+    #[test]
+    fn rendered_territory_colors_only_empty_points() {
+        let theme = Theme::default();
+        let mut board = Board::new(5, theme.clone());
+
+        board.debug_set_position(vec![stone(StoneColor::Black, 1, 1)], vec![]);
+        board.set_territory(Some(Territory {
+            black: vec![Coords::from(1, 1), Coords::from(1, 2)],
+            white: vec![],
+            dame: vec![Coords::from(2, 2)],
+        }));
+
+        assert_eq!(None, board.cell_at(&Coords::from(1, 1)).bg);
+        assert_eq!(
+            Some(theme.black_territory_color),
+            board.cell_at(&Coords::from(1, 2)).bg
+        );
+        assert_eq!(
+            Some(theme.dame_color),
+            board.cell_at(&Coords::from(2, 2)).fg
+        );
+    }
+
+    #[test]
+    fn incremental_updates_match_a_full_replace() {
+        let mut board = Board::new(5, Theme::default());
+        board.debug_set_position(
+            vec![
+                stone(StoneColor::Black, 1, 2),
+                stone(StoneColor::Black, 2, 2),
+            ],
+            vec![
+                stone(StoneColor::White, 1, 1),
+                stone(StoneColor::White, 3, 2),
+            ],
+        );
+
+        // black A2 captures the white stone at A1
+        board.add_stone(stone(StoneColor::Black, 2, 1));
+        board.remove_stones(&[Coords::from(1, 1)]);
+
+        let mut replaced = Board::new(5, Theme::default());
+        replaced.debug_set_position(
+            vec![
+                stone(StoneColor::Black, 1, 2),
+                stone(StoneColor::Black, 2, 2),
+                stone(StoneColor::Black, 2, 1),
+            ],
+            vec![stone(StoneColor::White, 3, 2)],
+        );
+
+        assert_eq!(replaced.render_text(), board.render_text());
+        assert_eq!(fresh_lines(&board), board.lines);
+        assert_eq!(replaced.black_stones(), board.black_stones());
+        assert_eq!(replaced.white_stones(), board.white_stones());
+    }
+
+    #[test]
+    fn markers_render_together_without_clobbering_stones() {
+        let theme = Theme::default();
+        let mut board = Board::new(5, theme.clone());
+
+        board.debug_set_position(vec![stone(StoneColor::Black, 1, 1)], vec![]);
+        board.set_markers(vec![
+            (Coords::from(1, 1), MarkerKind::Atari),
+            (Coords::from(3, 4), MarkerKind::Hint),
+            (Coords::from(5, 2), MarkerKind::Triangle),
+        ]);
+
+        assert_eq!(
+            vec![
+                " 5 ┼─▲─┼─┼─┼ 5",
+                " 4 ┼─┼─┼─┼─┼ 4",
+                " 3 ┼─┼─╋─◆─┼ 3",
+                " 2 ┼─┼─┼─┼─┼ 2",
+                " 1 ●─┼─┼─┼─┼ 1",
+            ],
+            board.render_text()
+        );
+        assert_eq!(
+            Some(theme.black_stone_color),
+            board.cell_at(&Coords::from(1, 1)).fg
+        );
+        assert_eq!(
+            Some(theme.atari_marker_color),
+            board.cell_at(&Coords::from(1, 1)).bg
+        );
+        assert_eq!(
+            Some(theme.hint_marker_color),
+            board.cell_at(&Coords::from(3, 4)).fg
+        );
+
+        board.set_markers(vec![]);
+        assert_eq!(fresh_lines(&board), board.lines);
+        assert_eq!(" 3 ┼─┼─╋─┼─┼ 3", board.render_text()[2]);
+    }
+
+    #[test]
+    fn dead_stones_are_drawn_apart_until_the_position_changes() {
+        let theme = Theme::default();
+        let mut board = Board::new(5, theme.clone());
+        let black = vec![stone(StoneColor::Black, 1, 1)];
+        let white = vec![
+            stone(StoneColor::White, 3, 3),
+            stone(StoneColor::White, 5, 5),
+        ];
+
+        board.debug_set_position(black.clone(), white.clone());
+        board.set_dead_stones(vec![Coords::from(3, 3), Coords::from(4, 4)]);
+
+        assert_eq!(
+            vec![
+                " 5 ┼─┼─┼─┼─● 5",
+                " 4 ┼─┼─┼─┼─┼ 4",
+                " 3 ┼─┼─×─┼─┼ 3",
+                " 2 ┼─┼─┼─┼─┼ 2",
+                " 1 ●─┼─┼─┼─┼ 1",
+            ],
+            board.render_text()
+        );
+        assert_eq!(
+            Some(theme.white_stone_color),
+            board.cell_at(&Coords::from(3, 3)).fg
+        );
+        assert_eq!(black.as_slice(), board.black_stones());
+        assert_eq!(white.as_slice(), board.white_stones());
+
+        board.add_stone(stone(StoneColor::Black, 2, 2));
+        assert!(!board.is_showing_dead_stones());
+        assert_eq!(fresh_lines(&board), board.lines);
+    }
+
+    #[test]
+    fn the_two_latest_moves_are_marked_apart() {
+        let theme = Theme::default();
+        let mut board = Board::new(5, theme.clone());
+
+        board.debug_set_position(
+            vec![
+                stone(StoneColor::Black, 3, 3),
+                stone(StoneColor::Black, 1, 1),
+            ],
+            vec![stone(StoneColor::White, 4, 2)],
+        );
+        // black C3, white B4, then black A1
+        board.set_recent_moves(vec![Some(Coords::from(1, 1)), Some(Coords::from(4, 2))]);
+
+        assert_eq!(
+            Some(theme.last_move_color),
+            board.cell_at(&Coords::from(1, 1)).bg
+        );
+        assert_eq!(
+            Some(theme.previous_move_color),
+            board.cell_at(&Coords::from(4, 2)).bg
+        );
+        assert_eq!(None, board.cell_at(&Coords::from(3, 3)).bg);
+
+        board.set_recent_moves(vec![None, Some(Coords::from(1, 1))]);
+        assert_eq!(fresh_lines(&board), board.lines);
+        assert_eq!(
+            Some(theme.previous_move_color),
+            board.cell_at(&Coords::from(1, 1)).bg
+        );
+    }
+
+    #[test]
+    fn highlighted_point_shows_whether_the_move_is_legal() {
+        let theme = Theme::default();
+        let mut board = Board::new(5, theme.clone());
+        let highlight = |row, col| OptCoords {
+            row: Some(row),
+            col: Some(col),
+        };
+
+        board.debug_set_position(vec![stone(StoneColor::Black, 1, 1)], vec![]);
+
+        board.highlight_coords(highlight(1, 1));
+        assert_eq!(
+            Some(theme.illegal_highlight_color),
+            board.cell_at(&Coords::from(1, 1)).bg
+        );
+
+        board.highlight_coords(highlight(3, 3));
+        assert_eq!(
+            Some(theme.legal_highlight_color),
+            board.cell_at(&Coords::from(3, 3)).bg
+        );
+        assert_eq!(
+            Some(theme.board_bg_hl_color),
+            board.cell_at(&Coords::from(3, 1)).bg
+        );
+    }
+
+    #[test]
+    fn ko_point_is_illegal_for_the_color_that_cant_retake() {
+        let theme = Theme::default();
+        let mut board = Board::new(5, theme.clone());
+        let ko = Coords::from(2, 2);
+
+        board.debug_set_position(
+            vec![
+                stone(StoneColor::Black, 1, 3),
+                stone(StoneColor::Black, 3, 3),
+                stone(StoneColor::Black, 2, 4),
+            ],
+            vec![
+                stone(StoneColor::White, 1, 2),
+                stone(StoneColor::White, 2, 1),
+                stone(StoneColor::White, 3, 2),
+                stone(StoneColor::White, 2, 3),
+            ],
+        );
+        board.highlight_coords(OptCoords {
+            row: Some(2),
+            col: Some(2),
+        });
+        assert_eq!(Some(theme.legal_highlight_color), board.cell_at(&ko).bg);
+
+        board.set_ko(Some((ko, StoneColor::Black)));
+        assert_eq!(Some(theme.illegal_highlight_color), board.cell_at(&ko).bg);
+
+        board.set_ko(Some((ko, StoneColor::White)));
+        assert_eq!(Some(theme.legal_highlight_color), board.cell_at(&ko).bg);
+    }
+
+    #[test]
+    fn line_number_column_fits_the_board_size() {
+        assert_eq!(3, line_number_width(9));
+        assert_eq!(4, line_number_width(19));
+
+        let labels = |board: &Board| -> (String, String) {
+            let line = board.build_line(5);
+            (line[1].text.clone(), line[line.len() - 1].text.clone())
+        };
+
+        assert_eq!(
+            ("5 ".to_string(), " 5".to_string()),
+            labels(&Board::new(9, Theme::default()))
+        );
+        assert_eq!(
+            (" 5 ".to_string(), " 5 ".to_string()),
+            labels(&Board::new(19, Theme::default()))
+        );
+    }
+
+    #[test]
+    fn compact_board_drops_the_connectors() {
+        let mut board = Board::new(19, Theme::default());
+        let width = |board: &Board| board.render_text()[0].chars().count();
+
+        // leading space, line numbers on both sides and 19 intersections
+        assert_eq!(1 + 3 + 37 + 3, width(&board));
+
+        board.set_compact(true);
+        assert_eq!(1 + 3 + 19 + 3, width(&board));
+        assert_eq!(
+            Some(Coords::from(19, 2)),
+            coords_at(19, 1, Point::new(5.0, 1.0))
+        );
+    }
+
+    #[test]
+    fn coords_at_maps_cells_to_intersections() {
+        // top left intersection is on the line after the header
+        assert_eq!(
+            Some(Coords::from(19, 1)),
+            coords_at(19, 2, Point::new(4.0, 1.0))
+        );
+        assert_eq!(
+            Some(Coords::from(1, 19)),
+            coords_at(19, 2, Point::new(40.0, 19.0))
+        );
+        // single digit boards have a narrower line number column
+        assert_eq!(
+            Some(Coords::from(5, 3)),
+            coords_at(9, 2, Point::new(7.0, 5.0))
+        );
+    }
+
+    #[test]
+    fn coords_at_ignores_positions_off_the_grid() {
+        // header, line numbers and past the last line
+        assert_eq!(None, coords_at(19, 2, Point::new(4.0, 0.0)));
+        assert_eq!(None, coords_at(19, 2, Point::new(2.0, 3.0)));
+        assert_eq!(None, coords_at(19, 2, Point::new(4.0, 20.0)));
+        assert_eq!(None, coords_at(19, 2, Point::new(42.0, 3.0)));
+        assert_eq!(None, coords_at(19, 2, Point::new(-1.0, 3.0)));
+    }
+
+    #[test]
+    fn connectors_snap_to_the_nearer_intersection() {
+        // A17 is drawn at x = 4, then its connector at 5 and B17 at 6
+        assert_eq!(
+            Some(Coords::from(17, 1)),
+            coords_at(19, 2, Point::new(5.0, 3.0))
+        );
+        assert_eq!(
+            Some(Coords::from(17, 1)),
+            coords_at(19, 2, Point::new(5.4, 3.0))
+        );
+        assert_eq!(
+            Some(Coords::from(17, 2)),
+            coords_at(19, 2, Point::new(5.8, 3.0))
+        );
+        // compact boards have no connectors, every cell is an intersection
+        assert_eq!(
+            Some(Coords::from(17, 2)),
+            coords_at(19, 1, Point::new(5.0, 3.0))
+        );
+    }
 }
@@ -1,12 +1,34 @@
-use crate::core::entities::Stone;
+use crate::core::engine::EngineInfo;
+use crate::core::entities::{Coords, GroupStatus, ScoreResult, Stone, Territory};
+use crate::core::game::{MoveHistory, MoveRecord};
 use crate::gogame::board::Board;
 use iced_native::Event;
+use std::time::Instant;
 
 #[derive(Clone, Debug)]
 pub enum GameMessage {
-    BoardLoaded(Board),
+    BoardLoaded(Board, MoveHistory, EngineInfo),
     EventOccurred(Event),
-    AfterStonePlayed(Vec<Stone>, Vec<Stone>),
-    AfterGenMove(Vec<Stone>, Vec<Stone>),
+    BoardHovered(Option<Coords>),
+    BoardClicked(Option<Coords>),
+    AfterStonePlayed(MoveRecord, Vec<Stone>, Vec<Stone>),
+    /// The engine refused the player's move, with its reason
+    MoveRejected(Coords, String),
+    AfterGenMove(MoveRecord, Vec<Stone>, Vec<Stone>),
+    MovesUndone(usize, Vec<Stone>, Vec<Stone>),
+    TerritoryEstimated(Territory),
+    DeadStonesListed(Vec<Coords>),
+    /// The territory is left out when the engine can't list it
+    GameScored(ScoreResult, Option<Territory>),
+    StonesRefreshed(Vec<Stone>, Vec<Stone>),
+    /// The engine died and a new one was set up with the position
+    EngineRestarted(Vec<Stone>, Vec<Stone>),
+    GroupStatusReported(Coords, GroupStatus),
+    /// Move the engine would play for the player, `None` when it would pass or resign
+    MoveSuggested(Option<Coords>),
+    /// Next stone of the replay of a loaded game
+    ReplayStep,
+    /// Clock tick, only subscribed to in timed games
+    Tick(Instant),
     GtpError(String),
 }
@@ -1,41 +1,182 @@
-use crate::core::engine::Engine;
-use crate::core::entities::{Coords, Stone, StoneColor};
+use crate::core::clock::{format_clock, GameClock};
+use crate::core::config::{
+    self, GeneralConfig, InputMode, PanelPosition, ResignRule, ThemeConfig, TwoPassesRule,
+};
+use crate::core::context::AppContext;
+use crate::core::engine::{Engine, EngineInfo, GenMoveResponse, PlayResponse};
+use crate::core::entities::{
+    Coords, GroupStatus, MarkerKind, OptCoords, ScoreResult, Stone, StoneColor, Territory,
+};
 use crate::core::errors::AppError;
-use crate::core::helpers::{parse_input_coords, TryPush};
+use crate::core::game::{GameProgress, MoveHistory, MoveKind, MoveRecord};
+use crate::core::goban::Goban;
+use crate::core::helpers::{
+    arrow_step, get_column_number, keypad_digit, parse_input_coords, parse_sgf_input_coords,
+    TryPush,
+};
+use crate::core::sgf::{self, SgfGame};
+use crate::core::signals;
+#[cfg(feature = "serve")]
+use crate::core::status_server::{GameStatus, LastMove, StatusServer};
 use crate::core::theme::Theme;
-use crate::core::{config, logger};
+use crate::core::title::{self, TerminalTitle};
+use crate::core::validator::{GobanValidator, MoveValidator};
 use crate::gogame::board::Board;
 use crate::gogame::game_message::GameMessage;
-use clap::{App, Arg};
+use crate::gogame::help;
+use crate::gogame::menu::{Menu, MenuAction};
+use crate::gogame::replay::Replay;
+use crate::gogame::summary::GameSummary;
 use iced_futures::executor::Tokio;
+use iced_futures::time;
 use iced_native::{
-    keyboard, subscription, Column, Command, Container, Element, Event, Length, Row, Subscription,
-    Text,
+    keyboard, subscription, window, Color, Column, Command, Container, Element, Event, Length, Row,
+    Subscription, Text,
 };
 use iced_tui::{Application, Style, TuiRenderer};
+use log::{info, warn};
+use std::io;
 use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
 
-const INPUT_CHAR_RANGE: [char; 19] = [
-    'A', 'B', 'C', 'D', 'E', 'F', 'G', 'H', 'J', 'K', 'L', 'M', 'N', 'O', 'P', 'Q', 'R', 'S', 'T',
-];
 const INPUT_NUMBER_RANGE: [char; 10] = ['0', '1', '2', '3', '4', '5', '6', '7', '8', '9'];
+/// Starts typing SGF letters, which would otherwise be taken as commands
+const SGF_INPUT_START: char = ':';
+/// How long the second `q`/Ctrl+C is waited for before quitting has to be asked again
+const QUIT_WINDOW: Duration = Duration::from_secs(3);
+/// Handicap stones of the games started from the menu when fewer are configured
+const MIN_HANDICAP: u8 = 2;
+
+/// What is needed to get the engine ready for a game
+#[derive(Clone)]
+struct GameSetup {
+    startup_attempts: u32,
+    startup_timeout: Duration,
+    reset_commands: Vec<String>,
+    /// Ignored when loading a game record, which has its own size
+    board_size: Option<u8>,
+    sgf_game: Option<SgfGame>,
+    /// File `sgf_game` was read from, for the engine to load itself (`--sgf`)
+    sgf_path: Option<String>,
+    main_time: Option<Duration>,
+    komi: Option<f32>,
+    first_player: StoneColor,
+    /// The engine plays the other color
+    player_color: StoneColor,
+    /// Ignored when loading a game record, like `board_size`
+    handicap: u8,
+    max_undo: Option<usize>,
+}
+
+/// Something waiting for a y/n answer
+#[derive(Debug, Copy, Clone, PartialEq)]
+enum Confirmation {
+    /// Both players passed
+    Score,
+    /// First stone of the game, on an empty board
+    FirstMove(Coords),
+    /// Abandons the game in progress, for one with the handicap stones if set instead of the
+    /// configured ones
+    NewGame(Option<u8>),
+}
 
-#[derive(PartialEq)]
+#[derive(Debug, PartialEq)]
 enum GtpStatus {
     Loading,
     Idle,
     Error,
 }
 
+impl GtpStatus {
+    /// Color of the status dot in the panel, and what it means
+    fn indicator(&self, theme: &Theme) -> (Color, &'static str) {
+        match self {
+            GtpStatus::Idle => (theme.status_idle_color, "ready"),
+            GtpStatus::Loading => (theme.status_busy_color, "busy"),
+            GtpStatus::Error => (theme.status_error_color, "error"),
+        }
+    }
+}
+
 pub struct GoGame {
     should_exit: Option<u8>,
     board: Option<Board>,
     next_move_input: String,
+    input_mode: InputMode,
     player_color: StoneColor,
     gtp_engine: Arc<Mutex<Engine>>,
     gtp_status: GtpStatus,
     gtp_error: Option<String>,
+    status_message: Option<String>,
+    hover_coords: Option<Coords>,
+    /// Point moved with the arrow keys, `None` until they are used in the current game
+    cursor: Option<Coords>,
+    sgf_save_path: String,
+    move_history: MoveHistory,
+    engine_info: EngineInfo,
+    two_passes: TwoPassesRule,
+    on_resign: ResignRule,
+    confirmation: Option<Confirmation>,
+    /// First press of a quit key, quitting takes a second one within [`QUIT_WINDOW`]
+    quit_requested_at: Option<Instant>,
+    /// Checks the player's moves before they reach the engine
+    move_validator: Box<dyn MoveValidator>,
+    /// Points the first `opening_points_moves` moves of each side are restricted to
+    opening_points: Vec<String>,
+    opening_points_moves: usize,
+    /// Komi the engine was told, to warn when it reports another one
+    komi: Option<f32>,
+    /// Only when enabled in the config
+    terminal_title: Option<TerminalTitle>,
+    /// Open actions menu
+    menu: Option<Menu>,
+    /// Key list, shown with `?` until the next key
+    show_help: bool,
+    /// Delay between the stones revealed when a loaded game is replayed
+    replay_stone_delay: Option<Duration>,
+    /// Replay in progress, the board shows only part of the position meanwhile
+    replay: Option<Replay>,
+    /// End-game screen, until dismissed to review the game
+    summary: Option<GameSummary>,
+    /// When the board was loaded, for the game duration
+    started_at: Option<Instant>,
+    confirm_first_move: bool,
+    auto_respond: bool,
+    auto_pass: bool,
+    atari_warnings: bool,
+    /// The engine plays the player's moves too, toggled with `a`
+    demonstrating: bool,
+    /// Both colors are played from the keyboard, `player_color` follows the turn
+    two_player: bool,
+    show_moves_since_capture: bool,
+    game_result: Option<String>,
+    /// Only in timed games
+    clock: Option<GameClock>,
+    low_time: Duration,
+    /// No more moves are accepted
+    game_over: bool,
+    panel_position: PanelPosition,
+    compact_board: bool,
+    /// Latest moves marked on the board
+    marked_moves: usize,
+    /// No engine could be started, the very weak built-in one is playing
+    builtin_engine: bool,
+    /// A restart was tried since the last move, so the next engine failure is final
+    engine_restarted: bool,
+    /// How the games started with `n` are set up, never from a game record
+    new_game_setup: Option<GameSetup>,
+    /// Configured `(top, left)` margin, see `board_margin` for what is actually used
+    board_margin: (u16, u16),
+    /// `(width, height)` from the last resize event
+    terminal_size: Option<(u16, u16)>,
+    theme_preset: String,
+    /// `(stones, grid)` drawn with ASCII glyphs, whatever the preset
+    ascii_glyphs: (bool, bool),
+    /// Applied to every preset, the theme cycled to included
+    theme_overrides: ThemeConfig,
     theme: Theme,
+    #[cfg(feature = "serve")]
+    status_server: Option<StatusServer>,
 }
 
 impl Application for GoGame {
@@ -43,63 +184,46 @@ impl Application for GoGame {
     type Executor = Tokio;
 
     fn new() -> (GoGame, Command<Self::Message>) {
-        let matches = App::new(env!("CARGO_PKG_NAME"))
-            .version(env!("CARGO_PKG_VERSION"))
-            .author(env!("CARGO_PKG_AUTHORS"))
-            .about(env!("CARGO_PKG_DESCRIPTION"))
-            .arg(
-                Arg::with_name("config")
-                    .short("c")
-                    .long("config")
-                    .value_name("FILE")
-                    .help("Sets a custom config file")
-                    .takes_value(true),
-            )
-            .arg(
-                Arg::with_name("debug-file")
-                    .short("d")
-                    .long("debug-file")
-                    .value_name("Debug file")
-                    .help("Output debug to a file")
-                    .takes_value(true),
-            )
-            .arg(
-                Arg::with_name("v")
-                    .short("v")
-                    .multiple(true)
-                    .help("Sets the level of verbosity"),
-            )
-            .get_matches();
-
-        logger::init_logger(
-            matches.value_of("debug-file"),
-            logger::get_logger_level_by_verbosity(matches.occurrences_of("v")),
-        )
-        .unwrap();
-
-        let app_config = config::get_app_config(matches.value_of("config")).unwrap();
+        let context = AppContext::get();
+        let app_config = &context.app_config;
+        let (engine, builtin_engine) =
+            match Engine::new(&app_config.engine.bin, &app_config.engine.startup_args()) {
+                Ok(engine) => (engine, false),
+                Err(app_error) => {
+                    warn!("{}, playing the built-in engine", app_error.message);
+                    (Engine::builtin(), true)
+                }
+            };
         let gtp_engine = Arc::new(Mutex::new(
-            Engine::new(&app_config.engine.bin, &app_config.engine.args).unwrap(),
+            engine
+                .with_timeouts(
+                    Duration::from_millis(app_config.engine.default_timeout_ms),
+                    Duration::from_millis(app_config.engine.genmove_timeout_ms),
+                )
+                .with_opening_genmove_timeout(
+                    Duration::from_millis(app_config.engine.opening_genmove_ms),
+                    app_config.engine.opening_moves,
+                )
+                .with_stone_source(app_config.engine.stone_source),
         ));
 
-        let theme = Theme::default();
+        signals::register_engine(gtp_engine.clone());
 
-        let state = GoGame {
-            should_exit: None,
-            board: None,
-            next_move_input: "".to_string(),
-            gtp_engine: gtp_engine.clone(),
-            gtp_status: GtpStatus::Idle,
-            player_color: StoneColor::Black,
-            gtp_error: None,
-            theme: theme.clone(),
-        };
+        let (mut state, game_setup) = GoGame::with_context(context, gtp_engine.clone());
+        state.builtin_engine = builtin_engine;
+        let theme = state.theme.clone();
+
+        #[cfg(feature = "serve")]
+        {
+            state.status_server = context.status_server.lock().unwrap().take();
+        }
 
         (
             state,
-            Command::perform(GoGame::load_board(gtp_engine, theme), |board| {
-                GameMessage::BoardLoaded(board)
-            }),
+            Command::perform(
+                GoGame::load_board(gtp_engine, theme, game_setup),
+                board_loaded_message,
+            ),
         )
     }
 
@@ -108,55 +232,79 @@ impl Application for GoGame {
     }
 
     fn subscription(&self) -> Subscription<Self::Message> {
-        subscription::events().map(Self::Message::EventOccurred)
+        let mut subscriptions = vec![subscription::events().map(Self::Message::EventOccurred)];
+
+        if self.clock.is_some() && !self.game_over {
+            subscriptions.push(time::every(Duration::from_millis(250)).map(Self::Message::Tick));
+        }
+        if let (Some(_), Some(delay)) = (&self.replay, self.replay_stone_delay) {
+            subscriptions.push(time::every(delay).map(|_| Self::Message::ReplayStep));
+        }
+
+        Subscription::batch(subscriptions)
     }
 
     fn view(&self) -> Element<Self::Message, TuiRenderer> {
-        Container::new(
-            Row::new()
+        let content: Element<Self::Message, TuiRenderer> = match self.panel_position {
+            PanelPosition::Right => Row::new()
                 .spacing(2)
                 .width(Length::Shrink)
-                .push(match &self.board {
-                    Some(board) => board.view(),
-                    None => Text::new("Empty board").into(),
-                })
-                .push(
-                    Column::new()
-                        .spacing(1)
-                        .push(Row::new().push(Text::new("Next move: ")).push(
-                            if self.gtp_status == GtpStatus::Loading {
-                                Text::new("Loading").width(Length::Units(7)).font(
-                                    Style::default()
-                                        .bg(self.theme.loading_label_bg)
-                                        .fg(self.theme.loading_label_fg),
-                                )
-                            } else {
-                                Text::new(self.next_move_input.clone()).width(Length::Units(7))
-                            },
-                        ))
-                        .try_push(self.gtp_error.clone().map(|message| {
-                            Container::new(Text::new(message))
-                                .style(
-                                    Style::default()
-                                        .bg(self.theme.error_message_bg)
-                                        .fg(self.theme.error_message_fg),
-                                )
-                                .width(Length::Units(18))
-                        })),
-                ),
-        )
-        .width(Length::Fill)
-        .height(Length::Fill)
-        .center_x()
-        .center_y()
-        .into()
+                .push(self.board_view())
+                .push(self.panel_view())
+                .into(),
+            PanelPosition::Left => Row::new()
+                .spacing(2)
+                .width(Length::Shrink)
+                .push(self.panel_view())
+                .push(self.board_view())
+                .into(),
+            PanelPosition::Bottom => Column::new()
+                .spacing(1)
+                .push(self.board_view())
+                .push(self.panel_view())
+                .into(),
+            PanelPosition::Hidden => self.board_view(),
+        };
+
+        let content = Column::new()
+            .push(self.status_line_view())
+            .try_push(Some(help::view(&self.theme)).filter(|_| self.show_help))
+            .try_push(self.menu.as_ref().map(|menu| menu.view(&self.theme)))
+            .try_push(
+                self.summary
+                    .as_ref()
+                    .map(|summary| summary.view(&self.theme)),
+            )
+            .push(content);
+
+        Container::new(content)
+            .width(Length::Fill)
+            .height(Length::Fill)
+            .center_x()
+            .center_y()
+            .into()
     }
 
     fn update(&mut self, message: Self::Message) -> Command<Self::Message> {
+        if self.replay.is_some() && interrupts_replay(&message) {
+            self.finish_replay();
+        }
+
         match message {
-            GameMessage::BoardLoaded(board) => {
+            GameMessage::BoardLoaded(mut board, move_history, engine_info) => {
+                board.set_compact(self.compact_board);
+                board.set_player_color(self.player_color);
                 self.board = Some(board);
-                Command::none()
+                self.move_history = move_history;
+                self.engine_info = engine_info;
+                self.cursor = None;
+                self.started_at = Some(Instant::now());
+                self.refresh_recent_moves();
+                self.start_replay();
+                self.status_message = komi_mismatch(self.komi, self.engine_info.komi);
+                self.publish_status();
+
+                self.continue_game()
             }
             GameMessage::EventOccurred(Event::Keyboard(keyboard::Event::KeyReleased {
                 key_code,
@@ -164,7 +312,55 @@ impl Application for GoGame {
             })) => {
                 if key_code == keyboard::KeyCode::C && modifiers.control {
                     // exit on ctrl+c (status 1 = error)
-                    self.should_exit = Some(1);
+                    self.request_quit(1, "Ctrl+C");
+                    return Command::none();
+                }
+
+                // keys without a character, the others cancel on CharacterReceived
+                if let keyboard::KeyCode::Escape
+                | keyboard::KeyCode::Enter
+                | keyboard::KeyCode::Backspace = key_code
+                {
+                    self.cancel_quit();
+                }
+                if arrow_step(key_code).is_some() {
+                    self.cancel_quit();
+                }
+
+                if self.show_help {
+                    if let keyboard::KeyCode::Escape | keyboard::KeyCode::Enter = key_code {
+                        self.show_help = false;
+                    }
+
+                    return Command::none();
+                }
+
+                if self.summary.is_some() {
+                    if let keyboard::KeyCode::Escape | keyboard::KeyCode::Enter = key_code {
+                        self.summary = None;
+                    }
+
+                    return Command::none();
+                }
+
+                if let Some(menu) = &mut self.menu {
+                    match key_code {
+                        keyboard::KeyCode::Up => menu.select_previous(),
+                        keyboard::KeyCode::Down => menu.select_next(),
+                        keyboard::KeyCode::Escape => self.menu = None,
+                        keyboard::KeyCode::Enter => return self.select_menu_action(),
+                        _ => {}
+                    }
+
+                    return Command::none();
+                }
+
+                if let Some(c) = keypad_digit(key_code) {
+                    self.push_input_char(c);
+                }
+
+                if let Some((rows, cols)) = arrow_step(key_code) {
+                    self.move_cursor(rows, cols);
                 }
 
                 if key_code == keyboard::KeyCode::Backspace && self.gtp_status == GtpStatus::Idle {
@@ -177,23 +373,7 @@ impl Application for GoGame {
                 if key_code == keyboard::KeyCode::Enter && self.gtp_status == GtpStatus::Idle {
                     if let Some(board) = &mut self.board {
                         if let Some(coords) = board.get_valid_highlight_coords() {
-                            self.gtp_status = GtpStatus::Loading;
-                            self.next_move_input = "".to_string();
-                            self.refresh_highlight_coords();
-
-                            return Command::perform(
-                                GoGame::play_move(
-                                    self.gtp_engine.clone(),
-                                    coords,
-                                    self.player_color,
-                                ),
-                                |result| match result {
-                                    Ok((black_stones, white_stones)) => {
-                                        GameMessage::AfterStonePlayed(black_stones, white_stones)
-                                    }
-                                    Err(app_error) => GameMessage::GtpError(app_error.message),
-                                },
-                            );
+                            return self.play(coords);
                         }
                     }
                 }
@@ -201,90 +381,3309 @@ impl Application for GoGame {
                 Command::none()
             }
             GameMessage::EventOccurred(Event::Keyboard(keyboard::Event::CharacterReceived(c))) => {
-                if INPUT_CHAR_RANGE.contains(&c) && self.gtp_status == GtpStatus::Idle {
-                    if self.next_move_input.is_empty() {
-                        self.next_move_input.push(c);
-                        self.refresh_highlight_coords();
+                if c != 'q' && !c.is_control() {
+                    self.cancel_quit();
+                }
+
+                if self.show_help {
+                    self.show_help = false;
+                    return Command::none();
+                }
+
+                if c == '?' {
+                    self.show_help = true;
+                    return Command::none();
+                }
+
+                // the menu takes over the keyboard until it's closed
+                if self.menu.is_some() {
+                    if c == 'm' {
+                        self.menu = None;
                     }
+                    return Command::none();
                 }
 
-                if INPUT_NUMBER_RANGE.contains(&c) && self.gtp_status == GtpStatus::Idle {
-                    if !self.next_move_input.is_empty() && self.next_move_input.len() < 3 {
-                        self.next_move_input.push(c);
-                        self.refresh_highlight_coords();
+                if self.gtp_status == GtpStatus::Idle && (c == 'y' || c == 'n') {
+                    if let Some(confirmation) = self.confirmation.take() {
+                        return self.answer_confirmation(confirmation, c == 'y');
                     }
                 }
 
+                // typed coordinates come first, SGF letters overlap with the letter commands
+                if self.push_input_char(c) {
+                    return Command::none();
+                }
+
+                if c == 'q' {
+                    self.request_quit(0, "q");
+                    return Command::none();
+                }
+
+                if c == 'm' {
+                    self.menu = Some(Menu::new(&self.engine_info));
+                }
+
+                if c == 'c' {
+                    self.cycle_theme();
+                }
+
+                if c == 'x' && self.gtp_status == GtpStatus::Idle {
+                    return self.swap_colors();
+                }
+
+                if c == 'u' && self.gtp_status == GtpStatus::Idle {
+                    return self.undo();
+                }
+
+                if c == 'n' && self.gtp_status != GtpStatus::Loading {
+                    return self.new_game(None);
+                }
+
+                if c == '=' && self.gtp_status == GtpStatus::Idle && !self.game_over {
+                    return self.score_game();
+                }
+
+                if c == 'p' && self.gtp_status == GtpStatus::Idle {
+                    return self.pass();
+                }
+
+                if c == 'g' && self.gtp_status == GtpStatus::Idle {
+                    return self.request_engine_reply();
+                }
+
+                #[cfg(feature = "debug-moves")]
+                if c == 'z' && self.gtp_status == GtpStatus::Idle {
+                    return self.play_random_move();
+                }
+
+                // force a re-sync with the engine, in case the board got out of date
+                if c == 'r' && self.gtp_status != GtpStatus::Loading {
+                    self.gtp_status = GtpStatus::Loading;
+                    self.status_message = None;
+
+                    return Command::perform(
+                        GoGame::refresh_stones(self.gtp_engine.clone()),
+                        |result| match result {
+                            Ok((black_stones, white_stones)) => {
+                                GameMessage::StonesRefreshed(black_stones, white_stones)
+                            }
+                            Err(app_error) => GameMessage::GtpError(app_error.message),
+                        },
+                    );
+                }
+
+                if c == 't' && self.gtp_status == GtpStatus::Idle {
+                    return self.toggle_territory();
+                }
+
+                if c == 'd' && self.gtp_status == GtpStatus::Idle {
+                    return self.toggle_dead_stones();
+                }
+
+                if c == 'a' && self.gtp_status == GtpStatus::Idle {
+                    return self.toggle_demonstration();
+                }
+
+                if c == 's' && self.gtp_status == GtpStatus::Idle {
+                    return self.check_group_status();
+                }
+
+                if c == 'w' {
+                    self.save_game();
+                }
+
+                Command::none()
+            }
+            GameMessage::AfterStonePlayed(mut record, black_stones, white_stones) => {
+                (record.captured, record.self_captured) =
+                    self.count_captured(&record, &black_stones, &white_stones);
+                self.move_history.push(record);
+                self.engine_restarted = false;
+                self.apply_move(&record, black_stones, white_stones);
+                self.refresh_recent_moves();
+                self.refresh_atari_markers();
+                self.publish_status();
+
+                self.continue_game()
+            }
+            GameMessage::MoveRejected(coords, reason) => {
+                // the turn stays with the player, the engine doesn't get to move
+                let board_size = self.board.as_ref().map_or(19, |board| board.board_size());
+                self.status_message = Some(format!(
+                    "Illegal move at {}: {}",
+                    coords.to_gtp(board_size),
+                    reason
+                ));
+                self.gtp_status = GtpStatus::Idle;
                 Command::none()
             }
-            GameMessage::AfterStonePlayed(black_stones, white_stones) => {
+            GameMessage::AfterGenMove(mut record, black_stones, white_stones) => {
+                (record.captured, record.self_captured) =
+                    self.count_captured(&record, &black_stones, &white_stones);
+                if let Some(think_time) = record.think_time {
+                    info!(
+                        "genmove-timing move={} color={} elapsed_ms={}",
+                        self.move_history.len() + 1,
+                        record.color.name(),
+                        think_time.as_millis()
+                    );
+                }
+                self.move_history.push(record);
+                self.engine_restarted = false;
+                self.apply_move(&record, black_stones, white_stones);
+                if record.kind == MoveKind::Pass {
+                    self.status_message = Some(format!("{} passed", record.color.name()));
+                }
+                self.refresh_recent_moves();
+                self.refresh_atari_markers();
+                self.publish_status();
+
+                self.continue_game()
+            }
+            GameMessage::MovesUndone(count, black_stones, white_stones) => {
+                for _ in 0..count {
+                    // only sent after checking there were enough moves to undo
+                    let _ = self.move_history.undo();
+                }
                 if let Some(board) = &mut self.board {
                     board.set_stones(black_stones, white_stones);
                 }
+                self.refresh_recent_moves();
+                self.refresh_atari_markers();
+                self.game_result = None;
+                self.game_over = false;
+                self.confirmation = None;
+                self.status_message = Some(format!("Undid {} move(s)", count));
+                self.follow_turn();
+                self.gtp_status = GtpStatus::Idle;
+                self.publish_status();
+                Command::none()
+            }
+            GameMessage::GameScored(score, territory) => {
+                if let (Some(board), Some(territory)) = (&mut self.board, territory) {
+                    board.set_territory(Some(territory));
+                }
+                self.finish_game(score.to_string(), format!("Game over: {}", score));
+                self.gtp_status = GtpStatus::Idle;
+                Command::none()
+            }
+            GameMessage::ReplayStep => {
+                if let Some(replay) = &mut self.replay {
+                    replay.advance();
 
-                Command::perform(
-                    GoGame::gen_next_move(self.gtp_engine.clone(), self.player_color),
-                    |result| match result {
-                        Ok((black_stones, white_stones)) => {
-                            GameMessage::AfterGenMove(black_stones, white_stones)
-                        }
-                        Err(app_error) => GameMessage::GtpError(app_error.message),
-                    },
-                )
+                    if replay.is_done() {
+                        self.finish_replay();
+                    } else if let Some(board) = &mut self.board {
+                        let (black_stones, white_stones) = replay.visible();
+                        board.set_stones(black_stones, white_stones);
+                    }
+                }
+                Command::none()
+            }
+            GameMessage::Tick(now) => {
+                let flagged = self.clock.as_ref().and_then(|clock| clock.flagged(now));
+
+                if let (Some(color), false) = (flagged, self.game_over) {
+                    self.finish_game(
+                        format!("{}+T", color.inverse().letter()),
+                        format!("{} lost on time", color.name()),
+                    );
+                }
+
+                Command::none()
+            }
+            GameMessage::TerritoryEstimated(territory) => {
+                if let Some(board) = &mut self.board {
+                    board.set_territory(Some(territory));
+                }
+                self.gtp_status = GtpStatus::Idle;
+                Command::none()
+            }
+            GameMessage::DeadStonesListed(dead_stones) => {
+                if dead_stones.is_empty() {
+                    self.status_message = Some("No dead stones".to_string());
+                }
+                if let Some(board) = &mut self.board {
+                    board.set_dead_stones(dead_stones);
+                }
+                self.gtp_status = GtpStatus::Idle;
+                Command::none()
             }
-            GameMessage::AfterGenMove(black_stones, white_stones) => {
+            GameMessage::StonesRefreshed(black_stones, white_stones) => {
                 if let Some(board) = &mut self.board {
                     board.set_stones(black_stones, white_stones);
                 }
                 self.gtp_status = GtpStatus::Idle;
+                self.gtp_error = None;
+                self.status_message = Some("Board re-synced".to_string());
+                self.publish_status();
+                Command::none()
+            }
+            GameMessage::GroupStatusReported(coords, status) => {
+                let board_size = self.board.as_ref().map_or(19, |b| b.board_size());
+
+                self.status_message = Some(format!(
+                    "Group at {} is {}",
+                    coords.to_gtp(board_size),
+                    status.name()
+                ));
+                self.gtp_status = GtpStatus::Idle;
+                Command::none()
+            }
+            GameMessage::MoveSuggested(coords) => {
+                let board_size = self.board.as_ref().map_or(19, |b| b.board_size());
+
+                self.status_message = Some(match coords {
+                    Some(coords) => format!("The engine suggests {}", coords.to_gtp(board_size)),
+                    None => "The engine would pass".to_string(),
+                });
+                if let Some(board) = &mut self.board {
+                    board.set_markers(
+                        coords
+                            .map(|coords| vec![(coords, MarkerKind::Hint)])
+                            .unwrap_or_default(),
+                    );
+                }
+                self.gtp_status = GtpStatus::Idle;
+                Command::none()
+            }
+            GameMessage::BoardHovered(coords) => {
+                self.hover_coords = coords;
                 Command::none()
             }
+            GameMessage::BoardClicked(Some(coords)) if self.gtp_status == GtpStatus::Idle => {
+                self.play(coords)
+            }
+            GameMessage::BoardClicked(_) => Command::none(),
             GameMessage::GtpError(message) => {
+                if let Some(command) = self.restart_dead_engine(&message) {
+                    return command;
+                }
+
                 self.gtp_error = Some(message);
                 self.gtp_status = GtpStatus::Error;
                 Command::none()
             }
+            GameMessage::EngineRestarted(black_stones, white_stones) => {
+                if let Some(board) = &mut self.board {
+                    board.set_stones(black_stones, white_stones);
+                }
+                self.gtp_status = GtpStatus::Idle;
+                self.gtp_error = None;
+                self.status_message = Some("Engine restarted".to_string());
+                self.publish_status();
+
+                self.continue_game()
+            }
+            GameMessage::EventOccurred(Event::Window(window::Event::Resized { width, height })) => {
+                self.terminal_size = Some((width as u16, height as u16));
+                Command::none()
+            }
             GameMessage::EventOccurred(_) => Command::none(),
         }
     }
 }
 
 impl GoGame {
-    async fn load_board(gtp_engine: Arc<Mutex<Engine>>, theme: Theme) -> Board {
-        let board_size = gtp_engine.lock().unwrap().query_board_size().unwrap();
-        Board::new(board_size, theme)
+    /// Replaces the default validator, which only checks the Go rules. Rule variants are
+    /// plugged in here, only tests do so far.
+    #[cfg(test)]
+    pub fn with_move_validator(mut self, move_validator: Box<dyn MoveValidator>) -> Self {
+        self.move_validator = move_validator;
+        self
+    }
+
+    /// Game set up from the command line and the config, before the engine loads the board
+    fn with_context(context: &AppContext, gtp_engine: Arc<Mutex<Engine>>) -> (GoGame, GameSetup) {
+        let app_config = &context.app_config;
+        let sgf_game = context.load.clone();
+        let game_setup = GameSetup {
+            startup_attempts: app_config.engine.startup_attempts,
+            startup_timeout: Duration::from_millis(app_config.engine.startup_timeout_ms),
+            reset_commands: app_config.engine.reset_commands.clone(),
+            board_size: app_config.engine.board_size,
+            komi: sgf_game
+                .as_ref()
+                .and_then(|sgf_game| sgf_game.komi)
+                .or(app_config.general.komi),
+            sgf_game,
+            sgf_path: context.sgf_path.clone(),
+            main_time: app_config.general.main_time_secs.map(Duration::from_secs),
+            first_player: app_config.general.first_player,
+            player_color: app_config.engine.player_color,
+            handicap: app_config.engine.handicap,
+            max_undo: app_config.general.max_undo,
+        };
+
+        let mut state = GoGame::with_config(gtp_engine, &app_config.general, &app_config.theme)
+            .expect("the theme is checked with the context");
+        state.komi = game_setup.komi;
+        state.player_color = game_setup.player_color;
+        state.new_game_setup = Some(GameSetup {
+            sgf_game: None,
+            sgf_path: None,
+            komi: app_config.general.komi,
+            ..game_setup.clone()
+        });
+
+        if let (Some(sgf_game), Some(continue_as)) = (&game_setup.sgf_game, &context.continue_as) {
+            let to_move = MoveHistory::from_sgf(sgf_game, game_setup.first_player).to_move();
+            state.player_color = continue_as_color(continue_as, to_move);
+        }
+
+        (state, game_setup)
+    }
+
+    fn with_config(
+        gtp_engine: Arc<Mutex<Engine>>,
+        general_config: &GeneralConfig,
+        theme_config: &ThemeConfig,
+    ) -> Result<GoGame, AppError> {
+        Ok(GoGame {
+            should_exit: None,
+            board: None,
+            next_move_input: "".to_string(),
+            input_mode: general_config.input_mode,
+            gtp_engine,
+            gtp_status: GtpStatus::Loading,
+            player_color: StoneColor::Black,
+            gtp_error: None,
+            status_message: None,
+            hover_coords: None,
+            cursor: None,
+            sgf_save_path: general_config.sgf_save_path.clone(),
+            move_history: MoveHistory::default(),
+            engine_info: EngineInfo::default(),
+            two_passes: general_config.two_passes,
+            on_resign: general_config.on_resign,
+            confirmation: None,
+            quit_requested_at: None,
+            move_validator: Box::new(GobanValidator),
+            opening_points: general_config.opening_points.clone(),
+            opening_points_moves: general_config.opening_points_moves,
+            komi: general_config.komi,
+            terminal_title: if general_config.terminal_title {
+                Some(TerminalTitle::default())
+            } else {
+                None
+            },
+            menu: None,
+            show_help: false,
+            replay_stone_delay: general_config.replay_stone_ms.map(Duration::from_millis),
+            replay: None,
+            summary: None,
+            started_at: None,
+            confirm_first_move: general_config.confirm_first_move,
+            auto_respond: general_config.auto_respond,
+            auto_pass: general_config.auto_pass,
+            atari_warnings: general_config.atari_warnings,
+            demonstrating: false,
+            two_player: general_config.two_player,
+            show_moves_since_capture: general_config.show_moves_since_capture,
+            game_result: None,
+            clock: general_config
+                .main_time_secs
+                .map(|secs| GameClock::new(Duration::from_secs(secs))),
+            low_time: Duration::from_secs(general_config.low_time_secs),
+            game_over: false,
+            panel_position: general_config.panel_position,
+            compact_board: general_config.compact_board,
+            marked_moves: general_config.marked_moves,
+            builtin_engine: false,
+            engine_restarted: false,
+            new_game_setup: None,
+            board_margin: (
+                general_config.board_margin_top,
+                general_config.board_margin_left,
+            ),
+            terminal_size: None,
+            theme_preset: general_config.theme_preset.clone(),
+            ascii_glyphs: (general_config.ascii_stones, general_config.ascii_grid),
+            theme_overrides: theme_config.clone(),
+            theme: Theme::preset(&general_config.theme_preset)?
+                .with_ascii(general_config.ascii_stones, general_config.ascii_grid)
+                .with_overrides(theme_config)?,
+            #[cfg(feature = "serve")]
+            status_server: None,
+        })
+    }
+
+    /// Puts the played stone and takes the captured ones off the board. A position that differs
+    /// in any other way from the engine's is replaced as a whole, to stay in sync.
+    fn apply_move(
+        &mut self,
+        record: &MoveRecord,
+        black_stones: Vec<Stone>,
+        white_stones: Vec<Stone>,
+    ) {
+        let board = match &mut self.board {
+            Some(board) => board,
+            None => return,
+        };
+
+        if let MoveKind::Play(coords) = record.kind {
+            board.add_stone(Stone {
+                color: record.color,
+                row: coords.row,
+                col: coords.col,
+            });
+        }
+
+        let removed: Vec<Coords> = board
+            .black_stones()
+            .iter()
+            .filter(|stone| !black_stones.contains(stone))
+            .chain(
+                board
+                    .white_stones()
+                    .iter()
+                    .filter(|stone| !white_stones.contains(stone)),
+            )
+            .map(|stone| Coords::from(stone.row, stone.col))
+            .collect();
+        board.remove_stones(&removed);
+
+        if board.black_stones().len() != black_stones.len()
+            || board.white_stones().len() != white_stones.len()
+        {
+            board.set_stones(black_stones, white_stones);
+        }
+    }
+
+    /// Opponent stones and own stones that left the board with the move, compared to the
+    /// position shown
+    fn count_captured(
+        &self,
+        record: &MoveRecord,
+        black_stones: &[Stone],
+        white_stones: &[Stone],
+    ) -> (usize, usize) {
+        let board = match &self.board {
+            Some(board) => board,
+            None => return (0, 0),
+        };
+        let (own_before, own_after, opponent_before, opponent_after) = match record.color {
+            StoneColor::Black => (
+                board.black_stones(),
+                black_stones,
+                board.white_stones(),
+                white_stones,
+            ),
+            StoneColor::White => (
+                board.white_stones(),
+                white_stones,
+                board.black_stones(),
+                black_stones,
+            ),
+        };
+        // the stone just played is gone as well when it's a suicide
+        let placed = match record.kind {
+            MoveKind::Play(_) => 1,
+            MoveKind::Pass | MoveKind::Resign => 0,
+        };
+
+        (
+            opponent_before.len().saturating_sub(opponent_after.len()),
+            (own_before.len() + placed).saturating_sub(own_after.len()),
+        )
+    }
+
+    async fn load_board(
+        gtp_engine: Arc<Mutex<Engine>>,
+        theme: Theme,
+        game_setup: GameSetup,
+    ) -> Result<(Board, MoveHistory, EngineInfo), AppError> {
+        let mut gtp_engine = gtp_engine.lock().unwrap();
+        gtp_engine.wait_until_ready(game_setup.startup_attempts, game_setup.startup_timeout)?;
+        if let (Some(board_size), None) = (game_setup.board_size, &game_setup.sgf_game) {
+            gtp_engine.set_board_size(board_size)?;
+        }
+        gtp_engine.new_game(&game_setup.reset_commands)?;
+
+        if let Some(main_time) = game_setup.main_time {
+            if let Err(app_error) = gtp_engine.time_settings(main_time) {
+                warn!("engine doesn't take time_settings: {}", app_error.message);
+            }
+        }
+
+        let move_history = match &game_setup.sgf_game {
+            Some(sgf_game) => {
+                // engines without loadsgf get the moves played one by one
+                let loaded_by_engine = match game_setup.sgf_path.as_deref() {
+                    Some(path) => match gtp_engine.load_sgf(path) {
+                        Ok(()) => true,
+                        Err(app_error) => {
+                            warn!("{}, playing the moves instead", app_error.message);
+                            false
+                        }
+                    },
+                    None => false,
+                };
+                if !loaded_by_engine {
+                    gtp_engine.load_game(sgf_game)?;
+                }
+                MoveHistory::from_sgf(sgf_game, game_setup.first_player)
+            }
+            None if game_setup.handicap > 0 => {
+                let board_size = gtp_engine.query_board_size()?;
+                config::check_handicap(game_setup.handicap, board_size)?;
+                let handicap_stones = gtp_engine.fixed_handicap(game_setup.handicap)?;
+                MoveHistory::new(StoneColor::White).with_setup(handicap_stones, vec![])
+            }
+            None => MoveHistory::new(game_setup.first_player),
+        }
+        .with_max_undo(game_setup.max_undo);
+
+        if let Some(komi) = game_setup.komi {
+            if let Err(app_error) = gtp_engine.set_komi(komi) {
+                warn!("engine doesn't take komi: {}", app_error.message);
+            }
+        }
+
+        let board_size = gtp_engine.query_board_size()?;
+        let mut board = Board::new(board_size, theme);
+
+        let (black_stones, white_stones) = gtp_engine.list_all_stones()?;
+        board.set_stones(black_stones, white_stones);
+        if let Some(sgf_game) = &game_setup.sgf_game {
+            board.set_markers(sgf_game.markup.clone());
+        }
+
+        let engine_info = EngineInfo {
+            komi: gtp_engine.query_komi().ok(),
+            name: gtp_engine.identity(),
+            commands: gtp_engine.supported_commands(),
+        };
+
+        Ok((board, move_history, engine_info))
+    }
+
+    async fn restart_engine(
+        gtp_engine: Arc<Mutex<Engine>>,
+        game_setup: GameSetup,
+        position: SgfGame,
+    ) -> Result<(Vec<Stone>, Vec<Stone>), AppError> {
+        let mut gtp_engine = gtp_engine.lock().unwrap();
+        gtp_engine.restart(game_setup.startup_attempts, game_setup.startup_timeout)?;
+        gtp_engine.load_game(&position)?;
+
+        if let Some(komi) = position.komi {
+            if let Err(app_error) = gtp_engine.set_komi(komi) {
+                warn!("engine doesn't take komi: {}", app_error.message);
+            }
+        }
+
+        gtp_engine.list_all_stones()
     }
 
+    /// `Ok(Err(reason))` when the engine refuses the move
     async fn play_move(
         gtp_engine: Arc<Mutex<Engine>>,
         coords: Coords,
         color: StoneColor,
-    ) -> Result<(Vec<Stone>, Vec<Stone>), AppError> {
+    ) -> Result<Result<(MoveRecord, Vec<Stone>, Vec<Stone>), String>, AppError> {
         let mut gtp_engine = gtp_engine.lock().unwrap();
-        gtp_engine.play(color, coords)?;
+        if let PlayResponse::Illegal(reason) = gtp_engine.try_play(color, coords)? {
+            return Ok(Err(reason));
+        }
 
-        let black_stones = gtp_engine.list_stones(StoneColor::Black)?;
-        let white_stones = gtp_engine.list_stones(StoneColor::White)?;
+        let (black_stones, white_stones) = gtp_engine.list_all_stones()?;
 
-        Ok((black_stones, white_stones))
+        Ok(Ok((
+            MoveRecord {
+                color,
+                kind: MoveKind::Play(coords),
+                think_time: None,
+                captured: 0,
+                self_captured: 0,
+            },
+            black_stones,
+            white_stones,
+        )))
     }
 
-    async fn gen_next_move(
+    async fn pass_move(
         gtp_engine: Arc<Mutex<Engine>>,
-        player_color: StoneColor,
-    ) -> Result<(Vec<Stone>, Vec<Stone>), AppError> {
+        color: StoneColor,
+    ) -> Result<(MoveRecord, Vec<Stone>, Vec<Stone>), AppError> {
         let mut gtp_engine = gtp_engine.lock().unwrap();
-        gtp_engine.gen_move(player_color.inverse())?;
+        gtp_engine.pass(color)?;
 
-        let black_stones = gtp_engine.list_stones(StoneColor::Black)?;
-        let white_stones = gtp_engine.list_stones(StoneColor::White)?;
+        let (black_stones, white_stones) = gtp_engine.list_all_stones()?;
 
-        Ok((black_stones, white_stones))
+        Ok((
+            MoveRecord {
+                color,
+                kind: MoveKind::Pass,
+                think_time: None,
+                captured: 0,
+                self_captured: 0,
+            },
+            black_stones,
+            white_stones,
+        ))
     }
 
-    fn refresh_highlight_coords(&mut self) {
-        if let Some(board) = &mut self.board {
-            board.highlight_coords(parse_input_coords(self.next_move_input.clone()));
+    async fn gen_next_move(
+        gtp_engine: Arc<Mutex<Engine>>,
+        color: StoneColor,
+        move_number: usize,
+        time_left: Option<Duration>,
+    ) -> Result<(MoveRecord, Vec<Stone>, Vec<Stone>), AppError> {
+        let mut gtp_engine = gtp_engine.lock().unwrap();
+
+        if let Some(time_left) = time_left {
+            if let Err(app_error) = gtp_engine.time_left(color, time_left) {
+                warn!("engine doesn't take time_left: {}", app_error.message);
+            }
         }
+        let (response, think_time) = gtp_engine.gen_move(color, move_number)?;
+
+        let (black_stones, white_stones) = gtp_engine.list_all_stones()?;
+
+        Ok((
+            MoveRecord {
+                color,
+                kind: match response {
+                    GenMoveResponse::Position(coords) => MoveKind::Play(coords),
+                    GenMoveResponse::Pass => MoveKind::Pass,
+                    GenMoveResponse::Resign => MoveKind::Resign,
+                },
+                think_time: Some(think_time),
+                captured: 0,
+                self_captured: 0,
+            },
+            black_stones,
+            white_stones,
+        ))
+    }
+
+    async fn refresh_stones(
+        gtp_engine: Arc<Mutex<Engine>>,
+    ) -> Result<(Vec<Stone>, Vec<Stone>), AppError> {
+        let mut gtp_engine = gtp_engine.lock().unwrap();
+
+        let (black_stones, white_stones) = gtp_engine.list_all_stones()?;
+
+        Ok((black_stones, white_stones))
+    }
+
+    async fn undo_moves(
+        gtp_engine: Arc<Mutex<Engine>>,
+        count: usize,
+    ) -> Result<(usize, Vec<Stone>, Vec<Stone>), AppError> {
+        let mut gtp_engine = gtp_engine.lock().unwrap();
+
+        for _ in 0..count {
+            gtp_engine.undo()?;
+        }
+
+        let (black_stones, white_stones) = gtp_engine.list_all_stones()?;
+
+        Ok((count, black_stones, white_stones))
+    }
+
+    /// Score and, for the summary, the territory when the engine can list it
+    async fn final_score(
+        gtp_engine: Arc<Mutex<Engine>>,
+    ) -> Result<(ScoreResult, Option<Territory>), AppError> {
+        let mut gtp_engine = gtp_engine.lock().unwrap();
+        let score = gtp_engine.final_score()?;
+
+        Ok((score, gtp_engine.estimate_territory().ok()))
+    }
+
+    async fn suggested_move(
+        gtp_engine: Arc<Mutex<Engine>>,
+        color: StoneColor,
+    ) -> Result<GenMoveResponse, AppError> {
+        gtp_engine.lock().unwrap().suggest_move(color)
+    }
+
+    async fn group_status(
+        gtp_engine: Arc<Mutex<Engine>>,
+        coords: Coords,
+    ) -> Result<GroupStatus, AppError> {
+        gtp_engine.lock().unwrap().group_status(coords)
+    }
+
+    async fn estimate_territory(gtp_engine: Arc<Mutex<Engine>>) -> Result<Territory, AppError> {
+        gtp_engine.lock().unwrap().estimate_territory()
+    }
+
+    async fn list_dead_stones(gtp_engine: Arc<Mutex<Engine>>) -> Result<Vec<Coords>, AppError> {
+        gtp_engine.lock().unwrap().final_status_list("dead")
+    }
+
+    /// Adds `c` to the typed coordinates, returns whether it was taken as part of them
+    fn push_input_char(&mut self, c: char) -> bool {
+        if self.gtp_status != GtpStatus::Idle {
+            return false;
+        }
+
+        let board_size = self.board.as_ref().map_or(19, |board| board.board_size());
+
+        let accepted = match self.input_mode {
+            InputMode::Gtp => {
+                // any column letter, checked against the board size below
+                let fits = if get_column_number(c).is_some() {
+                    self.next_move_input.is_empty()
+                } else {
+                    INPUT_NUMBER_RANGE.contains(&c)
+                        && !self.next_move_input.is_empty()
+                        && self.next_move_input.len() < 3
+                };
+                let candidate = format!("{}{}", self.next_move_input, c);
+                let coords = parse_input_coords(&candidate, board_size);
+                let on_board =
+                    coords.col.is_some() && (coords.row.is_some() || candidate.len() == 1);
+
+                if fits && !on_board {
+                    self.status_message = Some(format!("Invalid coordinate {}", candidate));
+                }
+
+                fits && on_board
+            }
+            InputMode::Sgf => match self.next_move_input.strip_prefix(SGF_INPUT_START) {
+                Some(letters) => {
+                    let fits = c.is_ascii_lowercase() && letters.len() < 2;
+                    let on_board = fits && (c as u8 - b'a') < board_size;
+
+                    if fits && !on_board {
+                        self.status_message = Some(format!("Invalid coordinate {}{}", letters, c));
+                    }
+
+                    on_board
+                }
+                None => c == SGF_INPUT_START && self.next_move_input.is_empty(),
+            },
+        };
+
+        if accepted {
+            self.next_move_input.push(c);
+            self.refresh_highlight_coords();
+        }
+
+        accepted
+    }
+
+    fn save_game(&mut self) {
+        let board_size = match &self.board {
+            Some(board) => board.board_size(),
+            None => return,
+        };
+        let komi = self.engine_info.komi.or(self.komi).unwrap_or(0.0);
+        let sgf = self.move_history.to_sgf(board_size, komi);
+
+        self.status_message = Some(match sgf::write_sgf_file(&self.sgf_save_path, &sgf) {
+            Ok(()) => format!("Game saved to {}", self.sgf_save_path),
+            Err(app_error) => app_error.message,
+        });
+    }
+
+    /// Shows the cursor with the highlight of typed coordinates, replacing them. It starts at
+    /// tengen, and stops at the edges.
+    fn move_cursor(&mut self, rows: i8, cols: i8) {
+        if let Some(board) = &mut self.board {
+            let size = board.board_size();
+            let step = |n: u8, by: i8| (n as i8 + by).clamp(1, size as i8) as u8;
+            let cursor = match self.cursor {
+                Some(cursor) => Coords::from(step(cursor.row, rows), step(cursor.col, cols)),
+                None => Coords::from(size.div_ceil(2), size.div_ceil(2)),
+            };
+
+            self.cursor = Some(cursor);
+            self.next_move_input.clear();
+            board.highlight_coords(OptCoords {
+                row: Some(cursor.row),
+                col: Some(cursor.col),
+            });
+        }
+    }
+
+    fn refresh_recent_moves(&mut self) {
+        let ko = self.goban().and_then(|goban| self.ko_point(&goban));
+
+        if let Some(board) = &mut self.board {
+            board.set_recent_moves(self.move_history.recent_points(self.marked_moves));
+            board.set_ko(ko);
+        }
+    }
+
+    /// Replaces the markers with the stones left in atari, when the warnings are on
+    fn refresh_atari_markers(&mut self) {
+        if !self.atari_warnings {
+            return;
+        }
+
+        let markers = match self.goban() {
+            Some(goban) => self
+                .board
+                .iter()
+                .flat_map(|board| board.black_stones().iter().chain(board.white_stones()))
+                .map(|stone| Coords::from(stone.row, stone.col))
+                .filter(|coords| goban.liberties(coords) == 1)
+                .map(|coords| (coords, MarkerKind::Atari))
+                .collect(),
+            None => return,
+        };
+
+        if let Some(board) = &mut self.board {
+            board.set_markers(markers);
+        }
+    }
+
+    /// Lets whoever follows the game outside the UI know the position changed
+    fn publish_status(&mut self) {
+        self.refresh_title();
+
+        #[cfg(feature = "serve")]
+        if let (Some(server), Some(board)) = (&self.status_server, &self.board) {
+            server.publish(&GameStatus {
+                board_size: board.board_size(),
+                to_move: self.move_history.to_move(),
+                black_stones: board.black_stones(),
+                white_stones: board.white_stones(),
+                black_captures: self.move_history.captures(StoneColor::Black),
+                white_captures: self.move_history.captures(StoneColor::White),
+                last_move: self.move_history.moves().last().map(|record| LastMove {
+                    color: record.color,
+                    vertex: match record.kind {
+                        MoveKind::Play(coords) => coords.to_gtp(board.board_size()),
+                        MoveKind::Pass => "pass".to_string(),
+                        MoveKind::Resign => "resign".to_string(),
+                    },
+                }),
+            });
+        }
+    }
+
+    fn refresh_title(&mut self) {
+        let text = title::format_title(
+            self.move_history.to_move(),
+            self.move_history.len(),
+            self.game_result.as_deref().filter(|_| self.game_over),
+        );
+
+        if let Some(terminal_title) = &mut self.terminal_title {
+            terminal_title.set(&mut io::stdout(), &text);
+        }
+    }
+
+    fn board_view(&self) -> Element<'_, GameMessage, TuiRenderer> {
+        match &self.board {
+            Some(board) => match (self.too_small_message(board), self.board_margin(board)) {
+                (Some(message), _) => Text::new(message).into(),
+                (None, (0, 0)) => board.view(),
+                (None, (top, left)) => Column::new()
+                    .push(Text::new("").height(Length::Units(top)))
+                    .push(
+                        Row::new()
+                            .push(Text::new("").width(Length::Units(left)))
+                            .push(board.view()),
+                    )
+                    .into(),
+            },
+            None if self.gtp_status == GtpStatus::Loading => Text::new("Starting engine…").into(),
+            None => Text::new("Empty board").into(),
+        }
+    }
+
+    /// Shown instead of a board that would be clipped, `None` when it fits or the terminal size
+    /// isn't known yet. Checked on every view, so growing the terminal brings the board back.
+    fn too_small_message(&self, board: &Board) -> Option<String> {
+        let (width, height) = self.terminal_size?;
+        // the status line takes a row above the board
+        let (needed_width, needed_height) = (board.width(), board.height() + 1);
+
+        if width < needed_width || height < needed_height {
+            Some(format!(
+                "Terminal too small for the board, {}x{} needed",
+                needed_width, needed_height
+            ))
+        } else {
+            None
+        }
+    }
+
+    /// Configured margin, reduced so the board still fits the terminal once its size is known
+    fn board_margin(&self, board: &Board) -> (u16, u16) {
+        let (top, left) = self.board_margin;
+
+        match self.terminal_size {
+            Some((width, height)) => (
+                top.min(height.saturating_sub(board.height())),
+                left.min(width.saturating_sub(board.width())),
+            ),
+            None => (top, left),
+        }
+    }
+
+    /// Both clocks, the one running low flashes
+    fn clock_view(&self, clock: &GameClock) -> Element<'_, GameMessage, TuiRenderer> {
+        let now = Instant::now();
+
+        [StoneColor::Black, StoneColor::White]
+            .iter()
+            .fold(Row::new().push(Text::new("Clock:")), |row, color| {
+                let remaining = clock.remaining(*color, now);
+                let flash_on = remaining.as_secs().is_multiple_of(2);
+                let style = if remaining < self.low_time && flash_on {
+                    Style::default()
+                        .bg(self.theme.error_message_bg)
+                        .fg(self.theme.error_message_fg)
+                } else {
+                    Style::default()
+                };
+
+                row.push(Text::new(format!(" {} ", color.letter())))
+                    .push(Text::new(format_clock(remaining)).font(style))
+            })
+            .into()
+    }
+
+    /// One line with the essentials, shown above the board even when the panel is hidden
+    fn status_line_view(&self) -> Element<'_, GameMessage, TuiRenderer> {
+        let to_move = self.move_history.to_move();
+        let line = format_status_line(
+            self.move_history.len(),
+            (
+                self.move_history.captures(StoneColor::Black),
+                self.move_history.captures(StoneColor::White),
+            ),
+            self.engine_info.komi,
+            self.terminal_size
+                .map(|(width, _)| (width as usize).saturating_sub(TO_MOVE_LABEL.chars().count())),
+        );
+        let stone_color = match to_move {
+            StoneColor::Black => self.theme.black_stone_color,
+            StoneColor::White => self.theme.white_stone_color,
+        };
+
+        Row::new()
+            .push(Text::new(line))
+            .push(
+                Text::new("●").font(
+                    Style::default()
+                        .fg(stone_color)
+                        .bg(self.theme.board_bg_color),
+                ),
+            )
+            .push(Text::new(" to move"))
+            .into()
+    }
+
+    fn panel_view(&self) -> Element<'_, GameMessage, TuiRenderer> {
+        let (status_color, status_label) = self.gtp_status.indicator(&self.theme);
+
+        Column::new()
+            .spacing(1)
+            .push(
+                Row::new()
+                    .push(Text::new("Engine: "))
+                    .push(Text::new("●").font(Style::default().fg(status_color)))
+                    .push(Text::new(format!(" {}", status_label))),
+            )
+            .try_push(
+                if self.builtin_engine {
+                    Some("Built-in, very weak")
+                } else {
+                    self.engine_info.name.as_deref()
+                }
+                .map(|name| {
+                    Text::new(name).font(Style::default().fg(self.theme.disabled_fg_color))
+                }),
+            )
+            .push(
+                Row::new()
+                    .push(Text::new(if self.two_player {
+                        "To move: "
+                    } else {
+                        "You play: "
+                    }))
+                    .push(
+                        Text::new("●").font(
+                            Style::default()
+                                .fg(match self.player_color {
+                                    StoneColor::Black => self.theme.black_stone_color,
+                                    StoneColor::White => self.theme.white_stone_color,
+                                })
+                                .bg(self.theme.board_bg_color),
+                        ),
+                    ),
+            )
+            .push(Row::new().push(Text::new("Next move: ")).push(
+                if self.gtp_status == GtpStatus::Loading {
+                    Text::new("Loading").width(Length::Units(7)).font(
+                        Style::default()
+                            .bg(self.theme.loading_label_bg)
+                            .fg(self.theme.loading_label_fg),
+                    )
+                } else {
+                    Text::new(self.next_move_input.clone()).width(Length::Units(7))
+                },
+            ))
+            .try_push(
+                self.board
+                    .as_ref()
+                    .and_then(|board| board.area_difference())
+                    .map(|diff| Text::new(format_area_difference(diff))),
+            )
+            .try_push(self.game_result.as_ref().map(|result| {
+                Text::new(format!("Result: {}", result)).font(Style::default().bold())
+            }))
+            .try_push(
+                self.engine_info
+                    .komi
+                    .or(self.komi)
+                    .map(|komi| Text::new(format!("Komi: {}", komi))),
+            )
+            .try_push(self.clock.as_ref().map(|clock| self.clock_view(clock)))
+            .try_push(
+                Some(self.move_history.moves_since_capture())
+                    .filter(|_| self.show_moves_since_capture)
+                    .map(|moves| Text::new(format!("Since capture: {}", moves))),
+            )
+            .try_push(self.move_history.last_think_time().map(|last| {
+                Text::new(format!(
+                    "Think: {:.1}s (avg {:.1}s)",
+                    last.as_secs_f32(),
+                    self.move_history
+                        .average_think_time()
+                        .unwrap_or(last)
+                        .as_secs_f32()
+                ))
+            }))
+            .try_push(
+                self.hover_coords
+                    .zip(self.board.as_ref())
+                    .map(|(coords, board)| {
+                        Text::new(format!("Hover: {}", coords.to_gtp(board.board_size())))
+                    }),
+            )
+            .try_push(self.status_message.clone().map(Text::new))
+            .try_push(self.gtp_error.clone().map(|message| {
+                Container::new(Text::new(message))
+                    .style(
+                        Style::default()
+                            .bg(self.theme.error_message_bg)
+                            .fg(self.theme.error_message_fg),
+                    )
+                    .width(Length::Units(18))
+            }))
+            .into()
+    }
+
+    fn play(&mut self, coords: Coords) -> Command<GameMessage> {
+        // a move sent while the engine is busy would be answered out of turn
+        if self.gtp_status != GtpStatus::Idle || self.game_over || self.confirmation.is_some() {
+            return Command::none();
+        }
+
+        if let Err(app_error) = self.move_history.check_turn(self.player_color) {
+            self.status_message = Some(app_error.message);
+            return Command::none();
+        }
+
+        if let Some(goban) = self.goban() {
+            if let Err(app_error) =
+                self.move_validator
+                    .validate_play(&goban, self.player_color, coords)
+            {
+                self.status_message = Some(app_error.message);
+                return Command::none();
+            }
+        }
+
+        if let Some(allowed) = self.off_book_opening(&coords) {
+            let board_size = self.board.as_ref().map_or(19, |b| b.board_size());
+            let points: Vec<String> = allowed.iter().map(|c| c.to_gtp(board_size)).collect();
+
+            self.status_message = Some(format!("Off-book opening, try {}", points.join(", ")));
+            if let Some(board) = &mut self.board {
+                board.set_markers(
+                    allowed
+                        .into_iter()
+                        .map(|coords| (coords, MarkerKind::Hint))
+                        .collect(),
+                );
+            }
+            return Command::none();
+        }
+
+        if self.confirm_first_move && self.is_board_empty() {
+            self.confirmation = Some(Confirmation::FirstMove(coords));
+            self.status_message = Some(format!(
+                "Start the game at {}? (y/n)",
+                coords.to_gtp(self.board.as_ref().map_or(19, |b| b.board_size()))
+            ));
+            return Command::none();
+        }
+
+        self.send_play(coords)
+    }
+
+    /// Plays a random legal move as the human, holding the key keeps the play/genmove cycle
+    /// going to surface races. Ko isn't known locally, so the engine may still reject a move.
+    #[cfg(feature = "debug-moves")]
+    fn play_random_move(&mut self) -> Command<GameMessage> {
+        let legal_moves = match self.goban() {
+            Some(goban) => goban.legal_moves(self.player_color),
+            None => return Command::none(),
+        };
+
+        if legal_moves.is_empty() {
+            self.status_message = Some("No legal moves left".to_string());
+            return Command::none();
+        }
+
+        self.play(legal_moves[fastrand::usize(..legal_moves.len())])
+    }
+
+    /// The opening points still free, when the player is within the restricted opening moves
+    /// and `coords` isn't one of them
+    fn off_book_opening(&self, coords: &Coords) -> Option<Vec<Coords>> {
+        self.opening_points_left(self.player_color)
+            .filter(|allowed| !allowed.contains(coords))
+    }
+
+    /// The opening points still free for `color`, while it's within the restricted opening moves
+    fn opening_points_left(&self, color: StoneColor) -> Option<Vec<Coords>> {
+        let board_size = self.board.as_ref()?.board_size();
+        let goban = self.goban()?;
+        let moves = self
+            .move_history
+            .moves()
+            .iter()
+            .filter(|record| record.color == color)
+            .count();
+
+        if self.opening_points.is_empty() || moves >= self.opening_points_moves {
+            return None;
+        }
+
+        let allowed: Vec<Coords> = self
+            .opening_points
+            .iter()
+            .filter_map(|point| Coords::from_gtp(point, board_size))
+            .filter(|point| goban.is_legal(color, point))
+            .collect();
+
+        // with every opening point taken there's nothing to hold the moves to
+        Some(allowed).filter(|allowed| !allowed.is_empty())
+    }
+
+    /// Position on the board, to check moves locally
+    fn goban(&self) -> Option<Goban> {
+        let board = self.board.as_ref()?;
+        let stones: Vec<Stone> = board
+            .black_stones()
+            .iter()
+            .chain(board.white_stones())
+            .copied()
+            .collect();
+
+        let goban = Goban::from_stones(board.board_size(), &stones);
+        let ko = self.ko_point(&goban).map(|(coords, _)| coords);
+
+        Some(goban.with_ko(ko))
+    }
+
+    /// Where the last move captured a single stone and could be captured right back, which the
+    /// other color has to wait a move to do
+    fn ko_point(&self, goban: &Goban) -> Option<(Coords, StoneColor)> {
+        let record = self.move_history.moves().last()?;
+        let coords = match record.kind {
+            MoveKind::Play(coords) if record.captured == 1 => coords,
+            _ => return None,
+        };
+
+        let neighbors = goban.neighbors(&coords);
+        let alone = neighbors
+            .iter()
+            .all(|neighbor| goban.get(neighbor) != Some(record.color));
+        let liberties: Vec<Coords> = neighbors
+            .into_iter()
+            .filter(|neighbor| goban.get(neighbor).is_none())
+            .collect();
+
+        match liberties.as_slice() {
+            [ko] if alone && goban.get(&coords) == Some(record.color) => {
+                Some((*ko, record.color.inverse()))
+            }
+            _ => None,
+        }
+    }
+
+    /// No intersection is left where the player could play
+    fn has_no_legal_moves(&self) -> bool {
+        self.goban()
+            .is_some_and(|goban| goban.legal_moves(self.player_color).is_empty())
+    }
+
+    /// The player passes, the game is scored once both players pass in a row
+    fn pass(&mut self) -> Command<GameMessage> {
+        if self.gtp_status != GtpStatus::Idle || self.game_over || self.confirmation.is_some() {
+            return Command::none();
+        }
+
+        if let Err(app_error) = self.move_history.check_turn(self.player_color) {
+            self.status_message = Some(app_error.message);
+            return Command::none();
+        }
+
+        self.status_message = None;
+        self.send_pass()
+    }
+
+    fn send_pass(&mut self) -> Command<GameMessage> {
+        if let Err(app_error) = self.move_validator.validate_pass(self.player_color) {
+            self.status_message = Some(app_error.message);
+            return Command::none();
+        }
+
+        self.gtp_status = GtpStatus::Loading;
+        self.next_move_input = "".to_string();
+        self.refresh_highlight_coords();
+
+        Command::perform(
+            GoGame::pass_move(self.gtp_engine.clone(), self.player_color),
+            |result| match result {
+                Ok((record, black_stones, white_stones)) => {
+                    GameMessage::AfterStonePlayed(record, black_stones, white_stones)
+                }
+                Err(app_error) => GameMessage::GtpError(app_error.message),
+            },
+        )
+    }
+
+    fn send_play(&mut self, coords: Coords) -> Command<GameMessage> {
+        if let Some(board) = &mut self.board {
+            board.set_markers(vec![]);
+        }
+        self.gtp_status = GtpStatus::Loading;
+        self.status_message = None;
+        self.next_move_input = "".to_string();
+        self.refresh_highlight_coords();
+
+        Command::perform(
+            GoGame::play_move(self.gtp_engine.clone(), coords, self.player_color),
+            move |result| match result {
+                Ok(Ok((record, black_stones, white_stones))) => {
+                    GameMessage::AfterStonePlayed(record, black_stones, white_stones)
+                }
+                Ok(Err(reason)) => GameMessage::MoveRejected(coords, reason),
+                Err(app_error) => GameMessage::GtpError(app_error.message),
+            },
+        )
+    }
+
+    /// Ends the game when the last move calls for it, otherwise lets the engine play if it's its
+    /// turn
+    fn continue_game(&mut self) -> Command<GameMessage> {
+        self.follow_turn();
+        // a move arriving after the end, like one in flight when the flag fell, doesn't carry the
+        // game on
+        if self.game_over {
+            self.gtp_status = GtpStatus::Idle;
+            return Command::none();
+        }
+
+        if let Some(clock) = &mut self.clock {
+            clock.start(self.move_history.to_move(), Instant::now());
+        }
+
+        match self.move_history.progress(self.two_passes, self.on_resign) {
+            GameProgress::Score => return self.score_game(),
+            GameProgress::ConfirmScore => {
+                self.confirmation = Some(Confirmation::Score);
+                self.status_message = Some("Both passed, score the game? (y/n)".to_string());
+            }
+            GameProgress::Resigned(color) => self.finish_game(
+                format!("{}+R", color.inverse().letter()),
+                if color == self.player_color {
+                    format!("{} resigned", color.name())
+                } else {
+                    format!("{} resigned, you win", color.name())
+                },
+            ),
+            GameProgress::ResignedReviewing(color) => {
+                self.game_result = Some(format!("{}+R", color.inverse().letter()));
+                self.status_message = Some(format!("{} resigned, reviewing", color.name()));
+            }
+            GameProgress::Playing if self.move_history.to_move() != self.player_color => {
+                if self.auto_respond {
+                    return self.request_ai_move();
+                }
+                self.status_message = Some("Press g for the engine's move".to_string());
+            }
+            GameProgress::Playing if self.demonstrating => {
+                return self.request_genmove(self.player_color);
+            }
+            GameProgress::Playing if self.auto_pass && self.has_no_legal_moves() => {
+                self.status_message = Some("No legal moves left, passing".to_string());
+                return self.send_pass();
+            }
+            GameProgress::Playing => {}
+        }
+
+        self.gtp_status = GtpStatus::Idle;
+        Command::none()
+    }
+
+    /// In two-player mode the keyboard plays whoever is to move
+    fn follow_turn(&mut self) {
+        if !self.two_player {
+            return;
+        }
+
+        let to_move = self.move_history.to_move();
+        self.player_color = to_move;
+        if let Some(board) = &mut self.board {
+            board.set_player_color(to_move);
+        }
+    }
+
+    fn answer_confirmation(
+        &mut self,
+        confirmation: Confirmation,
+        confirmed: bool,
+    ) -> Command<GameMessage> {
+        match (confirmation, confirmed) {
+            (Confirmation::Score, true) => self.score_game(),
+            (Confirmation::Score, false) => {
+                self.status_message = Some("Play continues".to_string());
+
+                if self.move_history.to_move() != self.player_color {
+                    return self.request_ai_move();
+                }
+
+                Command::none()
+            }
+            (Confirmation::FirstMove(coords), true) => self.send_play(coords),
+            (Confirmation::FirstMove(_), false) => {
+                self.status_message = None;
+                Command::none()
+            }
+            (Confirmation::NewGame(handicap), true) => self.start_new_game(handicap),
+            (Confirmation::NewGame(_), false) => {
+                self.status_message = None;
+                Command::none()
+            }
+        }
+    }
+
+    /// Exits with `status` on the second press of a quit key, the first one only asks
+    fn request_quit(&mut self, status: u8, key: &str) {
+        let now = Instant::now();
+
+        match self.quit_requested_at.take() {
+            Some(requested_at) if now.duration_since(requested_at) <= QUIT_WINDOW => {
+                self.should_exit = Some(status);
+            }
+            _ => {
+                self.quit_requested_at = Some(now);
+                self.status_message = Some(format!("Press {} again to quit", key));
+            }
+        }
+    }
+
+    fn cancel_quit(&mut self) {
+        if self.quit_requested_at.take().is_some() {
+            self.status_message = None;
+        }
+    }
+
+    /// Starts over right away once the game is over, otherwise asks first. `handicap` replaces
+    /// the configured handicap stones.
+    fn new_game(&mut self, handicap: Option<u8>) -> Command<GameMessage> {
+        if self.confirmation.is_some() {
+            return Command::none();
+        }
+
+        if !self.game_over && self.move_history.len() > 0 {
+            self.confirmation = Some(Confirmation::NewGame(handicap));
+            self.status_message = Some("Abandon this game and start a new one? (y/n)".to_string());
+            return Command::none();
+        }
+
+        self.start_new_game(handicap)
+    }
+
+    fn start_new_game(&mut self, handicap: Option<u8>) -> Command<GameMessage> {
+        let mut game_setup = match &self.new_game_setup {
+            Some(game_setup) => game_setup.clone(),
+            None => return Command::none(),
+        };
+        if let Some(handicap) = handicap {
+            game_setup.handicap = handicap;
+        }
+
+        self.gtp_status = GtpStatus::Loading;
+        self.gtp_error = None;
+        self.status_message = None;
+        self.next_move_input = "".to_string();
+        self.player_color = game_setup.player_color;
+        self.demonstrating = false;
+        self.menu = None;
+        self.game_result = None;
+        self.game_over = false;
+        self.summary = None;
+        self.komi = game_setup.komi;
+        self.clock = game_setup.main_time.map(GameClock::new);
+
+        Command::perform(
+            GoGame::load_board(self.gtp_engine.clone(), self.theme.clone(), game_setup),
+            board_loaded_message,
+        )
+    }
+
+    /// No moves and no setup stones yet
+    fn is_board_empty(&self) -> bool {
+        let no_stones = match &self.board {
+            Some(board) => board.black_stones().is_empty() && board.white_stones().is_empty(),
+            None => true,
+        };
+
+        self.move_history.len() == 0 && no_stones
+    }
+
+    /// Asks the engine whether the group under the typed coordinates, or else under the mouse,
+    /// lives, for life and death problems set up on the board
+    fn check_group_status(&mut self) -> Command<GameMessage> {
+        if !self.check_supported("final_status_list") {
+            return Command::none();
+        }
+
+        let goban = self.goban();
+        let coords = self
+            .board
+            .as_mut()
+            .and_then(|board| board.get_valid_highlight_coords())
+            .or(self.hover_coords)
+            .filter(|coords| goban.is_some_and(|goban| goban.get(coords).is_some()));
+
+        let coords = match coords {
+            Some(coords) => coords,
+            None => {
+                self.status_message = Some("Point at a stone to check its group".to_string());
+                return Command::none();
+            }
+        };
+
+        self.gtp_status = GtpStatus::Loading;
+
+        Command::perform(
+            GoGame::group_status(self.gtp_engine.clone(), coords),
+            move |result| match result {
+                Ok(status) => GameMessage::GroupStatusReported(coords, status),
+                Err(app_error) => GameMessage::GtpError(app_error.message),
+            },
+        )
+    }
+
+    fn toggle_territory(&mut self) -> Command<GameMessage> {
+        let showing = self
+            .board
+            .as_ref()
+            .is_some_and(|board| board.is_showing_territory());
+        if !showing && !self.check_supported("final_status_list") {
+            return Command::none();
+        }
+
+        if let Some(board) = &mut self.board {
+            if board.is_showing_territory() {
+                board.set_territory(None);
+            } else {
+                self.gtp_status = GtpStatus::Loading;
+
+                return Command::perform(
+                    GoGame::estimate_territory(self.gtp_engine.clone()),
+                    |result| match result {
+                        Ok(territory) => GameMessage::TerritoryEstimated(territory),
+                        Err(app_error) => GameMessage::GtpError(app_error.message),
+                    },
+                );
+            }
+        }
+
+        Command::none()
+    }
+
+    /// Review overlay of the stones the engine would remove when scoring
+    fn toggle_dead_stones(&mut self) -> Command<GameMessage> {
+        let showing = self
+            .board
+            .as_ref()
+            .is_some_and(|board| board.is_showing_dead_stones());
+        if !showing && !self.check_supported("final_status_list") {
+            return Command::none();
+        }
+
+        if let Some(board) = &mut self.board {
+            if board.is_showing_dead_stones() {
+                board.set_dead_stones(vec![]);
+            } else {
+                self.gtp_status = GtpStatus::Loading;
+
+                return Command::perform(
+                    GoGame::list_dead_stones(self.gtp_engine.clone()),
+                    |result| match result {
+                        Ok(dead_stones) => GameMessage::DeadStonesListed(dead_stones),
+                        Err(app_error) => GameMessage::GtpError(app_error.message),
+                    },
+                );
+            }
+        }
+
+        Command::none()
+    }
+
+    /// Starts a new engine with the game's position when the failure came from the engine dying.
+    /// Only one restart is tried until a move goes through, an engine that dies again on the
+    /// same position is reported with `message`.
+    fn restart_dead_engine(&mut self, message: &str) -> Option<Command<GameMessage>> {
+        let board_size = self.board.as_ref()?.board_size();
+        let game_setup = self.new_game_setup.clone()?;
+
+        if self.engine_restarted || self.gtp_engine.lock().unwrap().is_alive() {
+            return None;
+        }
+
+        warn!("engine died ({}), restarting it", message);
+        self.engine_restarted = true;
+        self.gtp_status = GtpStatus::Loading;
+        self.status_message = Some("Engine stopped, restarting it".to_string());
+
+        let position = self.move_history.to_sgf_game(board_size, self.komi);
+        let message = message.to_string();
+
+        Some(Command::perform(
+            GoGame::restart_engine(self.gtp_engine.clone(), game_setup, position),
+            move |result| match result {
+                Ok((black_stones, white_stones)) => {
+                    GameMessage::EngineRestarted(black_stones, white_stones)
+                }
+                Err(app_error) => GameMessage::GtpError(format!(
+                    "{} (restarting the engine failed: {})",
+                    message, app_error.message
+                )),
+            },
+        ))
+    }
+
+    /// Keys of features the engine lacks only explain why nothing happens
+    fn check_supported(&mut self, command: &str) -> bool {
+        let supported = self.engine_info.supports(command);
+
+        if !supported {
+            self.status_message = Some(format!("Not supported by the engine (no {})", command));
+        }
+
+        supported
+    }
+
+    /// Runs the highlighted menu entry and closes the menu, entries the engine doesn't support
+    /// leave it open
+    fn select_menu_action(&mut self) -> Command<GameMessage> {
+        let action = match self.menu.as_ref().map(|menu| menu.selected_action()) {
+            Some(Some(action)) => action,
+            Some(None) => {
+                self.status_message = Some("Not supported by the engine".to_string());
+                return Command::none();
+            }
+            None => return Command::none(),
+        };
+
+        self.menu = None;
+
+        if self.gtp_status != GtpStatus::Idle {
+            return Command::none();
+        }
+
+        match action {
+            MenuAction::Undo => self.undo(),
+            MenuAction::Score if !self.game_over => self.score_game(),
+            MenuAction::Score => Command::none(),
+            MenuAction::Territory => self.toggle_territory(),
+            MenuAction::Analysis => self.suggest_move(),
+            MenuAction::Handicap => {
+                let handicap = self
+                    .new_game_setup
+                    .as_ref()
+                    .map_or(0, |setup| setup.handicap);
+                self.new_game(Some(handicap.max(MIN_HANDICAP)))
+            }
+        }
+    }
+
+    /// Asks the engine which move it would play for the player and marks it on the board
+    fn suggest_move(&mut self) -> Command<GameMessage> {
+        if self.game_over || self.move_history.to_move() != self.player_color {
+            return Command::none();
+        }
+
+        self.gtp_status = GtpStatus::Loading;
+
+        Command::perform(
+            GoGame::suggested_move(self.gtp_engine.clone(), self.player_color),
+            |result| match result {
+                Ok(GenMoveResponse::Position(coords)) => GameMessage::MoveSuggested(Some(coords)),
+                Ok(GenMoveResponse::Pass | GenMoveResponse::Resign) => {
+                    GameMessage::MoveSuggested(None)
+                }
+                Err(app_error) => GameMessage::GtpError(app_error.message),
+            },
+        )
+    }
+
+    /// Hides the stones of a loaded game to reveal them in move order, when configured
+    fn start_replay(&mut self) {
+        if let (Some(board), Some(_)) = (&mut self.board, self.replay_stone_delay) {
+            if self.move_history.len() == 0 {
+                return;
+            }
+
+            let replay = Replay::new(
+                board.black_stones(),
+                board.white_stones(),
+                &self.move_history,
+            );
+            let (black_stones, white_stones) = replay.visible();
+
+            board.set_stones(black_stones, white_stones);
+            self.replay = Some(replay);
+        }
+    }
+
+    /// Shows the whole loaded position, when the replay ends or is interrupted
+    fn finish_replay(&mut self) {
+        if let (Some(board), Some(replay)) = (&mut self.board, self.replay.take()) {
+            let (black_stones, white_stones) = replay.finish();
+            board.set_stones(black_stones, white_stones);
+        }
+    }
+
+    /// No more moves are accepted, the clocks stop and the summary is shown
+    fn finish_game(&mut self, result: String, message: String) {
+        let now = Instant::now();
+
+        self.summary = Some(GameSummary {
+            result: result.clone(),
+            captures: (
+                self.move_history.captures(StoneColor::Black),
+                self.move_history.captures(StoneColor::White),
+            ),
+            territory: self
+                .board
+                .as_ref()
+                .and_then(|board| board.territory_points()),
+            moves: self.move_history.len(),
+            duration: self
+                .started_at
+                .map_or(Duration::from_secs(0), |started_at| now - started_at),
+        });
+        self.game_result = Some(result);
+        self.game_over = true;
+        self.status_message = Some(message);
+
+        if let Some(clock) = &mut self.clock {
+            clock.stop(now);
+        }
+        self.refresh_title();
+    }
+
+    fn score_game(&mut self) -> Command<GameMessage> {
+        if !self.engine_info.supports("final_score") {
+            self.gtp_status = GtpStatus::Idle;
+            self.status_message =
+                Some("The engine can't score games (no final_score command)".to_string());
+            return Command::none();
+        }
+
+        self.gtp_status = GtpStatus::Loading;
+
+        Command::perform(
+            GoGame::final_score(self.gtp_engine.clone()),
+            |result| match result {
+                Ok((score, territory)) => GameMessage::GameScored(score, territory),
+                Err(app_error) => GameMessage::GtpError(app_error.message),
+            },
+        )
+    }
+
+    /// The engine's move on request, when it doesn't reply on its own (`auto_respond` off)
+    fn request_engine_reply(&mut self) -> Command<GameMessage> {
+        if self.game_over
+            || self.confirmation.is_some()
+            || self.move_history.to_move() == self.player_color
+        {
+            return Command::none();
+        }
+
+        self.status_message = None;
+        self.request_ai_move()
+    }
+
+    fn request_ai_move(&mut self) -> Command<GameMessage> {
+        self.request_genmove(self.player_color.inverse())
+    }
+
+    /// The engine's move for `color`, which is played as is, even when `color` is the player's
+    fn request_genmove(&mut self, color: StoneColor) -> Command<GameMessage> {
+        self.gtp_status = GtpStatus::Loading;
+
+        // the engine is held to the opening points like the player, one of them is played for it
+        if let Some(allowed) = self.opening_points_left(color) {
+            let coords = allowed[fastrand::usize(..allowed.len())];
+
+            return Command::perform(
+                GoGame::play_move(self.gtp_engine.clone(), coords, color),
+                |result| match result {
+                    Ok(Ok((record, black_stones, white_stones))) => {
+                        GameMessage::AfterGenMove(record, black_stones, white_stones)
+                    }
+                    Ok(Err(reason)) => GameMessage::GtpError(reason),
+                    Err(app_error) => GameMessage::GtpError(app_error.message),
+                },
+            );
+        }
+
+        Command::perform(
+            GoGame::gen_next_move(
+                self.gtp_engine.clone(),
+                color,
+                self.move_history.len() + 1,
+                self.clock
+                    .as_ref()
+                    .map(|clock| clock.remaining(color, Instant::now())),
+            ),
+            |result| match result {
+                Ok((record, black_stones, white_stones)) => {
+                    GameMessage::AfterGenMove(record, black_stones, white_stones)
+                }
+                Err(app_error) => GameMessage::GtpError(app_error.message),
+            },
+        )
+    }
+
+    /// Toggles the engine playing the player's side too, to demonstrate how it would play it
+    fn toggle_demonstration(&mut self) -> Command<GameMessage> {
+        self.demonstrating = !self.demonstrating;
+        self.status_message = Some(format!(
+            "Engine plays your moves: {}",
+            if self.demonstrating { "on" } else { "off" }
+        ));
+
+        let players_turn = self.move_history.to_move() == self.player_color;
+        if self.demonstrating && players_turn && !self.game_over && self.confirmation.is_none() {
+            return self.request_genmove(self.player_color);
+        }
+
+        Command::none()
+    }
+
+    fn undo(&mut self) -> Command<GameMessage> {
+        if !self.check_supported("undo") {
+            return Command::none();
+        }
+
+        // the engine's reply is taken back too, so it's the player's turn again
+        let count = match self.move_history.moves().last() {
+            Some(record) if record.color != self.player_color && !self.two_player => 2,
+            _ => 1,
+        };
+
+        if self.move_history.undoable_moves() < count {
+            self.status_message = Some("Cannot undo further".to_string());
+            return Command::none();
+        }
+
+        self.gtp_status = GtpStatus::Loading;
+
+        Command::perform(
+            GoGame::undo_moves(self.gtp_engine.clone(), count),
+            |result| match result {
+                Ok((count, black_stones, white_stones)) => {
+                    GameMessage::MovesUndone(count, black_stones, white_stones)
+                }
+                Err(app_error) => GameMessage::GtpError(app_error.message),
+            },
+        )
+    }
+
+    fn swap_colors(&mut self) -> Command<GameMessage> {
+        match self.move_history.swap_colors(self.player_color) {
+            Ok(player_color) => {
+                self.player_color = player_color;
+                if let Some(board) = &mut self.board {
+                    board.set_player_color(player_color);
+                }
+                self.status_message = Some(format!("You play {}", player_color.name()));
+
+                if self.move_history.to_move() != self.player_color {
+                    return self.request_ai_move();
+                }
+            }
+            Err(app_error) => self.status_message = Some(app_error.message),
+        }
+
+        Command::none()
+    }
+
+    fn cycle_theme(&mut self) {
+        let next_preset = Theme::next_preset_name(&self.theme_preset);
+
+        let theme = Theme::preset(next_preset).and_then(|theme| {
+            theme
+                .with_ascii(self.ascii_glyphs.0, self.ascii_glyphs.1)
+                .with_overrides(&self.theme_overrides)
+        });
+
+        match theme {
+            Ok(theme) => {
+                if let Some(board) = &mut self.board {
+                    board.set_theme(theme.clone());
+                }
+                self.theme = theme;
+                self.theme_preset = next_preset.to_string();
+                self.status_message = Some(format!("Theme: {}", next_preset));
+            }
+            Err(app_error) => self.status_message = Some(app_error.message),
+        }
+    }
+
+    fn refresh_highlight_coords(&mut self) {
+        if let Some(board) = &mut self.board {
+            board.highlight_coords(match self.input_mode {
+                InputMode::Gtp => parse_input_coords(&self.next_move_input, board.board_size()),
+                InputMode::Sgf => parse_sgf_input_coords(
+                    self.next_move_input.trim_start_matches(SGF_INPUT_START),
+                    board.board_size(),
+                ),
+            });
+        }
+    }
+}
+
+fn board_loaded_message(result: Result<(Board, MoveHistory, EngineInfo), AppError>) -> GameMessage {
+    match result {
+        Ok((board, move_history, engine_info)) => {
+            GameMessage::BoardLoaded(board, move_history, engine_info)
+        }
+        Err(app_error) => GameMessage::GtpError(app_error.message),
+    }
+}
+
+/// Input and board changes skip the rest of a replay, so moves are always made on the whole
+/// position
+fn interrupts_replay(message: &GameMessage) -> bool {
+    matches!(
+        message,
+        GameMessage::EventOccurred(Event::Keyboard(_))
+            | GameMessage::BoardClicked(_)
+            | GameMessage::AfterStonePlayed(..)
+            | GameMessage::AfterGenMove(..)
+            | GameMessage::MovesUndone(..)
+            | GameMessage::StonesRefreshed(..)
+            | GameMessage::EngineRestarted(..)
+    )
+}
+
+/// Warning for an engine that reports a komi other than the one it was told, which would make
+/// its score differ from what the player expects. Engines that can't report komi are trusted.
+fn komi_mismatch(configured: Option<f32>, engine: Option<f32>) -> Option<String> {
+    match (configured, engine) {
+        (Some(configured), Some(engine)) if (configured - engine).abs() > f32::EPSILON => {
+            Some(format!(
+                "Warning: engine komi is {}, but {} was configured",
+                engine, configured
+            ))
+        }
+        _ => None,
+    }
+}
+
+/// Color the player takes over from a loaded game, `to-move` being whoever plays next
+fn continue_as_color(continue_as: &str, to_move: StoneColor) -> StoneColor {
+    match continue_as {
+        "black" => StoneColor::Black,
+        "white" => StoneColor::White,
+        _ => to_move,
+    }
+}
+
+/// What the status line ends with, the stone is drawn in the color to move
+const TO_MOVE_LABEL: &str = "● to move";
+
+/// Start of the status line, up to the stone of the color to move. Komi, then captures, then the
+/// move number are left out when they don't fit `max_width`.
+fn format_status_line(
+    move_number: usize,
+    (black_captures, white_captures): (usize, usize),
+    komi: Option<f32>,
+    max_width: Option<usize>,
+) -> String {
+    let mut segments = vec![
+        format!("Move {}", move_number),
+        format!("B:{} W:{} captures", black_captures, white_captures),
+    ];
+    if let Some(komi) = komi {
+        segments.push(format!("Komi {}", komi));
+    }
+
+    loop {
+        let line: String = segments.iter().map(|s| format!("{} | ", s)).collect();
+
+        match max_width {
+            Some(max_width) if line.chars().count() > max_width && !segments.is_empty() => {
+                segments.pop();
+            }
+            _ => return line,
+        }
+    }
+}
+
+fn format_area_difference(diff: i32) -> String {
+    match diff {
+        0 => "Area: even".to_string(),
+        d if d > 0 => format!("Area: B+{}", d),
+        d => format!("Area: W+{}", -d),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::clock::GameClock;
+    use crate::core::config::AppConfig;
+    use crate::core::engine::tests::{dead_engine, err, mock_engine, ok};
+    use crate::core::sgf::SgfMove;
+
+    fn test_game() -> GoGame {
+        let (engine, _) = mock_engine(vec![]);
+        let app_config: AppConfig = serde_yaml::from_str("empty: true").unwrap();
+
+        GoGame::with_config(
+            Arc::new(Mutex::new(engine)),
+            &app_config.general,
+            &app_config.theme,
+        )
+        .unwrap()
+    }
+
+    /// Test game with an empty board, as if the engine had just started
+    fn loaded_game(board_size: u8, first_player: StoneColor) -> GoGame {
+        let mut game = test_game();
+        game.update(GameMessage::BoardLoaded(
+            Board::new(board_size, Theme::default()),
+            MoveHistory::new(first_player),
+            EngineInfo::default(),
+        ));
+        game
+    }
+
+    fn record(color: StoneColor, kind: MoveKind) -> MoveRecord {
+        MoveRecord {
+            color,
+            kind,
+            think_time: None,
+            captured: 0,
+            self_captured: 0,
+        }
+    }
+
+    /// Setup of a new empty game, with black moving first
+    fn game_setup(player_color: StoneColor) -> GameSetup {
+        GameSetup {
+            startup_attempts: 1,
+            startup_timeout: Duration::from_millis(1),
+            reset_commands: vec![],
+            board_size: None,
+            sgf_game: None,
+            sgf_path: None,
+            main_time: None,
+            komi: None,
+            first_player: StoneColor::Black,
+            player_color,
+            handicap: 0,
+            max_undo: None,
+        }
+    }
+
+    fn character(c: char) -> GameMessage {
+        GameMessage::EventOccurred(Event::Keyboard(keyboard::Event::CharacterReceived(c)))
+    }
+
+    fn key(key_code: keyboard::KeyCode) -> GameMessage {
+        GameMessage::EventOccurred(Event::Keyboard(keyboard::Event::KeyReleased {
+            key_code,
+            modifiers: keyboard::Modifiers::default(),
+        }))
+    }
+
+    #[test]
+    fn input_is_ignored_while_the_engine_is_busy() {
+        let mut game = test_game();
+        game.board = Some(Board::new(9, Theme::default()));
+        game.gtp_status = GtpStatus::Loading;
+        game.status_message = Some("thinking".to_string());
+
+        game.update(character('D'));
+        game.update(character('4'));
+        game.update(GameMessage::BoardClicked(Some(Coords::from(4, 4))));
+        game.play(Coords::from(4, 4));
+
+        // a play that went through would have cleared the status message
+        assert_eq!("", game.next_move_input);
+        assert_eq!(Some("thinking".to_string()), game.status_message);
+
+        game.gtp_status = GtpStatus::Idle;
+        game.update(character('D'));
+        game.update(character('4'));
+
+        assert_eq!("D4", game.next_move_input);
+    }
+
+    #[test]
+    fn first_move_confirmation_only_applies_to_move_one() {
+        let mut game = loaded_game(9, StoneColor::Black);
+        game.confirm_first_move = true;
+
+        game.play(Coords::from(4, 4));
+        assert_eq!(
+            Some(Confirmation::FirstMove(Coords::from(4, 4))),
+            game.confirmation
+        );
+        assert_eq!(GtpStatus::Idle, game.gtp_status);
+
+        game.update(character('y'));
+        assert_eq!(None, game.confirmation);
+        assert_eq!(GtpStatus::Loading, game.gtp_status);
+
+        game.move_history.push(record(
+            StoneColor::Black,
+            MoveKind::Play(Coords::from(4, 4)),
+        ));
+        game.move_history.push(MoveRecord {
+            think_time: Some(Duration::from_millis(10)),
+            ..record(StoneColor::White, MoveKind::Pass)
+        });
+        game.gtp_status = GtpStatus::Idle;
+
+        game.play(Coords::from(5, 5));
+        assert_eq!(None, game.confirmation);
+        assert_eq!(GtpStatus::Loading, game.gtp_status);
+    }
+
+    #[test]
+    fn first_move_is_played_right_away_by_default() {
+        let mut game = loaded_game(9, StoneColor::Black);
+
+        game.play(Coords::from(4, 4));
+        assert_eq!(None, game.confirmation);
+        assert_eq!(GtpStatus::Loading, game.gtp_status);
+    }
+
+    #[cfg(feature = "debug-moves")]
+    #[test]
+    fn random_moves_respect_busy_and_game_over() {
+        let mut game = loaded_game(9, StoneColor::Black);
+
+        game.game_over = true;
+        game.update(character('z'));
+        assert_eq!(GtpStatus::Idle, game.gtp_status);
+
+        game.game_over = false;
+        game.update(character('z'));
+        assert_eq!(GtpStatus::Loading, game.gtp_status);
+
+        // busy now, so this one is dropped instead of queued
+        game.status_message = Some("thinking".to_string());
+        game.update(character('z'));
+        assert_eq!(Some("thinking".to_string()), game.status_message);
+    }
+
+    #[test]
+    fn passing_clears_the_input_and_two_passes_end_the_game() {
+        let mut game = loaded_game(9, StoneColor::Black);
+
+        game.update(character('D'));
+        game.update(character('4'));
+        game.update(character('p'));
+
+        assert_eq!(GtpStatus::Loading, game.gtp_status);
+        assert_eq!("", game.next_move_input);
+        assert_eq!(
+            None,
+            game.board.as_mut().unwrap().get_valid_highlight_coords()
+        );
+
+        for color in [StoneColor::Black, StoneColor::White] {
+            game.update(GameMessage::AfterStonePlayed(
+                record(color, MoveKind::Pass),
+                vec![],
+                vec![],
+            ));
+        }
+        assert_eq!(
+            GameProgress::Score,
+            game.move_history
+                .progress(TwoPassesRule::Score, ResignRule::End)
+        );
+    }
+
+    #[test]
+    fn two_players_take_turns_without_the_engine() {
+        let mut game = test_game();
+        game.two_player = true;
+        game.board = Some(Board::new(9, Theme::default()));
+        game.gtp_status = GtpStatus::Idle;
+        let d4 = Stone {
+            color: StoneColor::Black,
+            row: 4,
+            col: 4,
+        };
+
+        game.update(GameMessage::AfterStonePlayed(
+            record(StoneColor::Black, MoveKind::Play(Coords::from(4, 4))),
+            vec![d4],
+            vec![],
+        ));
+        assert_eq!(StoneColor::White, game.player_color);
+        assert_eq!(GtpStatus::Idle, game.gtp_status);
+
+        // rejected moves keep the turn
+        game.play(Coords::from(4, 4));
+        assert_eq!(
+            Some("Illegal move at D4: point is occupied".to_string()),
+            game.status_message
+        );
+        assert_eq!(StoneColor::White, game.player_color);
+        assert_eq!(GtpStatus::Idle, game.gtp_status);
+    }
+
+    #[test]
+    fn the_stone_just_taken_in_a_ko_cant_be_retaken_right_away() {
+        let mut game = test_game();
+        game.two_player = true;
+        game.gtp_status = GtpStatus::Idle;
+        let stones = |color, points: &[(u8, u8)]| -> Vec<Stone> {
+            points
+                .iter()
+                .map(|&(row, col)| Stone { color, row, col })
+                .collect()
+        };
+        let mut board = Board::new(9, Theme::default());
+        board.set_stones(
+            stones(StoneColor::Black, &[(1, 3), (3, 3), (2, 4), (2, 2)]),
+            stones(StoneColor::White, &[(1, 2), (2, 1), (3, 2)]),
+        );
+        game.board = Some(board);
+
+        game.update(GameMessage::AfterStonePlayed(
+            record(StoneColor::White, MoveKind::Play(Coords::from(2, 3))),
+            stones(StoneColor::Black, &[(1, 3), (3, 3), (2, 4)]),
+            stones(StoneColor::White, &[(1, 2), (2, 1), (3, 2), (2, 3)]),
+        ));
+        assert_eq!(StoneColor::Black, game.player_color);
+
+        game.play(Coords::from(2, 2));
+        assert_eq!(
+            Some("Illegal move at B2: ko".to_string()),
+            game.status_message
+        );
+        assert_eq!(GtpStatus::Idle, game.gtp_status);
+    }
+
+    #[test]
+    fn moves_refused_by_the_engine_are_reported_and_keep_the_turn() {
+        let mut game = test_game();
+        game.board = Some(Board::new(9, Theme::default()));
+        game.gtp_status = GtpStatus::Idle;
+        // the engine has a stone on D4 the board doesn't show
+        let (engine, _) = mock_engine(vec![err("illegal move")]);
+
+        game.play(Coords::from(4, 4));
+        assert_eq!(GtpStatus::Loading, game.gtp_status);
+
+        let result = iced_futures::futures::executor::block_on(GoGame::play_move(
+            Arc::new(Mutex::new(engine)),
+            Coords::from(4, 4),
+            StoneColor::Black,
+        ));
+        assert_eq!(Some("illegal move".to_string()), result.unwrap().err());
+
+        game.update(GameMessage::MoveRejected(
+            Coords::from(4, 4),
+            "illegal move".to_string(),
+        ));
+        assert_eq!(
+            Some("Illegal move at D4: illegal move".to_string()),
+            game.status_message
+        );
+        assert_eq!(GtpStatus::Idle, game.gtp_status);
+        assert_eq!(None, game.gtp_error);
+        assert_eq!(0, game.move_history.len());
+        assert_eq!(StoneColor::Black, game.move_history.to_move());
+    }
+
+    #[test]
+    fn board_margin_shrinks_to_keep_the_board_on_screen() {
+        let mut game = test_game();
+        let board = Board::new(19, Theme::default());
+        game.board_margin = (2, 4);
+
+        game.update(GameMessage::EventOccurred(Event::Window(
+            window::Event::Resized {
+                width: 80,
+                height: 24,
+            },
+        )));
+        assert_eq!((2, 4), game.board_margin(&board));
+
+        // a 19x19 board takes 45x21, leaving 3 lines and 35 columns to spare
+        game.board_margin = (5, 40);
+        assert_eq!((3, 35), game.board_margin(&board));
+    }
+
+    #[test]
+    fn quitting_takes_a_second_press_within_the_window() {
+        let mut game = test_game();
+        let ctrl_c = GameMessage::EventOccurred(Event::Keyboard(keyboard::Event::KeyReleased {
+            key_code: keyboard::KeyCode::C,
+            modifiers: keyboard::Modifiers {
+                control: true,
+                ..keyboard::Modifiers::default()
+            },
+        }));
+
+        game.update(character('q'));
+        assert_eq!(None, game.should_exit);
+        assert_eq!(
+            Some("Press q again to quit".to_string()),
+            game.status_message
+        );
+
+        // any other key cancels
+        game.update(character('y'));
+        assert_eq!(None, game.status_message);
+        game.update(character('q'));
+        game.update(key(keyboard::KeyCode::Escape));
+        game.update(character('q'));
+        assert_eq!(None, game.should_exit);
+
+        // too late for the first press to count
+        game.quit_requested_at = Some(Instant::now() - QUIT_WINDOW * 2);
+        game.update(character('q'));
+        assert_eq!(None, game.should_exit);
+
+        game.update(character('q'));
+        assert_eq!(Some(0), game.should_exit);
+
+        let mut game = test_game();
+        game.update(ctrl_c.clone());
+        assert_eq!(
+            Some("Press Ctrl+C again to quit".to_string()),
+            game.status_message
+        );
+        game.update(ctrl_c);
+        assert_eq!(Some(1), game.should_exit);
+    }
+
+    #[test]
+    fn small_terminals_show_a_message_until_the_board_fits_again() {
+        let mut game = test_game();
+        let board = Board::new(19, Theme::default());
+        let resize = |width, height| {
+            GameMessage::EventOccurred(Event::Window(window::Event::Resized { width, height }))
+        };
+        assert_eq!(None, game.too_small_message(&board));
+
+        game.update(resize(40, 24));
+        assert_eq!(
+            Some("Terminal too small for the board, 45x22 needed".to_string()),
+            game.too_small_message(&board)
+        );
+
+        game.update(resize(80, 24));
+        assert_eq!(None, game.too_small_message(&board));
+    }
+
+    #[test]
+    fn columns_past_t_can_be_typed_on_large_boards() {
+        let mut game = test_game();
+        game.board = Some(Board::new(19, Theme::default()));
+        game.gtp_status = GtpStatus::Idle;
+
+        game.update(character('U'));
+        assert_eq!("", game.next_move_input);
+
+        game.board = Some(Board::new(25, Theme::default()));
+        game.update(character('Z'));
+        game.update(character('2'));
+        game.update(character('5'));
+        assert_eq!("Z25", game.next_move_input);
+        assert_eq!(
+            Some(Coords::from(25, 25)),
+            game.board.as_mut().unwrap().get_valid_highlight_coords()
+        );
+    }
+
+    #[test]
+    fn rows_off_the_board_are_not_typed() {
+        let mut game = test_game();
+        game.board = Some(Board::new(19, Theme::default()));
+        game.gtp_status = GtpStatus::Idle;
+
+        game.update(character('A'));
+        game.update(character('5'));
+        game.update(character('0'));
+        assert_eq!("A5", game.next_move_input);
+        assert_eq!(
+            Some("Invalid coordinate A50".to_string()),
+            game.status_message
+        );
+
+        game.update(character('1'));
+        game.update(character('9'));
+        assert_eq!("A5", game.next_move_input);
+        assert_eq!(
+            Some(Coords::from(5, 1)),
+            game.board.as_mut().unwrap().get_valid_highlight_coords()
+        );
+    }
+
+    #[test]
+    fn arrow_keys_move_a_cursor_from_tengen_to_the_edge() {
+        let mut game = loaded_game(9, StoneColor::Black);
+
+        game.update(key(keyboard::KeyCode::Up));
+        assert_eq!(
+            Some(Coords::from(5, 5)),
+            game.board.as_mut().unwrap().get_valid_highlight_coords()
+        );
+
+        for _ in 0..6 {
+            game.update(key(keyboard::KeyCode::Left));
+        }
+        game.update(key(keyboard::KeyCode::Up));
+        assert_eq!(
+            Some(Coords::from(6, 1)),
+            game.board.as_mut().unwrap().get_valid_highlight_coords()
+        );
+
+        // typing takes over, and the cursor picks up where it was
+        game.update(character('D'));
+        assert_eq!(
+            None,
+            game.board.as_mut().unwrap().get_valid_highlight_coords()
+        );
+        game.update(key(keyboard::KeyCode::Right));
+        assert_eq!("", game.next_move_input);
+        assert_eq!(
+            Some(Coords::from(6, 2)),
+            game.board.as_mut().unwrap().get_valid_highlight_coords()
+        );
+
+        game.update(key(keyboard::KeyCode::Enter));
+        assert_eq!(GtpStatus::Loading, game.gtp_status);
+    }
+
+    #[test]
+    fn w_saves_the_game_as_sgf() {
+        let mut game = loaded_game(9, StoneColor::Black);
+        let path = std::env::temp_dir().join(format!("gogame-save-{}.sgf", std::process::id()));
+        game.sgf_save_path = path.to_str().unwrap().to_string();
+        game.move_history.push(record(
+            StoneColor::Black,
+            MoveKind::Play(Coords::from(5, 5)),
+        ));
+
+        game.update(character('w'));
+
+        let saved = std::fs::read_to_string(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert!(saved.contains("SZ[9]KM[6.5];B[ee])"), "{}", saved);
+        assert_eq!(
+            Some(format!("Game saved to {}", game.sgf_save_path)),
+            game.status_message
+        );
+    }
+
+    #[test]
+    fn keys_of_unsupported_commands_only_explain() {
+        let mut game = test_game();
+        game.update(GameMessage::BoardLoaded(
+            Board::new(9, Theme::default()),
+            MoveHistory::new(StoneColor::Black),
+            EngineInfo {
+                komi: None,
+                name: None,
+                commands: Some(vec!["play".to_string()]),
+            },
+        ));
+        game.move_history.push(record(
+            StoneColor::Black,
+            MoveKind::Play(Coords::from(5, 5)),
+        ));
+        game.player_color = StoneColor::White;
+
+        for c in ['u', 't', 'd'] {
+            game.update(character(c));
+            assert_eq!(GtpStatus::Idle, game.gtp_status);
+        }
+        assert_eq!(
+            Some("Not supported by the engine (no final_status_list)".to_string()),
+            game.status_message
+        );
+    }
+
+    #[test]
+    fn help_closes_on_the_next_key_without_running_it() {
+        let mut game = loaded_game(9, StoneColor::Black);
+
+        game.update(character('?'));
+        assert!(game.show_help);
+
+        game.update(character('c'));
+        assert!(!game.show_help);
+        assert_eq!("classic", game.theme_preset);
+
+        game.update(character('?'));
+        game.update(key(keyboard::KeyCode::Escape));
+        assert!(!game.show_help);
+    }
+
+    #[test]
+    fn sgf_input_mode_reads_letter_pairs() {
+        let mut game = loaded_game(19, StoneColor::Black);
+        game.input_mode = InputMode::Sgf;
+
+        // letters are commands until the coordinates are started
+        game.update(character('c'));
+        assert_eq!("", game.next_move_input);
+        assert_eq!("dark", game.theme_preset);
+
+        game.update(character(':'));
+        game.update(character('p'));
+        game.update(character('d'));
+        assert_eq!(":pd", game.next_move_input);
+        assert_eq!(
+            Some(Coords::from(16, 16)),
+            game.board.as_mut().unwrap().get_valid_highlight_coords()
+        );
+
+        // a third letter isn't taken as a coordinate, and can still be a command
+        game.update(character('c'));
+        assert_eq!(":pd", game.next_move_input);
+        assert_eq!("high_contrast", game.theme_preset);
+    }
+
+    #[test]
+    fn engine_waits_for_g_when_auto_respond_is_off() {
+        let mut game = test_game();
+        game.auto_respond = false;
+
+        game.update(character('g'));
+        assert_eq!(GtpStatus::Loading, game.gtp_status);
+
+        // white moves first, so it's the engine's turn right after loading
+        game.update(GameMessage::BoardLoaded(
+            Board::new(9, Theme::default()),
+            MoveHistory::new(StoneColor::White),
+            EngineInfo::default(),
+        ));
+        assert_eq!(GtpStatus::Idle, game.gtp_status);
+
+        game.update(character('g'));
+        assert_eq!(GtpStatus::Loading, game.gtp_status);
+    }
+
+    #[test]
+    fn auto_pass_triggers_only_without_legal_moves() {
+        let load_with_empty_points = |empty: &[(u8, u8)]| {
+            let mut game = test_game();
+            game.auto_pass = true;
+
+            // black everywhere else, filling the last liberty would be suicide
+            let black_stones: Vec<Stone> = (1..=3)
+                .flat_map(|row| (1..=3).map(move |col| (row, col)))
+                .filter(|point| !empty.contains(point))
+                .map(|(row, col)| Stone {
+                    color: StoneColor::Black,
+                    row,
+                    col,
+                })
+                .collect();
+            let mut board = Board::new(3, Theme::default());
+            board.set_stones(black_stones, vec![]);
+
+            game.update(GameMessage::BoardLoaded(
+                board,
+                MoveHistory::new(StoneColor::Black),
+                EngineInfo::default(),
+            ));
+            game
+        };
+
+        let game = load_with_empty_points(&[(2, 2), (1, 1)]);
+        assert_eq!(GtpStatus::Idle, game.gtp_status);
+        assert_eq!(None, game.status_message);
+
+        let game = load_with_empty_points(&[(2, 2)]);
+        assert_eq!(GtpStatus::Loading, game.gtp_status);
+        assert_eq!(
+            Some("No legal moves left, passing".to_string()),
+            game.status_message
+        );
+    }
+
+    #[test]
+    fn loaded_game_continues_with_the_player_on_the_chosen_color() {
+        let mut sgf_game = SgfGame::new(9);
+        sgf_game.moves = (0..30)
+            .map(|i| SgfMove {
+                color: if i % 2 == 0 {
+                    StoneColor::Black
+                } else {
+                    StoneColor::White
+                },
+                coords: Some(Coords::from(i / 9 + 1, i % 9 + 1)),
+            })
+            .collect();
+        let load = |continue_as: &str| {
+            let (engine, _) = mock_engine(vec![]);
+            let context = AppContext {
+                app_config: serde_yaml::from_str("empty: true").unwrap(),
+                load: Some(sgf_game.clone()),
+                sgf_path: None,
+                continue_as: Some(continue_as.to_string()),
+                #[cfg(feature = "serve")]
+                status_server: Mutex::new(None),
+            };
+            let (mut game, game_setup) =
+                GoGame::with_context(&context, Arc::new(Mutex::new(engine)));
+
+            // as the engine would load it
+            game.update(GameMessage::BoardLoaded(
+                Board::new(9, Theme::default()),
+                MoveHistory::from_sgf(&game_setup.sgf_game.unwrap(), game_setup.first_player),
+                EngineInfo::default(),
+            ));
+            game
+        };
+
+        let mut game = load("to-move");
+        assert_eq!(StoneColor::Black, game.player_color);
+        assert_eq!(30, game.move_history.len());
+        assert_eq!(GtpStatus::Idle, game.gtp_status);
+
+        game.update(GameMessage::BoardClicked(Some(Coords::from(9, 9))));
+        assert_eq!(GtpStatus::Loading, game.gtp_status);
+
+        // the engine takes black and plays right away
+        let game = load("white");
+        assert_eq!(StoneColor::White, game.player_color);
+        assert_eq!(GtpStatus::Loading, game.gtp_status);
+    }
+
+    #[test]
+    fn engine_komi_other_than_the_configured_one_is_warned_about() {
+        let load = |configured, engine| {
+            let mut game = test_game();
+            game.komi = configured;
+            game.update(GameMessage::BoardLoaded(
+                Board::new(9, Theme::default()),
+                MoveHistory::new(StoneColor::Black),
+                EngineInfo {
+                    komi: engine,
+                    name: None,
+                    commands: None,
+                },
+            ));
+            game.status_message
+        };
+
+        assert_eq!(
+            Some("Warning: engine komi is 7.5, but 6.5 was configured".to_string()),
+            load(Some(6.5), Some(7.5))
+        );
+        assert_eq!(None, load(Some(6.5), Some(6.5)));
+        assert_eq!(None, load(Some(6.5), None));
+        assert_eq!(None, load(None, Some(7.5)));
+    }
+
+    #[test]
+    fn off_book_openings_are_rejected_with_a_hint() {
+        let mut game = loaded_game(9, StoneColor::Black);
+        game.opening_points = vec!["D4".to_string(), "F6".to_string(), "Z9".to_string()];
+
+        game.update(GameMessage::BoardClicked(Some(Coords::from(5, 5))));
+        assert_eq!(GtpStatus::Idle, game.gtp_status);
+        assert_eq!(
+            Some("Off-book opening, try D4, F6".to_string()),
+            game.status_message
+        );
+
+        game.update(GameMessage::BoardClicked(Some(Coords::from(6, 6))));
+        assert_eq!(GtpStatus::Loading, game.gtp_status);
+
+        // the engine's first move is held to the points left too
+        game.update(GameMessage::AfterStonePlayed(
+            record(StoneColor::Black, MoveKind::Play(Coords::from(6, 6))),
+            vec![Stone {
+                color: StoneColor::Black,
+                row: 6,
+                col: 6,
+            }],
+            vec![],
+        ));
+        assert_eq!(
+            Some(vec![Coords::from(4, 4)]),
+            game.opening_points_left(StoneColor::White)
+        );
+        assert_eq!(None, game.opening_points_left(StoneColor::Black));
+    }
+
+    #[test]
+    fn replayed_load_ends_on_the_loaded_position_even_if_interrupted() {
+        let mut sgf_game = SgfGame::new(9);
+        sgf_game.moves = vec![
+            SgfMove {
+                color: StoneColor::Black,
+                coords: Some(Coords::from(3, 3)),
+            },
+            SgfMove {
+                color: StoneColor::White,
+                coords: Some(Coords::from(7, 7)),
+            },
+            SgfMove {
+                color: StoneColor::Black,
+                coords: Some(Coords::from(5, 5)),
+            },
+        ];
+        let stone = |color, row, col| Stone { color, row, col };
+        let black_stones = vec![
+            stone(StoneColor::Black, 3, 3),
+            stone(StoneColor::Black, 5, 5),
+        ];
+        let white_stones = vec![stone(StoneColor::White, 7, 7)];
+        let load = || {
+            let mut game = test_game();
+            game.replay_stone_delay = Some(Duration::from_millis(100));
+            let mut board = Board::new(9, Theme::default());
+            board.set_stones(black_stones.clone(), white_stones.clone());
+
+            game.update(GameMessage::BoardLoaded(
+                board,
+                MoveHistory::from_sgf(&sgf_game, StoneColor::Black),
+                EngineInfo::default(),
+            ));
+            game
+        };
+        let shown = |game: &GoGame| {
+            let board = game.board.as_ref().unwrap();
+            (board.black_stones().len(), board.white_stones().len())
+        };
+
+        let mut game = load();
+        assert_eq!((0, 0), shown(&game));
+        game.update(GameMessage::ReplayStep);
+        assert_eq!((1, 0), shown(&game));
+        for _ in 0..2 {
+            game.update(GameMessage::ReplayStep);
+        }
+        assert!(game.replay.is_none());
+        assert_eq!((2, 1), shown(&game));
+
+        let mut game = load();
+        game.update(GameMessage::ReplayStep);
+        game.update(character('D'));
+        assert!(game.replay.is_none());
+        assert_eq!(black_stones, game.board.as_ref().unwrap().black_stones());
+        assert_eq!(white_stones, game.board.as_ref().unwrap().white_stones());
+    }
+
+    #[test]
+    fn custom_validator_can_forbid_points() {
+        struct NoCenter;
+
+        impl MoveValidator for NoCenter {
+            fn validate_play(
+                &self,
+                _goban: &Goban,
+                _color: StoneColor,
+                coords: Coords,
+            ) -> Result<(), AppError> {
+                if coords == Coords::from(5, 5) {
+                    Err(AppError {
+                        message: "The center is off limits".to_string(),
+                    })
+                } else {
+                    Ok(())
+                }
+            }
+        }
+
+        let mut game = loaded_game(9, StoneColor::Black).with_move_validator(Box::new(NoCenter));
+
+        game.update(GameMessage::BoardClicked(Some(Coords::from(5, 5))));
+        assert_eq!(GtpStatus::Idle, game.gtp_status);
+        assert_eq!(
+            Some("The center is off limits".to_string()),
+            game.status_message
+        );
+
+        game.update(GameMessage::BoardClicked(Some(Coords::from(3, 3))));
+        assert_eq!(GtpStatus::Loading, game.gtp_status);
+    }
+
+    #[test]
+    fn group_status_needs_a_stone_under_the_cursor() {
+        let mut game = test_game();
+        let mut board = Board::new(9, Theme::default());
+        board.set_stones(
+            vec![Stone {
+                color: StoneColor::Black,
+                row: 3,
+                col: 3,
+            }],
+            vec![],
+        );
+        game.board = Some(board);
+        game.gtp_status = GtpStatus::Idle;
+
+        game.hover_coords = Some(Coords::from(4, 4));
+        game.update(character('s'));
+        assert_eq!(GtpStatus::Idle, game.gtp_status);
+
+        game.hover_coords = Some(Coords::from(3, 3));
+        game.update(character('s'));
+        assert_eq!(GtpStatus::Loading, game.gtp_status);
+
+        game.update(GameMessage::GroupStatusReported(
+            Coords::from(3, 3),
+            GroupStatus::Dead,
+        ));
+        assert_eq!(Some("Group at C3 is dead".to_string()), game.status_message);
+    }
+
+    #[test]
+    fn demonstration_plays_the_players_move_then_the_reply() {
+        let mut game = loaded_game(9, StoneColor::Black);
+
+        game.update(character('a'));
+        assert!(game.demonstrating);
+        assert_eq!(GtpStatus::Loading, game.gtp_status);
+
+        let stone = Stone {
+            color: StoneColor::Black,
+            row: 4,
+            col: 4,
+        };
+        game.update(GameMessage::AfterGenMove(
+            MoveRecord {
+                think_time: Some(Duration::from_millis(10)),
+                ..record(StoneColor::Black, MoveKind::Play(Coords::from(4, 4)))
+            },
+            vec![stone],
+            vec![],
+        ));
+
+        assert_eq!(&[stone], game.board.as_ref().unwrap().black_stones());
+        assert_eq!(StoneColor::White, game.move_history.to_move());
+        // the opponent's reply was requested
+        assert_eq!(GtpStatus::Loading, game.gtp_status);
+    }
+
+    #[test]
+    fn dead_engine_is_restarted_with_the_position() {
+        let app_config: AppConfig = serde_yaml::from_str("empty: true").unwrap();
+        // protocol_version, boardsize, clear_board, play, komi, then the two stone lists
+        let engine = dead_engine(vec![
+            ok("2"),
+            ok(""),
+            ok(""),
+            ok(""),
+            ok(""),
+            ok("E5"),
+            ok(""),
+        ]);
+        let mut game = GoGame::with_config(
+            Arc::new(Mutex::new(engine)),
+            &app_config.general,
+            &app_config.theme,
+        )
+        .unwrap();
+        let game_setup = game_setup(StoneColor::White);
+        game.new_game_setup = Some(game_setup.clone());
+        game.player_color = StoneColor::White;
+        let mut history = MoveHistory::new(StoneColor::Black);
+        history.push(record(
+            StoneColor::Black,
+            MoveKind::Play(Coords::from(5, 5)),
+        ));
+        game.update(GameMessage::BoardLoaded(
+            Board::new(9, Theme::default()),
+            history,
+            EngineInfo::default(),
+        ));
+
+        game.update(GameMessage::GtpError(
+            "Error calling command 'play W D4'".to_string(),
+        ));
+        assert_eq!(GtpStatus::Loading, game.gtp_status);
+        assert_eq!(None, game.gtp_error);
+
+        let position = game.move_history.to_sgf_game(9, game.komi);
+        let (black_stones, white_stones) = iced_futures::futures::executor::block_on(
+            GoGame::restart_engine(game.gtp_engine.clone(), game_setup, position),
+        )
+        .unwrap();
+        assert_eq!(1, black_stones.len());
+
+        game.update(GameMessage::EngineRestarted(black_stones, white_stones));
+        assert_eq!(GtpStatus::Idle, game.gtp_status);
+        assert_eq!(Some("Engine restarted".to_string()), game.status_message);
+
+        // the new engine is alive, so its failures are shown
+        game.update(GameMessage::GtpError(
+            "Error calling command 'play W D4'".to_string(),
+        ));
+        assert_eq!(GtpStatus::Error, game.gtp_status);
+    }
+
+    #[test]
+    fn engine_opens_new_games_when_the_player_is_white() {
+        let mut game = test_game();
+        game.new_game_setup = Some(game_setup(StoneColor::White));
+
+        game.start_new_game(None);
+        game.update(GameMessage::BoardLoaded(
+            Board::new(9, Theme::default()),
+            MoveHistory::new(StoneColor::Black),
+            EngineInfo::default(),
+        ));
+
+        assert_eq!(StoneColor::White, game.player_color);
+        assert_eq!(GtpStatus::Loading, game.gtp_status);
+    }
+
+    #[test]
+    fn new_game_asks_before_abandoning_a_game_and_starts_empty() {
+        let mut game = test_game();
+        game.new_game_setup = Some(game_setup(StoneColor::Black));
+        let mut board = Board::new(9, Theme::default());
+        board.set_stones(
+            vec![Stone {
+                color: StoneColor::Black,
+                row: 5,
+                col: 5,
+            }],
+            vec![],
+        );
+        let mut history = MoveHistory::new(StoneColor::Black);
+        history.push(record(
+            StoneColor::Black,
+            MoveKind::Play(Coords::from(5, 5)),
+        ));
+        // white's turn, and white is the player
+        game.player_color = StoneColor::White;
+        game.update(GameMessage::BoardLoaded(
+            board,
+            history,
+            EngineInfo::default(),
+        ));
+
+        game.update(character('n'));
+        assert_eq!(Some(Confirmation::NewGame(None)), game.confirmation);
+        game.update(character('n'));
+        assert_eq!(None, game.confirmation);
+        assert_eq!(1, game.move_history.len());
+
+        game.update(character('n'));
+        game.update(character('y'));
+        assert_eq!(GtpStatus::Loading, game.gtp_status);
+        assert_eq!(StoneColor::Black, game.player_color);
+
+        game.update(GameMessage::BoardLoaded(
+            Board::new(9, Theme::default()),
+            MoveHistory::new(StoneColor::Black),
+            EngineInfo::default(),
+        ));
+        let board = game.board.as_ref().unwrap();
+        assert!(board.black_stones().is_empty() && board.white_stones().is_empty());
+        assert_eq!(0, game.move_history.len());
+        assert!(!game.game_over);
+    }
+
+    #[test]
+    fn scoring_key_explains_engines_without_final_score() {
+        let mut game = test_game();
+        game.update(GameMessage::BoardLoaded(
+            Board::new(9, Theme::default()),
+            MoveHistory::new(StoneColor::Black),
+            EngineInfo {
+                komi: None,
+                name: None,
+                commands: Some(vec!["play".to_string(), "genmove".to_string()]),
+            },
+        ));
+
+        game.update(character('='));
+        assert_eq!(GtpStatus::Idle, game.gtp_status);
+        assert_eq!(
+            Some("The engine can't score games (no final_score command)".to_string()),
+            game.status_message
+        );
+
+        game.engine_info.commands = None;
+        game.update(character('='));
+        assert_eq!(GtpStatus::Loading, game.gtp_status);
+    }
+
+    #[test]
+    fn engine_passes_and_resignations_are_announced() {
+        let mut game = loaded_game(9, StoneColor::Black);
+        let engine_move = |kind| MoveRecord {
+            think_time: Some(Duration::from_millis(10)),
+            ..record(StoneColor::White, kind)
+        };
+
+        game.move_history.push(record(
+            StoneColor::Black,
+            MoveKind::Play(Coords::from(4, 4)),
+        ));
+        game.update(GameMessage::AfterGenMove(
+            engine_move(MoveKind::Pass),
+            vec![],
+            vec![],
+        ));
+        assert_eq!(Some("white passed".to_string()), game.status_message);
+        assert!(!game.game_over);
+
+        game.move_history.push(record(
+            StoneColor::Black,
+            MoveKind::Play(Coords::from(5, 5)),
+        ));
+        game.update(GameMessage::AfterGenMove(
+            engine_move(MoveKind::Resign),
+            vec![],
+            vec![],
+        ));
+        assert_eq!(
+            Some("white resigned, you win".to_string()),
+            game.status_message
+        );
+        assert_eq!(Some("B+R".to_string()), game.game_result);
+
+        // no more moves once the game is over
+        game.play(Coords::from(6, 6));
+        assert_eq!(GtpStatus::Idle, game.gtp_status);
+    }
+
+    #[test]
+    fn status_line_shows_move_captures_and_komi() {
+        assert_eq!(
+            "Move 0 | B:0 W:0 captures | Komi 6.5 | ",
+            format_status_line(0, (0, 0), Some(6.5), None)
+        );
+        assert_eq!(
+            "Move 42 | B:3 W:5 captures | ",
+            format_status_line(42, (3, 5), None, Some(80))
+        );
+        assert_eq!(
+            "Move 42 | ",
+            format_status_line(42, (3, 5), Some(6.5), Some(20))
+        );
+        assert_eq!("", format_status_line(42, (3, 5), Some(6.5), Some(5)));
+    }
+
+    #[test]
+    fn captures_are_counted_from_the_stones_that_left_the_board() {
+        let mut game = test_game();
+        let mut board = Board::new(9, Theme::default());
+        board.set_stones(
+            vec![],
+            vec![Stone {
+                color: StoneColor::White,
+                row: 1,
+                col: 1,
+            }],
+        );
+        game.update(GameMessage::BoardLoaded(
+            board,
+            MoveHistory::new(StoneColor::Black),
+            EngineInfo::default(),
+        ));
+
+        game.update(GameMessage::AfterStonePlayed(
+            record(StoneColor::Black, MoveKind::Play(Coords::from(1, 2))),
+            vec![Stone {
+                color: StoneColor::Black,
+                row: 1,
+                col: 2,
+            }],
+            vec![],
+        ));
+        assert_eq!(1, game.move_history.captures(StoneColor::Black));
+
+        // a suicide takes the mover's own stone, which counts for the opponent
+        game.update(GameMessage::AfterStonePlayed(
+            record(StoneColor::White, MoveKind::Play(Coords::from(1, 1))),
+            vec![Stone {
+                color: StoneColor::Black,
+                row: 1,
+                col: 2,
+            }],
+            vec![],
+        ));
+        assert_eq!(2, game.move_history.captures(StoneColor::Black));
+        assert_eq!(0, game.move_history.captures(StoneColor::White));
+    }
+
+    #[test]
+    fn menu_entries_the_engine_lacks_do_nothing() {
+        let mut game = test_game();
+        game.update(GameMessage::BoardLoaded(
+            Board::new(9, Theme::default()),
+            MoveHistory::new(StoneColor::Black),
+            EngineInfo {
+                komi: None,
+                name: None,
+                commands: Some(vec!["final_score".to_string()]),
+            },
+        ));
+        game.update(character('m'));
+        assert!(!game.menu.as_ref().unwrap().is_enabled(MenuAction::Undo));
+
+        // undo is the first entry
+        game.update(key(keyboard::KeyCode::Enter));
+        assert!(game.menu.is_some());
+        assert_eq!(GtpStatus::Idle, game.gtp_status);
+
+        game.update(key(keyboard::KeyCode::Down));
+        game.update(key(keyboard::KeyCode::Enter));
+        assert!(game.menu.is_none());
+        assert_eq!(GtpStatus::Loading, game.gtp_status);
+    }
+
+    #[test]
+    fn moves_change_the_board_in_place_unless_the_engine_disagrees() {
+        let mut game = loaded_game(9, StoneColor::Black);
+        let stone = |color, row, col| Stone { color, row, col };
+        let play = |color, row, col| record(color, MoveKind::Play(Coords::from(row, col)));
+        game.board.as_mut().unwrap().set_stones(
+            vec![stone(StoneColor::Black, 1, 2)],
+            vec![stone(StoneColor::White, 1, 1)],
+        );
+
+        game.update(GameMessage::AfterStonePlayed(
+            play(StoneColor::Black, 2, 1),
+            vec![
+                stone(StoneColor::Black, 1, 2),
+                stone(StoneColor::Black, 2, 1),
+            ],
+            vec![],
+        ));
+        let board = game.board.as_ref().unwrap();
+        assert_eq!(
+            &[
+                stone(StoneColor::Black, 1, 2),
+                stone(StoneColor::Black, 2, 1)
+            ],
+            board.black_stones()
+        );
+        assert!(board.white_stones().is_empty());
+
+        // a stone the move can't explain means the board was out of date
+        game.update(GameMessage::AfterGenMove(
+            play(StoneColor::White, 5, 5),
+            vec![stone(StoneColor::Black, 1, 2)],
+            vec![
+                stone(StoneColor::White, 5, 5),
+                stone(StoneColor::White, 7, 7),
+            ],
+        ));
+        let board = game.board.as_ref().unwrap();
+        assert_eq!(&[stone(StoneColor::Black, 1, 2)], board.black_stones());
+        assert_eq!(
+            &[
+                stone(StoneColor::White, 5, 5),
+                stone(StoneColor::White, 7, 7)
+            ],
+            board.white_stones()
+        );
+    }
+
+    #[test]
+    fn stones_left_in_atari_are_marked_when_the_warnings_are_on() {
+        let mut game = loaded_game(9, StoneColor::Black);
+        game.atari_warnings = true;
+        let black = Stone {
+            color: StoneColor::Black,
+            row: 1,
+            col: 2,
+        };
+        let white = Stone {
+            color: StoneColor::White,
+            row: 1,
+            col: 1,
+        };
+        game.board.as_mut().unwrap().set_stones(vec![], vec![white]);
+
+        game.update(GameMessage::AfterStonePlayed(
+            record(StoneColor::Black, MoveKind::Play(Coords::from(1, 2))),
+            vec![black],
+            vec![white],
+        ));
+
+        assert_eq!(
+            &[(Coords::from(1, 1), MarkerKind::Atari)],
+            game.board.as_ref().unwrap().markers()
+        );
+    }
+
+    #[test]
+    fn open_menu_keeps_the_letter_commands_from_running() {
+        let mut game = loaded_game(9, StoneColor::Black);
+        game.move_history.push(record(
+            StoneColor::Black,
+            MoveKind::Play(Coords::from(3, 3)),
+        ));
+
+        game.update(character('m'));
+        game.update(character('c'));
+        game.update(character('D'));
+        assert_eq!("classic", game.theme_preset);
+        assert_eq!("", game.next_move_input);
+
+        game.update(character('m'));
+        assert!(game.menu.is_none());
+
+        // the handicap game is the last entry, and the game in progress is given up first
+        game.update(character('m'));
+        game.update(key(keyboard::KeyCode::Up));
+        game.update(key(keyboard::KeyCode::Enter));
+        assert_eq!(
+            Some(Confirmation::NewGame(Some(MIN_HANDICAP))),
+            game.confirmation
+        );
+    }
+
+    #[test]
+    fn scored_game_shows_a_summary_until_dismissed() {
+        let mut game = loaded_game(9, StoneColor::Black);
+        for (color, captured) in [(StoneColor::Black, 2), (StoneColor::White, 1)] {
+            game.move_history.push(MoveRecord {
+                captured,
+                ..record(color, MoveKind::Pass)
+            });
+        }
+
+        game.update(GameMessage::GameScored(
+            ScoreResult::Win {
+                winner: StoneColor::Black,
+                margin: 3.5,
+            },
+            Some(Territory {
+                black: vec![Coords::from(1, 1), Coords::from(1, 2)],
+                white: vec![Coords::from(9, 9)],
+                dame: vec![],
+            }),
+        ));
+
+        let summary = game.summary.clone().unwrap();
+        assert_eq!("B+3.5", summary.result);
+        assert_eq!((2, 1), summary.captures);
+        assert_eq!(Some((2, 1)), summary.territory);
+        assert_eq!(2, summary.moves);
+
+        game.update(GameMessage::EventOccurred(Event::Keyboard(
+            keyboard::Event::KeyReleased {
+                key_code: keyboard::KeyCode::Escape,
+                modifiers: keyboard::Modifiers::default(),
+            },
+        )));
+        assert!(game.summary.is_none());
+        assert!(game.game_over);
+    }
+
+    #[test]
+    fn running_out_of_time_loses_the_game() {
+        let mut game = test_game();
+        let start = Instant::now();
+        let mut clock = GameClock::new(Duration::from_secs(60));
+        clock.start(StoneColor::Black, start);
+        game.clock = Some(clock);
+
+        game.update(GameMessage::Tick(start + Duration::from_secs(59)));
+        assert!(!game.game_over);
+
+        game.update(GameMessage::Tick(start + Duration::from_secs(60)));
+        assert!(game.game_over);
+        assert_eq!(Some("W+T".to_string()), game.game_result);
+        assert_eq!(Some("black lost on time".to_string()), game.status_message);
+
+        // the clocks stay stopped for moves played after the end
+        game.update(GameMessage::AfterStonePlayed(
+            record(StoneColor::White, MoveKind::Pass),
+            vec![],
+            vec![],
+        ));
+        let later = Instant::now() + Duration::from_secs(10);
+        assert_eq!(
+            Duration::from_secs(60),
+            game.clock
+                .as_ref()
+                .unwrap()
+                .remaining(StoneColor::White, later)
+        );
+
+        // nor does the engine answer the player's move that was in flight when the flag fell
+        game.update(GameMessage::AfterStonePlayed(
+            record(StoneColor::Black, MoveKind::Play(Coords::from(4, 4))),
+            vec![],
+            vec![],
+        ));
+        assert_eq!(GtpStatus::Idle, game.gtp_status);
+        assert_eq!(2, game.move_history.len());
+        assert_eq!(Some("black lost on time".to_string()), game.status_message);
+    }
+
+    #[test]
+    fn engine_is_busy_while_generating_a_move() {
+        let mut game = test_game();
+        assert_eq!(GtpStatus::Loading, game.gtp_status);
+
+        // white moves first, so the engine plays right after loading
+        game.update(GameMessage::BoardLoaded(
+            Board::new(9, Theme::default()),
+            MoveHistory::new(StoneColor::White),
+            EngineInfo::default(),
+        ));
+        assert_eq!(GtpStatus::Loading, game.gtp_status);
+
+        game.update(GameMessage::AfterGenMove(
+            MoveRecord {
+                think_time: Some(Duration::from_millis(10)),
+                ..record(StoneColor::White, MoveKind::Play(Coords::from(3, 3)))
+            },
+            vec![],
+            vec![Stone {
+                color: StoneColor::White,
+                row: 3,
+                col: 3,
+            }],
+        ));
+        assert_eq!(GtpStatus::Idle, game.gtp_status);
+
+        game.update(GameMessage::GtpError("timeout".to_string()));
+        assert_eq!(GtpStatus::Error, game.gtp_status);
+        assert_eq!(
+            game.theme.status_error_color,
+            game.gtp_status.indicator(&game.theme).0
+        );
     }
 }
@@ -0,0 +1,72 @@
+use crate::core::theme::Theme;
+use crate::gogame::game_message::GameMessage;
+use iced_native::{Column, Container, Element, Text};
+use iced_tui::{Style, TuiRenderer};
+
+/// Keys and what they do, in the order the help lists them
+const KEY_BINDINGS: [(&str, &str); 22] = [
+    ("D4", "type a move (:pd in SGF input mode)"),
+    ("Arrows", "move the cursor"),
+    ("Enter", "play the move or the cursor"),
+    ("Backspace", "erase the last typed character"),
+    ("p", "pass"),
+    ("u", "undo"),
+    ("g", "ask the engine for its move"),
+    ("=", "score the game"),
+    ("n", "new game"),
+    ("x", "swap colors before the first move"),
+    ("w", "save the game as SGF"),
+    ("m", "menu"),
+    ("t", "territory estimate"),
+    ("d", "dead stones"),
+    ("s", "status of the group under the cursor"),
+    ("a", "let the engine play both sides"),
+    ("r", "re-sync the board with the engine"),
+    ("c", "next theme"),
+    ("y/n", "answer a question"),
+    ("?", "this help"),
+    ("q", "quit, press twice"),
+    ("Ctrl+C", "quit, press twice"),
+];
+
+/// Lines of the help, kept apart from the view so they can be checked in tests
+pub fn lines() -> Vec<String> {
+    let key_width = KEY_BINDINGS
+        .iter()
+        .map(|(key, _)| key.chars().count())
+        .max()
+        .unwrap_or(0);
+
+    KEY_BINDINGS
+        .iter()
+        .map(|(key, action)| format!("{:width$}  {}", key, action, width = key_width))
+        .collect()
+}
+
+/// Key list opened with `?`
+pub fn view(theme: &Theme) -> Element<'_, GameMessage, TuiRenderer> {
+    let column = lines()
+        .into_iter()
+        .fold(
+            Column::new().push(Text::new("Keys").font(Style::default().bold())),
+            |column, line| column.push(Text::new(line)),
+        )
+        .push(Text::new("Any key to close").font(Style::default().fg(theme.disabled_fg_color)));
+
+    Container::new(column)
+        .style(Style::default().bg(theme.board_bg_hl_color))
+        .into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn actions_line_up_after_the_longest_key() {
+        let lines = lines();
+
+        assert_eq!("p          pass", lines[4]);
+        assert_eq!("Ctrl+C     quit, press twice", lines[lines.len() - 1]);
+    }
+}
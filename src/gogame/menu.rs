@@ -0,0 +1,141 @@
+use crate::core::engine::EngineInfo;
+use crate::core::theme::Theme;
+use crate::gogame::game_message::GameMessage;
+use iced_native::{Column, Element, Text};
+use iced_tui::{Style, TuiRenderer};
+
+/// Something the menu can do
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum MenuAction {
+    Undo,
+    Score,
+    Territory,
+    /// Marks the move the engine would play for the player
+    Analysis,
+    /// Starts over with handicap stones
+    Handicap,
+}
+
+impl MenuAction {
+    pub const ALL: [MenuAction; 5] = [
+        MenuAction::Undo,
+        MenuAction::Score,
+        MenuAction::Territory,
+        MenuAction::Analysis,
+        MenuAction::Handicap,
+    ];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            MenuAction::Undo => "Undo",
+            MenuAction::Score => "Score game",
+            MenuAction::Territory => "Estimate territory",
+            MenuAction::Analysis => "Suggest a move",
+            MenuAction::Handicap => "New handicap game",
+        }
+    }
+
+    /// GTP command the action can't work without
+    pub fn required_command(&self) -> &'static str {
+        match self {
+            MenuAction::Undo => "undo",
+            MenuAction::Score => "final_score",
+            MenuAction::Territory => "final_status_list",
+            MenuAction::Analysis => "reg_genmove",
+            MenuAction::Handicap => "fixed_handicap",
+        }
+    }
+}
+
+/// Actions list opened with `m`, the ones the engine doesn't support are shown but can't be
+/// selected
+#[derive(Debug, Clone)]
+pub struct Menu {
+    /// Which actions the engine supports, in the order of [`MenuAction::ALL`]
+    enabled: Vec<bool>,
+    selected: usize,
+}
+
+impl Menu {
+    pub fn new(engine_info: &EngineInfo) -> Self {
+        Self {
+            enabled: MenuAction::ALL
+                .iter()
+                .map(|action| engine_info.supports(action.required_command()))
+                .collect(),
+            selected: 0,
+        }
+    }
+
+    pub fn is_enabled(&self, action: MenuAction) -> bool {
+        MenuAction::ALL
+            .iter()
+            .zip(&self.enabled)
+            .any(|(a, enabled)| *a == action && *enabled)
+    }
+
+    pub fn select_next(&mut self) {
+        self.selected = (self.selected + 1) % MenuAction::ALL.len();
+    }
+
+    pub fn select_previous(&mut self) {
+        self.selected = (self.selected + MenuAction::ALL.len() - 1) % MenuAction::ALL.len();
+    }
+
+    /// The highlighted action, `None` when the engine doesn't support it
+    pub fn selected_action(&self) -> Option<MenuAction> {
+        Some(MenuAction::ALL[self.selected]).filter(|action| self.is_enabled(*action))
+    }
+
+    pub fn view(&self, theme: &Theme) -> Element<'_, GameMessage, TuiRenderer> {
+        MenuAction::ALL
+            .iter()
+            .enumerate()
+            .fold(Column::new(), |column, (index, action)| {
+                let mut style = Style::default();
+
+                if !self.is_enabled(*action) {
+                    style = style.fg(theme.disabled_fg_color);
+                }
+                if index == self.selected {
+                    style = style.bg(theme.board_bg_hl_color);
+                }
+
+                column.push(Text::new(action.label()).font(style))
+            })
+            .into()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn actions_the_engine_lacks_are_disabled() {
+        let menu = Menu::new(&EngineInfo {
+            komi: None,
+            name: None,
+            commands: Some(vec![
+                "final_score".to_string(),
+                "final_status_list".to_string(),
+            ]),
+        });
+
+        assert!(!menu.is_enabled(MenuAction::Undo));
+        assert!(menu.is_enabled(MenuAction::Score));
+        assert!(!menu.is_enabled(MenuAction::Analysis));
+        assert_eq!(None, menu.selected_action());
+    }
+
+    #[test]
+    fn selection_wraps_around() {
+        let mut menu = Menu::new(&EngineInfo::default());
+
+        menu.select_previous();
+        assert_eq!(Some(MenuAction::Handicap), menu.selected_action());
+
+        menu.select_next();
+        assert_eq!(Some(MenuAction::Undo), menu.selected_action());
+    }
+}
@@ -1,5 +1,12 @@
+mod bench;
 mod board;
 mod game_message;
 mod gogame;
+mod help;
+mod menu;
+mod mouse_area;
+mod replay;
+mod summary;
 
+pub use bench::bench_render;
 pub use gogame::GoGame;
@@ -0,0 +1,126 @@
+use iced_native::layout::{Layout, Limits, Node};
+use iced_native::{
+    event, mouse, Clipboard, Element, Event, Hasher, Length, Point, Rectangle, Widget,
+};
+
+/// Wraps an element and reports the cursor position relative to the element's top left corner
+pub struct MouseArea<'a, Message, Renderer> {
+    content: Element<'a, Message, Renderer>,
+    on_move: Option<Box<dyn Fn(Point) -> Message + 'a>>,
+    on_press: Option<Box<dyn Fn(Point) -> Message + 'a>>,
+}
+
+impl<'a, Message, Renderer> MouseArea<'a, Message, Renderer> {
+    pub fn new<E>(content: E) -> Self
+    where
+        E: Into<Element<'a, Message, Renderer>>,
+    {
+        Self {
+            content: content.into(),
+            on_move: None,
+            on_press: None,
+        }
+    }
+
+    pub fn on_move<F>(mut self, f: F) -> Self
+    where
+        F: 'a + Fn(Point) -> Message,
+    {
+        self.on_move = Some(Box::new(f));
+        self
+    }
+
+    pub fn on_press<F>(mut self, f: F) -> Self
+    where
+        F: 'a + Fn(Point) -> Message,
+    {
+        self.on_press = Some(Box::new(f));
+        self
+    }
+}
+
+fn relative_position(bounds: Rectangle, cursor_position: Point) -> Point {
+    Point::new(cursor_position.x - bounds.x, cursor_position.y - bounds.y)
+}
+
+impl<'a, Message, Renderer> Widget<Message, Renderer> for MouseArea<'a, Message, Renderer>
+where
+    Renderer: iced_native::Renderer,
+{
+    fn width(&self) -> Length {
+        self.content.width()
+    }
+
+    fn height(&self) -> Length {
+        self.content.height()
+    }
+
+    fn layout(&self, renderer: &Renderer, limits: &Limits) -> Node {
+        self.content.layout(renderer, limits)
+    }
+
+    fn draw(
+        &self,
+        renderer: &mut Renderer,
+        defaults: &Renderer::Defaults,
+        layout: Layout<'_>,
+        cursor_position: Point,
+        viewport: &Rectangle,
+    ) -> Renderer::Output {
+        self.content
+            .draw(renderer, defaults, layout, cursor_position, viewport)
+    }
+
+    fn hash_layout(&self, state: &mut Hasher) {
+        self.content.hash_layout(state);
+    }
+
+    fn on_event(
+        &mut self,
+        event: Event,
+        layout: Layout<'_>,
+        cursor_position: Point,
+        renderer: &Renderer,
+        clipboard: &mut dyn Clipboard,
+        messages: &mut Vec<Message>,
+    ) -> event::Status {
+        let bounds = layout.bounds();
+
+        match &event {
+            Event::Mouse(mouse::Event::CursorMoved { .. }) => {
+                if let Some(on_move) = &self.on_move {
+                    messages.push(on_move(relative_position(bounds, cursor_position)));
+                }
+            }
+            Event::Mouse(mouse::Event::ButtonPressed(mouse::Button::Left))
+                if bounds.contains(cursor_position) =>
+            {
+                if let Some(on_press) = &self.on_press {
+                    messages.push(on_press(relative_position(bounds, cursor_position)));
+                    return event::Status::Captured;
+                }
+            }
+            _ => {}
+        }
+
+        self.content.on_event(
+            event,
+            layout,
+            cursor_position,
+            renderer,
+            clipboard,
+            messages,
+        )
+    }
+}
+
+impl<'a, Message, Renderer> From<MouseArea<'a, Message, Renderer>>
+    for Element<'a, Message, Renderer>
+where
+    Message: 'a,
+    Renderer: 'a + iced_native::Renderer,
+{
+    fn from(mouse_area: MouseArea<'a, Message, Renderer>) -> Element<'a, Message, Renderer> {
+        Element::new(mouse_area)
+    }
+}
@@ -0,0 +1,121 @@
+use crate::core::entities::{Stone, StoneColor};
+use crate::core::game::{MoveHistory, MoveKind};
+
+/// Stones of a loaded position revealed one at a time, in the order they were played. Stones
+/// captured along the way are never shown, so every step is a subset of the loaded position.
+#[derive(Debug, Clone)]
+pub struct Replay {
+    /// Stones without a move in the history (setup stones) come first and are shown at once
+    order: Vec<Stone>,
+    shown: usize,
+}
+
+impl Replay {
+    pub fn new(black_stones: &[Stone], white_stones: &[Stone], history: &MoveHistory) -> Self {
+        let stones: Vec<Stone> = black_stones.iter().chain(white_stones).copied().collect();
+        let mut played: Vec<Stone> = vec![];
+
+        for record in history.moves() {
+            if let MoveKind::Play(coords) = record.kind {
+                let stone = Stone {
+                    color: record.color,
+                    row: coords.row,
+                    col: coords.col,
+                };
+
+                if stones.contains(&stone) && !played.contains(&stone) {
+                    played.push(stone);
+                }
+            }
+        }
+
+        let mut order: Vec<Stone> = stones
+            .into_iter()
+            .filter(|stone| !played.contains(stone))
+            .collect();
+        let shown = order.len();
+        order.extend(played);
+
+        Self { order, shown }
+    }
+
+    /// Reveals the next stone, returns whether there was one
+    pub fn advance(&mut self) -> bool {
+        if self.is_done() {
+            return false;
+        }
+
+        self.shown += 1;
+        true
+    }
+
+    pub fn is_done(&self) -> bool {
+        self.shown >= self.order.len()
+    }
+
+    /// `(black, white)` stones shown so far
+    pub fn visible(&self) -> (Vec<Stone>, Vec<Stone>) {
+        self.order[..self.shown]
+            .iter()
+            .partition(|stone| stone.color == StoneColor::Black)
+    }
+
+    /// `(black, white)` stones of the loaded position
+    pub fn finish(mut self) -> (Vec<Stone>, Vec<Stone>) {
+        self.shown = self.order.len();
+        self.visible()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::entities::Coords;
+    use crate::core::game::MoveRecord;
+
+    fn stone(color: StoneColor, row: u8, col: u8) -> Stone {
+        Stone { color, row, col }
+    }
+
+    #[test]
+    fn stones_are_revealed_in_move_order_and_end_as_loaded() {
+        let mut history = MoveHistory::new(StoneColor::Black);
+        for (color, row, col) in [
+            (StoneColor::Black, 4, 4),
+            (StoneColor::White, 1, 2),
+            (StoneColor::Black, 1, 3),
+            (StoneColor::White, 6, 6),
+        ] {
+            history.push(MoveRecord {
+                color,
+                kind: MoveKind::Play(Coords::from(row, col)),
+                think_time: None,
+                captured: 0,
+                self_captured: 0,
+            });
+        }
+        // B1 was captured, and C3 is a setup stone
+        let black = vec![
+            stone(StoneColor::Black, 3, 3),
+            stone(StoneColor::Black, 4, 4),
+            stone(StoneColor::Black, 1, 3),
+        ];
+        let white = vec![stone(StoneColor::White, 6, 6)];
+
+        let mut replay = Replay::new(&black, &white, &history);
+        assert_eq!((vec![black[0]], vec![]), replay.visible());
+
+        assert!(replay.advance());
+        assert_eq!((vec![black[0], black[1]], vec![]), replay.visible());
+
+        let interrupted = replay.clone().finish();
+        while replay.advance() {}
+
+        assert!(replay.is_done());
+        assert_eq!(interrupted, replay.visible());
+        assert_eq!(
+            (vec![black[0], black[1], black[2]], vec![white[0]]),
+            interrupted
+        );
+    }
+}
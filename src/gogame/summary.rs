@@ -0,0 +1,81 @@
+use crate::core::clock::format_clock;
+use crate::core::theme::Theme;
+use crate::gogame::game_message::GameMessage;
+use iced_native::{Column, Container, Element, Text};
+use iced_tui::{Style, TuiRenderer};
+use std::time::Duration;
+
+/// What the end-game screen shows, put together once the game is over
+#[derive(Debug, Clone, PartialEq)]
+pub struct GameSummary {
+    pub result: String,
+    /// `(black, white)` stones each color captured
+    pub captures: (usize, usize),
+    /// `(black, white)` territory points, only when the engine estimated them
+    pub territory: Option<(usize, usize)>,
+    pub moves: usize,
+    pub duration: Duration,
+}
+
+impl GameSummary {
+    /// Lines of the summary, kept apart from the view so they can be checked in tests
+    pub fn lines(&self) -> Vec<String> {
+        let territory = match self.territory {
+            Some((black, white)) => format!("B {}  W {}", black, white),
+            None => "-".to_string(),
+        };
+
+        vec![
+            format!("Result:    {}", self.result),
+            format!("Captures:  B {}  W {}", self.captures.0, self.captures.1),
+            format!("Territory: {}", territory),
+            format!("Moves:     {}", self.moves),
+            format!("Duration:  {}", format_clock(self.duration)),
+        ]
+    }
+
+    pub fn view(&self, theme: &Theme) -> Element<'_, GameMessage, TuiRenderer> {
+        let column = self
+            .lines()
+            .into_iter()
+            .fold(
+                Column::new().push(Text::new("Game over").font(Style::default().bold())),
+                |column, line| column.push(Text::new(line)),
+            )
+            .push(
+                Text::new("Esc to review the game")
+                    .font(Style::default().fg(theme.disabled_fg_color)),
+            );
+
+        Container::new(column)
+            .style(Style::default().bg(theme.board_bg_hl_color))
+            .into()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn missing_territory_is_shown_as_a_dash() {
+        let summary = GameSummary {
+            result: "W+R".to_string(),
+            captures: (2, 7),
+            territory: None,
+            moves: 87,
+            duration: Duration::from_secs(754),
+        };
+
+        assert_eq!(
+            vec![
+                "Result:    W+R",
+                "Captures:  B 2  W 7",
+                "Territory: -",
+                "Moves:     87",
+                "Duration:  12:34",
+            ],
+            summary.lines()
+        );
+    }
+}
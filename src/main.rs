@@ -1,9 +1,46 @@
 mod core;
 mod gogame;
 
-use crate::gogame::GoGame;
+use crate::core::context::AppContext;
+use crate::core::{cli, logger, signals, title};
+use crate::gogame::{bench_render, GoGame};
 use iced_tui::Application;
+use std::process;
 
 fn main() {
+    let matches = cli::get_matches();
+
+    if let Some(frames) = matches.value_of("bench-render") {
+        if let Err(app_error) = bench_render(frames) {
+            eprintln!("{}", app_error.message);
+            process::exit(1);
+        }
+        return;
+    }
+
+    if let Err(app_error) = cli::check_interactive_terminal(&std::io::stdin(), &std::io::stdout()) {
+        eprintln!("{}", app_error.message);
+        process::exit(1);
+    }
+
+    logger::init_logger(
+        matches.value_of("debug-file"),
+        logger::get_logger_level_by_verbosity(matches.occurrences_of("v")),
+    )
+    .unwrap();
+
+    match AppContext::from_matches(&matches) {
+        Ok(context) => AppContext::install(context),
+        Err(app_error) => {
+            eprintln!("{}", app_error.message);
+            process::exit(1);
+        }
+    }
+
+    if let Err(app_error) = signals::install_terminal_restore() {
+        eprintln!("{}", app_error.message);
+    }
+
     GoGame::run();
+    title::restore_original(&mut std::io::stdout());
 }